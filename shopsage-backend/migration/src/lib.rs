@@ -3,6 +3,24 @@ pub use sea_orm_migration::prelude::*;
 mod m20250726_092311_create_initial_schema;
 mod m20250726_095152_fix_decimal_precision;
 mod m20250804_000000_create_sessions_table;
+mod m20250810_000000_create_auth_nonces_table;
+mod m20250812_000000_create_auth_sessions_table;
+mod m20250814_000000_add_is_admin_to_users;
+mod m20250816_000000_constrain_session_status;
+mod m20250818_000000_create_session_events_table;
+mod m20250820_000000_add_session_pricing;
+mod m20250822_000000_prevent_session_overlap;
+mod m20250824_000000_create_expert_time_off_table;
+mod m20250826_000000_create_notifications_table;
+mod m20250828_000000_add_expert_search_index;
+mod m20250830_000000_create_categories_tables;
+mod m20250901_000000_constrain_shopper_profiles;
+mod m20250903_000000_create_shopper_saved_experts_table;
+mod m20250905_000000_create_reviews_table;
+mod m20250907_000000_create_content_reports_table;
+mod m20250909_000000_add_expert_rating_aggregates;
+mod m20250911_000000_create_payments_table;
+mod m20250913_000000_add_session_payment_reference;
 
 pub struct Migrator;
 
@@ -13,6 +31,24 @@ impl MigratorTrait for Migrator {
             Box::new(m20250726_092311_create_initial_schema::Migration),
             Box::new(m20250726_095152_fix_decimal_precision::Migration),
             Box::new(m20250804_000000_create_sessions_table::Migration),
+            Box::new(m20250810_000000_create_auth_nonces_table::Migration),
+            Box::new(m20250812_000000_create_auth_sessions_table::Migration),
+            Box::new(m20250814_000000_add_is_admin_to_users::Migration),
+            Box::new(m20250816_000000_constrain_session_status::Migration),
+            Box::new(m20250818_000000_create_session_events_table::Migration),
+            Box::new(m20250820_000000_add_session_pricing::Migration),
+            Box::new(m20250822_000000_prevent_session_overlap::Migration),
+            Box::new(m20250824_000000_create_expert_time_off_table::Migration),
+            Box::new(m20250826_000000_create_notifications_table::Migration),
+            Box::new(m20250828_000000_add_expert_search_index::Migration),
+            Box::new(m20250830_000000_create_categories_tables::Migration),
+            Box::new(m20250901_000000_constrain_shopper_profiles::Migration),
+            Box::new(m20250903_000000_create_shopper_saved_experts_table::Migration),
+            Box::new(m20250905_000000_create_reviews_table::Migration),
+            Box::new(m20250907_000000_create_content_reports_table::Migration),
+            Box::new(m20250909_000000_add_expert_rating_aggregates::Migration),
+            Box::new(m20250911_000000_create_payments_table::Migration),
+            Box::new(m20250913_000000_add_session_payment_reference::Migration),
         ]
     }
 }
@@ -0,0 +1,52 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create auth_nonces table holding outstanding Sign-In With Solana challenges
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuthNonces::Table)
+                    .if_not_exists()
+                    .col(uuid(AuthNonces::Id).primary_key())
+                    .col(string_len(AuthNonces::WalletAddress, 80).not_null())
+                    .col(string_len(AuthNonces::Nonce, 64).not_null().unique_key())
+                    .col(text(AuthNonces::Message).not_null())
+                    .col(timestamp_with_time_zone(AuthNonces::ExpiresAt).not_null())
+                    .col(timestamp_with_time_zone(AuthNonces::CreatedAt).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_auth_nonces_expires_at")
+                    .table(AuthNonces::Table)
+                    .col(AuthNonces::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuthNonces::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuthNonces {
+    Table,
+    Id,
+    WalletAddress,
+    Nonce,
+    Message,
+    ExpiresAt,
+    CreatedAt,
+}
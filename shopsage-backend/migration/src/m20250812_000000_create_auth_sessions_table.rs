@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create auth_sessions table, one row per login holding the current refresh token hash
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuthSessions::Table)
+                    .if_not_exists()
+                    .col(uuid(AuthSessions::Id).primary_key())
+                    .col(uuid(AuthSessions::UserId).not_null())
+                    .col(string_len(AuthSessions::RefreshTokenHash, 64).not_null())
+                    .col(timestamp_with_time_zone(AuthSessions::ExpiresAt).not_null())
+                    .col(timestamp_with_time_zone_null(AuthSessions::LastUsedAt))
+                    .col(timestamp_with_time_zone_null(AuthSessions::RevokedAt))
+                    .col(string_null(AuthSessions::RevokedReason))
+                    .col(timestamp_with_time_zone(AuthSessions::CreatedAt).not_null())
+                    .col(timestamp_with_time_zone(AuthSessions::UpdatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_auth_sessions_user_id")
+                            .from(AuthSessions::Table, AuthSessions::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_auth_sessions_user_id")
+                    .table(AuthSessions::Table)
+                    .col(AuthSessions::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuthSessions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuthSessions {
+    Table,
+    Id,
+    UserId,
+    RefreshTokenHash,
+    ExpiresAt,
+    LastUsedAt,
+    RevokedAt,
+    RevokedReason,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
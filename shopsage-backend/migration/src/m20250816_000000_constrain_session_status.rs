@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Rows written before the state machine used the old "created" default
+        db.execute_unprepared(
+            "UPDATE sessions SET status = 'pending' WHERE status IS NULL OR status = 'created'",
+        )
+        .await?;
+
+        db.execute_unprepared("ALTER TABLE sessions ALTER COLUMN status SET DEFAULT 'pending'")
+            .await?;
+
+        // Same values as shopsage_session::SessionStatus plus the off-chain lifecycle states
+        db.execute_unprepared(
+            "ALTER TABLE sessions ADD CONSTRAINT chk_sessions_status CHECK (status IN (\
+             'pending', 'active', 'completed', 'cancelled', \
+             'scheduled', 'no_show', 'disputed', 'refunded'))",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("ALTER TABLE sessions DROP CONSTRAINT IF EXISTS chk_sessions_status")
+            .await?;
+        db.execute_unprepared("ALTER TABLE sessions ALTER COLUMN status SET DEFAULT 'created'")
+            .await?;
+
+        Ok(())
+    }
+}
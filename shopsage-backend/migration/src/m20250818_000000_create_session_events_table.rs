@@ -0,0 +1,85 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Append-only audit trail of session status changes
+        manager
+            .create_table(
+                Table::create()
+                    .table(SessionEvents::Table)
+                    .if_not_exists()
+                    .col(uuid(SessionEvents::Id).primary_key())
+                    .col(uuid(SessionEvents::SessionId).not_null())
+                    .col(uuid_null(SessionEvents::ActorId))
+                    .col(string_null(SessionEvents::OldStatus))
+                    .col(string(SessionEvents::NewStatus).not_null())
+                    .col(text_null(SessionEvents::Reason))
+                    .col(string_len_null(SessionEvents::ClientIp, 45))
+                    .col(string_len_null(SessionEvents::TxSignature, 88))
+                    .col(timestamp_with_time_zone(SessionEvents::CreatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_session_events_session_id")
+                            .from(SessionEvents::Table, SessionEvents::SessionId)
+                            .to(Sessions::Table, Sessions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_session_events_actor_id")
+                            .from(SessionEvents::Table, SessionEvents::ActorId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_session_events_session_id_created_at")
+                    .table(SessionEvents::Table)
+                    .col(SessionEvents::SessionId)
+                    .col(SessionEvents::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SessionEvents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionEvents {
+    Table,
+    Id,
+    SessionId,
+    ActorId,
+    OldStatus,
+    NewStatus,
+    Reason,
+    ClientIp,
+    TxSignature,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
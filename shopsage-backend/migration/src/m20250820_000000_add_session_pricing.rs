@@ -0,0 +1,83 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // How an expert charges: session_rate per session ("flat") or per minute ("per_minute")
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ExpertProfiles::Table)
+                    .add_column(
+                        string_len(ExpertProfiles::PricingModel, 16)
+                            .not_null()
+                            .default("flat"),
+                    )
+                    .add_column(
+                        json(ExpertProfiles::AllowedDurations)
+                            .not_null()
+                            .default(Expr::cust("'[15, 30, 60]'::json")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "ALTER TABLE expert_profiles ADD CONSTRAINT chk_expert_profiles_pricing_model \
+                 CHECK (pricing_model IN ('flat', 'per_minute'))",
+            )
+            .await?;
+
+        // Booked length and the server-side quote; null on sessions created before pricing
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .add_column(integer_null(Sessions::DurationMinutes))
+                    .add_column(decimal_len_null(Sessions::QuotedPrice, 10, 2))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .drop_column(Sessions::DurationMinutes)
+                    .drop_column(Sessions::QuotedPrice)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ExpertProfiles::Table)
+                    .drop_column(ExpertProfiles::PricingModel)
+                    .drop_column(ExpertProfiles::AllowedDurations)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExpertProfiles {
+    Table,
+    PricingModel,
+    AllowedDurations,
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    DurationMinutes,
+    QuotedPrice,
+}
@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // The booked window lives apart from start_time/end_time, which the state machine
+        // overwrites with the actual times once a session starts and ends
+        db.execute_unprepared(
+            "ALTER TABLE sessions \
+             ADD COLUMN booked_start timestamptz, \
+             ADD COLUMN booked_end timestamptz",
+        )
+        .await?;
+
+        // Until now start_time/end_time held the booking; only keep them as actual times for
+        // sessions that got past pending
+        db.execute_unprepared(
+            "UPDATE sessions SET booked_start = start_time, booked_end = end_time \
+             WHERE start_time IS NOT NULL AND end_time IS NOT NULL AND end_time > start_time",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE sessions SET start_time = NULL, end_time = NULL \
+             WHERE status IN ('scheduled', 'pending', 'cancelled', 'no_show')",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "ALTER TABLE sessions ADD CONSTRAINT chk_sessions_booked_window \
+             CHECK ((booked_start IS NULL AND booked_end IS NULL) OR booked_end > booked_start)",
+        )
+        .await?;
+
+        // Existing double bookings would make the constraint below fail. The first booking made
+        // keeps its slot; later unstarted ones are cancelled and the rest, which already took
+        // place, drop their booked window.
+        db.execute_unprepared(
+            "DO $$ \
+             DECLARE s RECORD; \
+             BEGIN \
+               FOR s IN SELECT id, expert_id, status, booked_start, booked_end, created_at \
+                 FROM sessions \
+                 WHERE booked_start IS NOT NULL AND status NOT IN ('cancelled', 'refunded') \
+                 ORDER BY created_at, id \
+               LOOP \
+                 IF EXISTS ( \
+                   SELECT 1 FROM sessions o \
+                   WHERE o.expert_id = s.expert_id \
+                     AND o.id <> s.id \
+                     AND o.booked_start IS NOT NULL \
+                     AND o.status NOT IN ('cancelled', 'refunded') \
+                     AND (o.created_at, o.id) < (s.created_at, s.id) \
+                     AND tstzrange(o.booked_start, o.booked_end, '[)') \
+                       && tstzrange(s.booked_start, s.booked_end, '[)') \
+                 ) THEN \
+                   IF s.status IN ('scheduled', 'pending') THEN \
+                     UPDATE sessions SET status = 'cancelled', updated_at = now() \
+                       WHERE id = s.id; \
+                     INSERT INTO session_events \
+                       (id, session_id, actor_id, old_status, new_status, reason, created_at) \
+                       VALUES (gen_random_uuid(), s.id, NULL, s.status, 'cancelled', \
+                         'overlapping booking', now()); \
+                   ELSE \
+                     UPDATE sessions SET booked_start = NULL, booked_end = NULL \
+                       WHERE id = s.id; \
+                   END IF; \
+                 END IF; \
+               END LOOP; \
+             END $$",
+        )
+        .await?;
+
+        // btree_gist lets the uuid equality and the range overlap share one GiST index
+        db.execute_unprepared("CREATE EXTENSION IF NOT EXISTS btree_gist")
+            .await?;
+
+        // An expert can't hold two live bookings at once; cancelled and refunded ones free the slot
+        db.execute_unprepared(
+            "ALTER TABLE sessions ADD CONSTRAINT excl_sessions_expert_no_overlap \
+             EXCLUDE USING gist (expert_id WITH =, tstzrange(booked_start, booked_end, '[)') WITH &&) \
+             WHERE (booked_start IS NOT NULL AND status NOT IN ('cancelled', 'refunded'))",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            "ALTER TABLE sessions DROP CONSTRAINT IF EXISTS excl_sessions_expert_no_overlap",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE sessions DROP CONSTRAINT IF EXISTS chk_sessions_booked_window",
+        )
+        .await?;
+
+        // Unstarted sessions go back to keeping their booking in start_time/end_time
+        db.execute_unprepared(
+            "UPDATE sessions SET start_time = booked_start, end_time = booked_end \
+             WHERE start_time IS NULL AND booked_start IS NOT NULL",
+        )
+        .await?;
+        db.execute_unprepared(
+            "ALTER TABLE sessions DROP COLUMN IF EXISTS booked_start, \
+             DROP COLUMN IF EXISTS booked_end",
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One-off or recurring blocks of time an expert can't be booked for
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExpertTimeOff::Table)
+                    .if_not_exists()
+                    .col(uuid(ExpertTimeOff::Id).primary_key())
+                    .col(uuid(ExpertTimeOff::UserId).not_null())
+                    .col(timestamp_with_time_zone(ExpertTimeOff::StartsAt).not_null())
+                    .col(timestamp_with_time_zone(ExpertTimeOff::EndsAt).not_null())
+                    .col(
+                        string_len(ExpertTimeOff::Timezone, 64)
+                            .not_null()
+                            .default("UTC"),
+                    )
+                    .col(
+                        string_len(ExpertTimeOff::Recurrence, 16)
+                            .not_null()
+                            .default("once"),
+                    )
+                    .col(timestamp_with_time_zone_null(ExpertTimeOff::RepeatUntil))
+                    .col(text_null(ExpertTimeOff::Reason))
+                    .col(timestamp_with_time_zone(ExpertTimeOff::CreatedAt).not_null())
+                    .col(timestamp_with_time_zone(ExpertTimeOff::UpdatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_expert_time_off_user_id")
+                            .from(ExpertTimeOff::Table, ExpertTimeOff::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .check(Expr::col(ExpertTimeOff::EndsAt).gt(Expr::col(ExpertTimeOff::StartsAt)))
+                    .check(Expr::col(ExpertTimeOff::Recurrence).is_in(["once", "weekly", "yearly"]))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_expert_time_off_user_id_starts_at")
+                    .table(ExpertTimeOff::Table)
+                    .col(ExpertTimeOff::UserId)
+                    .col(ExpertTimeOff::StartsAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExpertTimeOff::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExpertTimeOff {
+    Table,
+    Id,
+    UserId,
+    StartsAt,
+    EndsAt,
+    Timezone,
+    Recurrence,
+    RepeatUntil,
+    Reason,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Trigram similarity for typo-tolerant name and specialization matches
+        db.execute_unprepared("CREATE EXTENSION IF NOT EXISTS pg_trgm")
+            .await?;
+
+        // A STORED generated column can't read users.name, so the vector is kept up to date by
+        // triggers on both tables instead. It is deliberately left out of the SeaORM entity.
+        db.execute_unprepared(
+            "ALTER TABLE expert_profiles \
+             ADD COLUMN search_vector tsvector NOT NULL DEFAULT ''::tsvector",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE FUNCTION expert_profiles_search_vector(p_user_id uuid, p_specialization text, p_bio text) \
+             RETURNS tsvector LANGUAGE sql STABLE AS $$ \
+               SELECT setweight(to_tsvector('simple', coalesce((SELECT name FROM users WHERE id = p_user_id), '')), 'A') \
+                   || setweight(to_tsvector('english', coalesce(p_specialization, '')), 'A') \
+                   || setweight(to_tsvector('english', coalesce(p_bio, '')), 'B') \
+             $$",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE FUNCTION expert_profiles_search_vector_refresh() RETURNS trigger \
+             LANGUAGE plpgsql AS $$ \
+             BEGIN \
+               NEW.search_vector := expert_profiles_search_vector(NEW.user_id, NEW.specialization, NEW.bio); \
+               RETURN NEW; \
+             END $$",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER trg_expert_profiles_search_vector \
+             BEFORE INSERT OR UPDATE OF user_id, specialization, bio ON expert_profiles \
+             FOR EACH ROW EXECUTE FUNCTION expert_profiles_search_vector_refresh()",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE FUNCTION users_name_search_vector_refresh() RETURNS trigger \
+             LANGUAGE plpgsql AS $$ \
+             BEGIN \
+               UPDATE expert_profiles \
+                 SET search_vector = expert_profiles_search_vector(user_id, specialization, bio) \
+                 WHERE user_id = NEW.id; \
+               RETURN NULL; \
+             END $$",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER trg_users_name_search_vector \
+             AFTER UPDATE OF name ON users \
+             FOR EACH ROW WHEN (OLD.name IS DISTINCT FROM NEW.name) \
+             EXECUTE FUNCTION users_name_search_vector_refresh()",
+        )
+        .await?;
+
+        // Backfill existing experts
+        db.execute_unprepared(
+            "UPDATE expert_profiles \
+             SET search_vector = expert_profiles_search_vector(user_id, specialization, bio)",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE INDEX idx_expert_profiles_search_vector ON expert_profiles USING gin (search_vector)",
+        )
+        .await?;
+        db.execute_unprepared(
+            "CREATE INDEX idx_expert_profiles_specialization_trgm \
+             ON expert_profiles USING gin (specialization gin_trgm_ops)",
+        )
+        .await?;
+        db.execute_unprepared("CREATE INDEX idx_users_name_trgm ON users USING gin (name gin_trgm_ops)")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_users_name_trgm").await?;
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_expert_profiles_specialization_trgm")
+            .await?;
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_expert_profiles_search_vector")
+            .await?;
+        db.execute_unprepared("DROP TRIGGER IF EXISTS trg_users_name_search_vector ON users")
+            .await?;
+        db.execute_unprepared(
+            "DROP TRIGGER IF EXISTS trg_expert_profiles_search_vector ON expert_profiles",
+        )
+        .await?;
+        db.execute_unprepared("DROP FUNCTION IF EXISTS users_name_search_vector_refresh()")
+            .await?;
+        db.execute_unprepared("DROP FUNCTION IF EXISTS expert_profiles_search_vector_refresh()")
+            .await?;
+        db.execute_unprepared(
+            "DROP FUNCTION IF EXISTS expert_profiles_search_vector(uuid, text, text)",
+        )
+        .await?;
+        db.execute_unprepared("ALTER TABLE expert_profiles DROP COLUMN IF EXISTS search_vector")
+            .await?;
+
+        Ok(())
+    }
+}
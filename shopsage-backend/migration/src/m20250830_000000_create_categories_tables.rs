@@ -0,0 +1,200 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Specialization taxonomy; top-level categories have no parent
+        manager
+            .create_table(
+                Table::create()
+                    .table(Categories::Table)
+                    .if_not_exists()
+                    .col(uuid(Categories::Id).primary_key())
+                    .col(uuid_null(Categories::ParentId))
+                    .col(string_len(Categories::Slug, 64).not_null().unique_key())
+                    .col(string_len(Categories::Name, 128).not_null())
+                    .col(integer(Categories::SortOrder).not_null().default(0))
+                    .col(timestamp_with_time_zone(Categories::CreatedAt).not_null())
+                    .col(timestamp_with_time_zone(Categories::UpdatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_categories_parent_id")
+                            .from(Categories::Table, Categories::ParentId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_categories_parent_id")
+                    .table(Categories::Table)
+                    .col(Categories::ParentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExpertCategories::Table)
+                    .if_not_exists()
+                    .col(uuid(ExpertCategories::ExpertId).not_null())
+                    .col(uuid(ExpertCategories::CategoryId).not_null())
+                    .col(boolean(ExpertCategories::IsPrimary).not_null().default(false))
+                    .col(timestamp_with_time_zone(ExpertCategories::CreatedAt).not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(ExpertCategories::ExpertId)
+                            .col(ExpertCategories::CategoryId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_expert_categories_expert_id")
+                            .from(ExpertCategories::Table, ExpertCategories::ExpertId)
+                            .to(ExpertProfiles::Table, ExpertProfiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_expert_categories_category_id")
+                            .from(ExpertCategories::Table, ExpertCategories::CategoryId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_expert_categories_category_id")
+                    .table(ExpertCategories::Table)
+                    .col(ExpertCategories::CategoryId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+
+        // At most one primary category per expert
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX uq_expert_categories_primary \
+             ON expert_categories (expert_id) WHERE is_primary",
+        )
+        .await?;
+
+        // Seed taxonomy, shared by every environment
+        db.execute_unprepared(
+            "INSERT INTO categories (id, parent_id, slug, name, sort_order, created_at, updated_at) \
+             SELECT gen_random_uuid(), NULL, v.slug, v.name, v.sort_order, now(), now() \
+             FROM (VALUES \
+               ('fashion-style', 'Fashion & Style', 1), \
+               ('technology-gadgets', 'Technology & Gadgets', 2), \
+               ('beauty-skincare', 'Beauty & Skincare', 3), \
+               ('health-wellness', 'Health & Wellness', 4), \
+               ('home-living', 'Home & Living', 5) \
+             ) AS v(slug, name, sort_order)",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "INSERT INTO categories (id, parent_id, slug, name, sort_order, created_at, updated_at) \
+             SELECT gen_random_uuid(), p.id, v.slug, v.name, v.sort_order, now(), now() \
+             FROM (VALUES \
+               ('fashion-style', 'clothing', 'Clothing', 1), \
+               ('fashion-style', 'shoes', 'Shoes', 2), \
+               ('fashion-style', 'accessories', 'Accessories', 3), \
+               ('technology-gadgets', 'smartphones', 'Smartphones', 1), \
+               ('technology-gadgets', 'computers-laptops', 'Computers & Laptops', 2), \
+               ('technology-gadgets', 'gaming-electronics', 'Gaming & Electronics', 3), \
+               ('technology-gadgets', 'smart-home', 'Smart Home', 4), \
+               ('beauty-skincare', 'skincare', 'Skincare', 1), \
+               ('beauty-skincare', 'makeup', 'Makeup', 2), \
+               ('beauty-skincare', 'fragrance', 'Fragrance', 3), \
+               ('health-wellness', 'fitness', 'Fitness', 1), \
+               ('health-wellness', 'nutrition', 'Nutrition', 2), \
+               ('home-living', 'furniture', 'Furniture', 1), \
+               ('home-living', 'kitchen', 'Kitchen', 2) \
+             ) AS v(parent_slug, slug, name, sort_order) \
+             JOIN categories p ON p.slug = v.parent_slug",
+        )
+        .await?;
+
+        // Specializations that name no seeded category become top-level categories of their own
+        db.execute_unprepared(
+            "INSERT INTO categories (id, parent_id, slug, name, sort_order, created_at, updated_at) \
+             SELECT gen_random_uuid(), NULL, s.slug, s.name, 100, now(), now() \
+             FROM ( \
+               SELECT DISTINCT ON (slug) name, slug FROM ( \
+                 SELECT trim(ep.specialization) AS name, \
+                        trim(BOTH '-' FROM regexp_replace(lower(trim(ep.specialization)), '[^a-z0-9]+', '-', 'g')) AS slug \
+                 FROM expert_profiles ep \
+                 WHERE NOT EXISTS ( \
+                   SELECT 1 FROM categories c \
+                   WHERE position(lower(c.name) IN lower(ep.specialization)) > 0 \
+                 ) \
+               ) AS unmatched \
+               WHERE slug <> '' \
+             ) AS s \
+             ON CONFLICT (slug) DO NOTHING",
+        )
+        .await?;
+
+        // Link every expert to the most specific category named in their specialization,
+        // e.g. "Gaming & Electronics Advisor" -> gaming-electronics
+        db.execute_unprepared(
+            "INSERT INTO expert_categories (expert_id, category_id, is_primary, created_at) \
+             SELECT DISTINCT ON (ep.id) ep.id, c.id, true, now() \
+             FROM expert_profiles ep \
+             JOIN categories c ON position(lower(c.name) IN lower(ep.specialization)) > 0 \
+             ORDER BY ep.id, length(c.name) DESC",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ExpertCategories::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Categories::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Categories {
+    Table,
+    Id,
+    ParentId,
+    Slug,
+    Name,
+    SortOrder,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ExpertCategories {
+    Table,
+    ExpertId,
+    CategoryId,
+    IsPrimary,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ExpertProfiles {
+    Table,
+    Id,
+}
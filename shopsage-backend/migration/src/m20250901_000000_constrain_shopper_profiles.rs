@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Keep the oldest profile for users that ended up with more than one
+        db.execute_unprepared(
+            "DELETE FROM shopper_profiles sp USING shopper_profiles older \
+             WHERE sp.user_id = older.user_id \
+               AND (sp.created_at, sp.id) > (older.created_at, older.id)",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE UNIQUE INDEX IF NOT EXISTS uq_shopper_profiles_user_id \
+             ON shopper_profiles (user_id)",
+        )
+        .await?;
+
+        // Rows from before price ranges were validated
+        db.execute_unprepared(
+            "UPDATE shopper_profiles \
+             SET price_range_min = LEAST(price_range_min, price_range_max), \
+                 price_range_max = GREATEST(price_range_min, price_range_max) \
+             WHERE price_range_min > price_range_max",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "ALTER TABLE shopper_profiles ADD CONSTRAINT chk_shopper_profiles_price_range \
+             CHECK (price_range_min >= 0 AND price_range_min <= price_range_max)",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            "ALTER TABLE shopper_profiles DROP CONSTRAINT IF EXISTS chk_shopper_profiles_price_range",
+        )
+        .await?;
+        db.execute_unprepared("DROP INDEX IF EXISTS uq_shopper_profiles_user_id")
+            .await?;
+
+        Ok(())
+    }
+}
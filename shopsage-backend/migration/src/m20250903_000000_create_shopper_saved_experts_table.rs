@@ -0,0 +1,143 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Experts a shopper has saved; preferred ones are their favourites
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShopperSavedExperts::Table)
+                    .if_not_exists()
+                    .col(uuid(ShopperSavedExperts::ShopperProfileId).not_null())
+                    .col(uuid(ShopperSavedExperts::ExpertId).not_null())
+                    .col(boolean(ShopperSavedExperts::IsPreferred).not_null().default(false))
+                    .col(timestamp_with_time_zone(ShopperSavedExperts::CreatedAt).not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(ShopperSavedExperts::ShopperProfileId)
+                            .col(ShopperSavedExperts::ExpertId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_shopper_saved_experts_shopper_profile_id")
+                            .from(ShopperSavedExperts::Table, ShopperSavedExperts::ShopperProfileId)
+                            .to(ShopperProfiles::Table, ShopperProfiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_shopper_saved_experts_expert_id")
+                            .from(ShopperSavedExperts::Table, ShopperSavedExperts::ExpertId)
+                            .to(ExpertProfiles::Table, ExpertProfiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_shopper_saved_experts_expert_id")
+                    .table(ShopperSavedExperts::Table)
+                    .col(ShopperSavedExperts::ExpertId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+
+        // Copy the JSON arrays across. Entries hold either expert profile ids or expert user ids
+        // (the seeders used the latter); anything that doesn't resolve to an expert is dropped.
+        db.execute_unprepared(
+            "INSERT INTO shopper_saved_experts (shopper_profile_id, expert_id, is_preferred, created_at) \
+             SELECT sp.id, ep.id, bool_or(entry.preferred), now() \
+             FROM shopper_profiles sp \
+             CROSS JOIN LATERAL ( \
+               SELECT value, false AS preferred FROM json_array_elements_text( \
+                 CASE WHEN json_typeof(sp.saved_experts) = 'array' \
+                 THEN sp.saved_experts ELSE '[]'::json END) \
+               UNION ALL \
+               SELECT value, true AS preferred FROM json_array_elements_text( \
+                 CASE WHEN json_typeof(sp.preferred_experts) = 'array' \
+                 THEN sp.preferred_experts ELSE '[]'::json END) \
+             ) AS entry \
+             JOIN expert_profiles ep \
+               ON ep.id::text = entry.value OR ep.user_id::text = entry.value \
+             GROUP BY sp.id, ep.id \
+             ON CONFLICT DO NOTHING",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShopperProfiles::Table)
+                    .drop_column(ShopperProfiles::SavedExperts)
+                    .drop_column(ShopperProfiles::PreferredExperts)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShopperProfiles::Table)
+                    .add_column(json(ShopperProfiles::PreferredExperts).not_null().default("[]"))
+                    .add_column(json(ShopperProfiles::SavedExperts).not_null().default("[]"))
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            "UPDATE shopper_profiles sp SET \
+               saved_experts = saved.ids, \
+               preferred_experts = saved.preferred_ids \
+             FROM ( \
+               SELECT shopper_profile_id, \
+                 json_agg(expert_id::text) AS ids, \
+                 COALESCE(json_agg(expert_id::text) FILTER (WHERE is_preferred), '[]'::json) \
+                   AS preferred_ids \
+               FROM shopper_saved_experts \
+               GROUP BY shopper_profile_id \
+             ) AS saved \
+             WHERE saved.shopper_profile_id = sp.id",
+        )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().table(ShopperSavedExperts::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ShopperSavedExperts {
+    Table,
+    ShopperProfileId,
+    ExpertId,
+    IsPreferred,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ShopperProfiles {
+    Table,
+    Id,
+    SavedExperts,
+    PreferredExperts,
+}
+
+#[derive(DeriveIden)]
+enum ExpertProfiles {
+    Table,
+    Id,
+}
@@ -0,0 +1,158 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One review per completed session, written by its shopper; the expert may reply
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reviews::Table)
+                    .if_not_exists()
+                    .col(uuid(Reviews::Id).primary_key())
+                    .col(uuid(Reviews::SessionId).not_null().unique_key())
+                    .col(uuid(Reviews::ExpertId).not_null())
+                    .col(uuid(Reviews::ShopperId).not_null())
+                    .col(small_integer(Reviews::Rating).not_null())
+                    .col(text(Reviews::Comment).not_null().default(""))
+                    .col(text_null(Reviews::ExpertReply))
+                    .col(timestamp_with_time_zone_null(Reviews::RepliedAt))
+                    .col(timestamp_with_time_zone(Reviews::CreatedAt).not_null())
+                    .col(timestamp_with_time_zone(Reviews::UpdatedAt).not_null())
+                    .check(Expr::col(Reviews::Rating).between(1, 5))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_reviews_session_id")
+                            .from(Reviews::Table, Reviews::SessionId)
+                            .to(Sessions::Table, Sessions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_reviews_expert_id")
+                            .from(Reviews::Table, Reviews::ExpertId)
+                            .to(ExpertProfiles::Table, ExpertProfiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_reviews_shopper_id")
+                            .from(Reviews::Table, Reviews::ShopperId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Backs the newest-first listing of an expert's reviews
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reviews_expert_id_created_at")
+                    .table(Reviews::Table)
+                    .col(Reviews::ExpertId)
+                    .col(Reviews::CreatedAt)
+                    .col(Reviews::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Reports of abusive or fake reviews, one per reporter
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReviewReports::Table)
+                    .if_not_exists()
+                    .col(uuid(ReviewReports::Id).primary_key())
+                    .col(uuid(ReviewReports::ReviewId).not_null())
+                    .col(uuid(ReviewReports::ReporterId).not_null())
+                    .col(text_null(ReviewReports::Reason))
+                    .col(timestamp_with_time_zone(ReviewReports::CreatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_review_reports_review_id")
+                            .from(ReviewReports::Table, ReviewReports::ReviewId)
+                            .to(Reviews::Table, Reviews::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_review_reports_reporter_id")
+                            .from(ReviewReports::Table, ReviewReports::ReporterId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("uq_review_reports_review_id_reporter_id")
+                    .table(ReviewReports::Table)
+                    .col(ReviewReports::ReviewId)
+                    .col(ReviewReports::ReporterId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReviewReports::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Reviews::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reviews {
+    Table,
+    Id,
+    SessionId,
+    ExpertId,
+    ShopperId,
+    Rating,
+    Comment,
+    ExpertReply,
+    RepliedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ReviewReports {
+    Table,
+    Id,
+    ReviewId,
+    ReporterId,
+    Reason,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ExpertProfiles {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,321 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Reports of user-generated content awaiting moderation. A report without a reporter was
+        // raised by the content filter.
+        manager
+            .create_table(
+                Table::create()
+                    .table(ContentReports::Table)
+                    .if_not_exists()
+                    .col(uuid(ContentReports::Id).primary_key())
+                    .col(string_len(ContentReports::ContentType, 32).not_null())
+                    .col(uuid(ContentReports::ContentId).not_null())
+                    .col(uuid_null(ContentReports::ReporterId))
+                    .col(text_null(ContentReports::Reason))
+                    .col(string_len(ContentReports::Status, 16).not_null().default("open"))
+                    .col(uuid_null(ContentReports::ResolvedBy))
+                    .col(timestamp_with_time_zone_null(ContentReports::ResolvedAt))
+                    .col(timestamp_with_time_zone(ContentReports::CreatedAt).not_null())
+                    .check(Expr::col(ContentReports::ContentType).is_in(["review", "expert_bio"]))
+                    .check(Expr::col(ContentReports::Status).is_in(["open", "resolved", "dismissed"]))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_content_reports_reporter_id")
+                            .from(ContentReports::Table, ContentReports::ReporterId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_content_reports_resolved_by")
+                            .from(ContentReports::Table, ContentReports::ResolvedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One report per reporter and piece of content; filter reports have no reporter
+        manager
+            .create_index(
+                Index::create()
+                    .name("uq_content_reports_content_reporter")
+                    .table(ContentReports::Table)
+                    .col(ContentReports::ContentType)
+                    .col(ContentReports::ContentId)
+                    .col(ContentReports::ReporterId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Backs the moderation queue, oldest open reports first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_content_reports_status_created_at")
+                    .table(ContentReports::Table)
+                    .col(ContentReports::Status)
+                    .col(ContentReports::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+
+        db.execute_unprepared(
+            "INSERT INTO content_reports (id, content_type, content_id, reporter_id, reason, status, created_at) \
+             SELECT id, 'review', review_id, reporter_id, reason, 'open', created_at FROM review_reports",
+        )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().table(ReviewReports::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reviews::Table)
+                    .add_column(timestamp_with_time_zone_null(Reviews::HiddenAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ExpertProfiles::Table)
+                    .add_column(timestamp_with_time_zone_null(ExpertProfiles::BioHiddenAt))
+                    // Hidden by the content filter rather than a moderator
+                    .add_column(boolean(ExpertProfiles::BioHeld).not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(timestamp_with_time_zone_null(Users::BannedAt))
+                    .add_column(text_null(Users::BanReason))
+                    .to_owned(),
+            )
+            .await?;
+
+        // A hidden bio must not make its expert findable by the hidden text
+        db.execute_unprepared(
+            "CREATE OR REPLACE FUNCTION expert_profiles_search_vector_refresh() RETURNS trigger \
+             LANGUAGE plpgsql AS $$ \
+             BEGIN \
+               NEW.search_vector := expert_profiles_search_vector(NEW.user_id, NEW.specialization, \
+                 CASE WHEN NEW.bio_hidden_at IS NULL THEN NEW.bio END); \
+               RETURN NEW; \
+             END $$",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE OR REPLACE FUNCTION users_name_search_vector_refresh() RETURNS trigger \
+             LANGUAGE plpgsql AS $$ \
+             BEGIN \
+               UPDATE expert_profiles \
+                 SET search_vector = expert_profiles_search_vector(user_id, specialization, \
+                   CASE WHEN bio_hidden_at IS NULL THEN bio END) \
+                 WHERE user_id = NEW.id; \
+               RETURN NULL; \
+             END $$",
+        )
+        .await?;
+
+        db.execute_unprepared("DROP TRIGGER trg_expert_profiles_search_vector ON expert_profiles")
+            .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER trg_expert_profiles_search_vector \
+             BEFORE INSERT OR UPDATE OF user_id, specialization, bio, bio_hidden_at ON expert_profiles \
+             FOR EACH ROW EXECUTE FUNCTION expert_profiles_search_vector_refresh()",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP TRIGGER trg_expert_profiles_search_vector ON expert_profiles")
+            .await?;
+
+        db.execute_unprepared(
+            "CREATE TRIGGER trg_expert_profiles_search_vector \
+             BEFORE INSERT OR UPDATE OF user_id, specialization, bio ON expert_profiles \
+             FOR EACH ROW EXECUTE FUNCTION expert_profiles_search_vector_refresh()",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE OR REPLACE FUNCTION expert_profiles_search_vector_refresh() RETURNS trigger \
+             LANGUAGE plpgsql AS $$ \
+             BEGIN \
+               NEW.search_vector := expert_profiles_search_vector(NEW.user_id, NEW.specialization, NEW.bio); \
+               RETURN NEW; \
+             END $$",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE OR REPLACE FUNCTION users_name_search_vector_refresh() RETURNS trigger \
+             LANGUAGE plpgsql AS $$ \
+             BEGIN \
+               UPDATE expert_profiles \
+                 SET search_vector = expert_profiles_search_vector(user_id, specialization, bio) \
+                 WHERE user_id = NEW.id; \
+               RETURN NULL; \
+             END $$",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::BannedAt)
+                    .drop_column(Users::BanReason)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ExpertProfiles::Table)
+                    .drop_column(ExpertProfiles::BioHiddenAt)
+                    .drop_column(ExpertProfiles::BioHeld)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Bring hidden bios back into the search vector
+        db.execute_unprepared(
+            "UPDATE expert_profiles \
+             SET search_vector = expert_profiles_search_vector(user_id, specialization, bio)",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reviews::Table)
+                    .drop_column(Reviews::HiddenAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReviewReports::Table)
+                    .if_not_exists()
+                    .col(uuid(ReviewReports::Id).primary_key())
+                    .col(uuid(ReviewReports::ReviewId).not_null())
+                    .col(uuid(ReviewReports::ReporterId).not_null())
+                    .col(text_null(ReviewReports::Reason))
+                    .col(timestamp_with_time_zone(ReviewReports::CreatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_review_reports_review_id")
+                            .from(ReviewReports::Table, ReviewReports::ReviewId)
+                            .to(Reviews::Table, Reviews::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_review_reports_reporter_id")
+                            .from(ReviewReports::Table, ReviewReports::ReporterId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("uq_review_reports_review_id_reporter_id")
+                    .table(ReviewReports::Table)
+                    .col(ReviewReports::ReviewId)
+                    .col(ReviewReports::ReporterId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only user reports of reviews that still exist fit the old table
+        db.execute_unprepared(
+            "INSERT INTO review_reports (id, review_id, reporter_id, reason, created_at) \
+             SELECT cr.id, cr.content_id, cr.reporter_id, cr.reason, cr.created_at \
+             FROM content_reports cr JOIN reviews r ON r.id = cr.content_id \
+             WHERE cr.content_type = 'review' AND cr.reporter_id IS NOT NULL",
+        )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().table(ContentReports::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ContentReports {
+    Table,
+    Id,
+    ContentType,
+    ContentId,
+    ReporterId,
+    Reason,
+    Status,
+    ResolvedBy,
+    ResolvedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ReviewReports {
+    Table,
+    Id,
+    ReviewId,
+    ReporterId,
+    Reason,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Reviews {
+    Table,
+    Id,
+    HiddenAt,
+}
+
+#[derive(DeriveIden)]
+enum ExpertProfiles {
+    Table,
+    BioHiddenAt,
+    BioHeld,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    BannedAt,
+    BanReason,
+}
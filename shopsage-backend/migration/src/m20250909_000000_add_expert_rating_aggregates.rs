@@ -0,0 +1,85 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Visible review count and star total behind `rating`, plus the Bayesian average experts
+        // are ranked by. The score defaults to the default prior mean, which is what an expert
+        // without reviews gets.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ExpertProfiles::Table)
+                    .add_column(integer(ExpertProfiles::RatingCount).not_null().default(0))
+                    .add_column(integer(ExpertProfiles::RatingSum).not_null().default(0))
+                    .add_column(
+                        decimal_len(ExpertProfiles::RatingScore, 3, 2)
+                            .not_null()
+                            .default(Expr::cust("3.50")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Score existing reviews with the default prior (mean 3.5, weight 5); `recompute-stats`
+        // rescores them under a configured one
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE expert_profiles ep \
+                 SET rating_count = r.count, \
+                     rating_sum = r.sum, \
+                     rating = ROUND(r.sum::numeric / r.count, 2), \
+                     rating_score = ROUND((r.sum + 3.5 * 5) / (r.count + 5), 2) \
+                 FROM ( \
+                   SELECT expert_id, COUNT(*)::int AS count, SUM(rating)::int AS sum \
+                   FROM reviews WHERE hidden_at IS NULL GROUP BY expert_id \
+                 ) r \
+                 WHERE r.expert_id = ep.id",
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_expert_profiles_rating_score")
+                    .table(ExpertProfiles::Table)
+                    .col(ExpertProfiles::RatingScore)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_expert_profiles_rating_score")
+                    .table(ExpertProfiles::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ExpertProfiles::Table)
+                    .drop_column(ExpertProfiles::RatingCount)
+                    .drop_column(ExpertProfiles::RatingSum)
+                    .drop_column(ExpertProfiles::RatingScore)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ExpertProfiles {
+    Table,
+    RatingCount,
+    RatingSum,
+    RatingScore,
+}
@@ -0,0 +1,139 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // On-chain payments for sessions. Wallets are copied from the users at payment time so the
+        // record keeps pointing at the accounts that were actually debited and credited.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Payments::Table)
+                    .if_not_exists()
+                    .col(uuid(Payments::Id).primary_key())
+                    .col(uuid(Payments::SessionId).not_null())
+                    .col(uuid(Payments::PayerId).not_null())
+                    .col(uuid(Payments::PayeeId).not_null())
+                    .col(string_len(Payments::PayerWallet, 44).not_null())
+                    .col(string_len(Payments::PayeeWallet, 44).not_null())
+                    .col(big_integer(Payments::Lamports).not_null())
+                    .col(string_len(Payments::Currency, 8).not_null().default("SOL"))
+                    .col(string_len(Payments::TxSignature, 88).not_null().unique_key())
+                    .col(string_len(Payments::Status, 16).not_null().default("pending"))
+                    .col(text_null(Payments::FailureReason))
+                    .col(timestamp_with_time_zone_null(Payments::ConfirmedAt))
+                    .col(timestamp_with_time_zone(Payments::CreatedAt).not_null())
+                    .col(timestamp_with_time_zone(Payments::UpdatedAt).not_null())
+                    .check(Expr::col(Payments::Lamports).gt(0))
+                    .check(Expr::col(Payments::Currency).is_in(["SOL"]))
+                    .check(Expr::col(Payments::Status).is_in(["pending", "confirmed", "failed"]))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payments_session_id")
+                            .from(Payments::Table, Payments::SessionId)
+                            .to(Sessions::Table, Sessions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payments_payer_id")
+                            .from(Payments::Table, Payments::PayerId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payments_payee_id")
+                            .from(Payments::Table, Payments::PayeeId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payments_session_id")
+                    .table(Payments::Table)
+                    .col(Payments::SessionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Payment history lists both what a user paid and what they were paid
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payments_payer_id_created_at")
+                    .table(Payments::Table)
+                    .col(Payments::PayerId)
+                    .col(Payments::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payments_payee_id_created_at")
+                    .table(Payments::Table)
+                    .col(Payments::PayeeId)
+                    .col(Payments::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // A session is paid for at most once, however many transactions are submitted for it
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE UNIQUE INDEX uq_payments_session_id_confirmed \
+                 ON payments (session_id) WHERE status = 'confirmed'",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Payments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Payments {
+    Table,
+    Id,
+    SessionId,
+    PayerId,
+    PayeeId,
+    PayerWallet,
+    PayeeWallet,
+    Lamports,
+    Currency,
+    TxSignature,
+    Status,
+    FailureReason,
+    ConfirmedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
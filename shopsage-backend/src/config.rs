@@ -1,37 +1,215 @@
 use std::env;
+use std::net::IpAddr;
+
+use crate::services::ratings::RatingPrior;
+use crate::services::recommendations::RecommendationWeights;
+use crate::services::solana::SHOPSAGE_PAYMENT_PROGRAM_ID;
+
+/// Placeholder treasury used when `PLATFORM_TREASURY_WALLET` is unset; only acceptable in
+/// development
+pub const DEFAULT_PLATFORM_TREASURY: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+
+/// Placeholder secret used when `JWT_SECRET` is unset; only acceptable in development
+pub const DEFAULT_JWT_SECRET: &str = "your-secret-key";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+  #[error("Environment variable error: {0}")]
+  VarError(#[from] env::VarError),
+  #[error("JWT_SECRET must be set to a non-default value when APP_ENV is '{0}'")]
+  InsecureJwtSecret(String),
+  #[error("PLATFORM_TREASURY_WALLET must be set to a non-default address when APP_ENV is '{0}'")]
+  InsecureTreasuryWallet(String),
+  #[error("Invalid JWT_PREVIOUS_KEYS entry '{0}', expected 'kid:secret'")]
+  InvalidVerificationKey(String),
+  #[error("Invalid RECOMMENDATION_WEIGHTS entry '{0}', expected 'factor=weight'")]
+  InvalidRecommendationWeight(String),
+  #[error("Invalid RATING_PRIOR entry '{0}', expected 'mean=1..5' or 'weight=0..'")]
+  InvalidRatingPrior(String),
+  #[error("{0} must be a base58-encoded Solana address")]
+  InvalidSolanaAddress(&'static str),
+  #[error("Invalid TRUSTED_PROXIES entry '{0}', expected an IP address")]
+  InvalidTrustedProxy(String),
+}
 
 #[derive(Clone, Debug)]
 pub struct Config {
+  pub app_env: String,
   pub database_url: String,
   pub redis_url: String,
   pub jwt_secret: String,
+  pub jwt_key_id: String,
+  /// Retired signing keys, as `(kid, secret)`, still accepted for verification
+  pub jwt_previous_keys: Vec<(String, String)>,
+  pub jwt_algorithm: String,
+  pub jwt_ttl_secs: i64,
+  pub jwt_issuer: String,
+  pub jwt_audience: String,
+  pub refresh_token_ttl_secs: i64,
   pub solana_rpc_url: String,
+  /// Wallet that receives the platform's share of session payments
+  pub platform_treasury: String,
+  /// Deployment of `shopsage_payment` that session payments may go through
+  pub payment_program_id: String,
   pub port: u16,
+  pub siws_domain: String,
+  pub siws_nonce_ttl_secs: i64,
+  /// Expert recommendation scoring weights, tunable through `RECOMMENDATION_WEIGHTS`
+  pub recommendation_weights: RecommendationWeights,
+  /// Word list for the content filter, replacing the built-in one
+  pub content_filter_wordlist: Option<String>,
+  /// Prior of the smoothed expert rating, tunable through `RATING_PRIOR`
+  pub rating_prior: RatingPrior,
+  /// Shared secret of the payment webhook; the webhook is disabled without one
+  pub payment_webhook_secret: Option<String>,
+  /// Reverse proxies whose `X-Forwarded-For` and `X-Real-IP` headers are believed
+  pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl Config {
-  pub fn from_env() -> Result<Self, env::VarError> {
+  pub fn from_env() -> Result<Self, ConfigError> {
     dotenvy::dotenv().ok();
 
-    Ok(Config {
+    let config = Config {
+      app_env: env::var("APP_ENV").unwrap_or_else(|_| "development".to_string()),
       database_url: env::var("DATABASE_URL").or_else(|_| {
         let db_host = env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string());
         let db_name = env::var("DB_NAME").unwrap_or_else(|_| "shopsage".to_string());
         let db_user = env::var("DB_USER").unwrap_or_else(|_| "postgres".to_string());
         let db_password = env::var("DB_PASSWORD").unwrap_or_else(|_| "".to_string());
-        Ok(format!(
+        Ok::<_, env::VarError>(format!(
           "postgresql://{}:{}@{}/{}",
           db_user, db_password, db_host, db_name
         ))
       })?,
       redis_url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string()),
-      jwt_secret: env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string()),
+      jwt_secret: env::var("JWT_SECRET").unwrap_or_else(|_| DEFAULT_JWT_SECRET.to_string()),
+      jwt_key_id: env::var("JWT_KEY_ID").unwrap_or_else(|_| "primary".to_string()),
+      jwt_previous_keys: Self::parse_previous_keys(
+        &env::var("JWT_PREVIOUS_KEYS").unwrap_or_default(),
+      )?,
+      jwt_algorithm: env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string()),
+      jwt_ttl_secs: env::var("JWT_TTL_SECS")
+        .unwrap_or_else(|_| "900".to_string())
+        .parse()
+        .unwrap_or(900),
+      jwt_issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "shopsage-backend".to_string()),
+      jwt_audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| "shopsage-app".to_string()),
+      refresh_token_ttl_secs: env::var("REFRESH_TOKEN_TTL_SECS")
+        .unwrap_or_else(|_| "2592000".to_string())
+        .parse()
+        .unwrap_or(2592000),
       solana_rpc_url: env::var("SOLANA_RPC_URL")
         .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+      platform_treasury: Self::solana_address(
+        "PLATFORM_TREASURY_WALLET",
+        DEFAULT_PLATFORM_TREASURY,
+      )?,
+      payment_program_id: Self::solana_address("PAYMENT_PROGRAM_ID", SHOPSAGE_PAYMENT_PROGRAM_ID)?,
       port: env::var("PORT")
         .unwrap_or_else(|_| "3001".to_string())
         .parse()
         .unwrap_or(3001),
-    })
+      siws_domain: env::var("SIWS_DOMAIN").unwrap_or_else(|_| "shopsage.app".to_string()),
+      siws_nonce_ttl_secs: env::var("SIWS_NONCE_TTL_SECS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300),
+      recommendation_weights: RecommendationWeights::from_spec(
+        &env::var("RECOMMENDATION_WEIGHTS").unwrap_or_default(),
+      )
+      .map_err(ConfigError::InvalidRecommendationWeight)?,
+      content_filter_wordlist: env::var("CONTENT_FILTER_WORDLIST").ok(),
+      rating_prior: RatingPrior::from_spec(&env::var("RATING_PRIOR").unwrap_or_default())
+        .map_err(ConfigError::InvalidRatingPrior)?,
+      payment_webhook_secret: env::var("PAYMENT_WEBHOOK_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty()),
+      trusted_proxies: Self::parse_trusted_proxies(
+        &env::var("TRUSTED_PROXIES").unwrap_or_default(),
+      )?,
+    };
+
+    if !config.is_development() && config.jwt_secret == DEFAULT_JWT_SECRET {
+      return Err(ConfigError::InsecureJwtSecret(config.app_env));
+    }
+    if !config.is_development() && config.platform_treasury == DEFAULT_PLATFORM_TREASURY {
+      return Err(ConfigError::InsecureTreasuryWallet(config.app_env));
+    }
+
+    Ok(config)
+  }
+
+  pub fn is_development(&self) -> bool {
+    matches!(
+      self.app_env.as_str(),
+      "development" | "dev" | "local" | "test"
+    )
+  }
+
+  fn solana_address(var: &'static str, default: &str) -> Result<String, ConfigError> {
+    let address = env::var(var).unwrap_or_else(|_| default.to_string());
+    match bs58::decode(&address).into_vec() {
+      Ok(bytes) if bytes.len() == 32 => Ok(address),
+      _ => Err(ConfigError::InvalidSolanaAddress(var)),
+    }
+  }
+
+  fn parse_trusted_proxies(raw: &str) -> Result<Vec<IpAddr>, ConfigError> {
+    raw
+      .split(',')
+      .map(str::trim)
+      .filter(|entry| !entry.is_empty())
+      .map(|entry| {
+        entry
+          .parse()
+          .map_err(|_| ConfigError::InvalidTrustedProxy(entry.to_string()))
+      })
+      .collect()
+  }
+
+  fn parse_previous_keys(raw: &str) -> Result<Vec<(String, String)>, ConfigError> {
+    raw
+      .split(',')
+      .map(str::trim)
+      .filter(|entry| !entry.is_empty())
+      .map(|entry| match entry.split_once(':') {
+        Some((kid, secret)) if !kid.is_empty() && !secret.is_empty() => {
+          Ok((kid.to_string(), secret.to_string()))
+        }
+        _ => Err(ConfigError::InvalidVerificationKey(entry.to_string())),
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+impl Config {
+  /// Development defaults without touching the process environment
+  pub fn for_tests() -> Self {
+    Config {
+      app_env: "test".to_string(),
+      database_url: "postgresql://postgres:@localhost/shopsage_test".to_string(),
+      redis_url: "redis://localhost:6379".to_string(),
+      jwt_secret: DEFAULT_JWT_SECRET.to_string(),
+      jwt_key_id: "primary".to_string(),
+      jwt_previous_keys: vec![],
+      jwt_algorithm: "HS256".to_string(),
+      jwt_ttl_secs: 900,
+      jwt_issuer: "shopsage-backend".to_string(),
+      jwt_audience: "shopsage-app".to_string(),
+      refresh_token_ttl_secs: 2592000,
+      solana_rpc_url: "https://api.devnet.solana.com".to_string(),
+      platform_treasury: DEFAULT_PLATFORM_TREASURY.to_string(),
+      payment_program_id: SHOPSAGE_PAYMENT_PROGRAM_ID.to_string(),
+      port: 3001,
+      siws_domain: "shopsage.app".to_string(),
+      siws_nonce_ttl_secs: 300,
+      recommendation_weights: RecommendationWeights::default(),
+      content_filter_wordlist: None,
+      rating_prior: RatingPrior::default(),
+      payment_webhook_secret: None,
+      trusted_proxies: vec![],
+    }
   }
 }
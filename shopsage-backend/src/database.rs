@@ -28,6 +28,12 @@ pub enum DatabaseError {
   SessionNotFound,
   #[error("Payment not found")]
   PaymentNotFound,
+  #[error("Shopper profile not found")]
+  ShopperProfileNotFound,
+  #[error("Shopper profile already exists")]
+  ShopperProfileExists,
+  #[error("Price range must be non-negative with priceRangeMin no greater than priceRangeMax")]
+  InvalidPriceRange,
 }
 
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
@@ -0,0 +1,23 @@
+//! `SeaORM` Entity for auth_nonces table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "auth_nonces")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub wallet_address: String,
+  #[sea_orm(unique)]
+  pub nonce: String,
+  #[sea_orm(column_type = "Text")]
+  pub message: String,
+  pub expires_at: DateTimeWithTimeZone,
+  pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,50 @@
+//! `SeaORM` Entity for categories table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "categories")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub parent_id: Option<Uuid>,
+  #[sea_orm(unique)]
+  pub slug: String,
+  pub name: String,
+  pub sort_order: i32,
+  pub created_at: DateTimeWithTimeZone,
+  pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "Entity",
+    from = "Column::ParentId",
+    to = "Column::Id",
+    on_update = "NoAction",
+    on_delete = "Restrict"
+  )]
+  SelfRef,
+  #[sea_orm(has_many = "super::expert_categories::Entity")]
+  ExpertCategories,
+}
+
+impl Related<super::expert_categories::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ExpertCategories.def()
+  }
+}
+
+impl Related<super::expert_profiles::Entity> for Entity {
+  fn to() -> RelationDef {
+    super::expert_categories::Relation::ExpertProfiles.def()
+  }
+
+  fn via() -> Option<RelationDef> {
+    Some(super::expert_categories::Relation::Categories.def().rev())
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,42 @@
+//! `SeaORM` Entity for content_reports table
+
+use super::sea_orm_active_enums::{ContentType, ReportStatus};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "content_reports")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub content_type: ContentType,
+  pub content_id: Uuid,
+  /// `None` when the content filter raised the report
+  pub reporter_id: Option<Uuid>,
+  #[sea_orm(column_type = "Text")]
+  pub reason: Option<String>,
+  pub status: ReportStatus,
+  pub resolved_by: Option<Uuid>,
+  pub resolved_at: Option<DateTimeWithTimeZone>,
+  pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::ReporterId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Reporter,
+}
+
+impl Related<super::users::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Reporter.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,49 @@
+//! `SeaORM` Entity for expert_categories table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "expert_categories")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub expert_id: Uuid,
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub category_id: Uuid,
+  pub is_primary: bool,
+  pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::expert_profiles::Entity",
+    from = "Column::ExpertId",
+    to = "super::expert_profiles::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  ExpertProfiles,
+  #[sea_orm(
+    belongs_to = "super::categories::Entity",
+    from = "Column::CategoryId",
+    to = "super::categories::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Categories,
+}
+
+impl Related<super::expert_profiles::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ExpertProfiles.def()
+  }
+}
+
+impl Related<super::categories::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Categories.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
+use super::sea_orm_active_enums::PricingModel;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -12,14 +13,28 @@ pub struct Model {
   pub specialization: String,
   #[sea_orm(column_type = "Text")]
   pub bio: String,
+  /// Set while moderation keeps the bio off public pages and out of search
+  pub bio_hidden_at: Option<DateTimeWithTimeZone>,
+  /// Whether the content filter, not a moderator, hid the bio; a passing rewrite shows it again
+  pub bio_held: bool,
   #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
   pub session_rate: Decimal,
+  /// Plain average of the visible reviews
   #[sea_orm(column_type = "Decimal(Some((3, 2)))")]
   pub rating: Decimal,
+  pub rating_count: i32,
+  /// Total stars across the visible reviews
+  pub rating_sum: i32,
+  /// Bayesian average experts are ranked by, see `RatingService`
+  #[sea_orm(column_type = "Decimal(Some((3, 2)))")]
+  pub rating_score: Decimal,
   pub total_consultations: i32,
   pub is_verified: bool,
   pub is_online: bool,
   pub profile_image_url: String,
+  pub pricing_model: PricingModel,
+  /// Session lengths in minutes the expert accepts, as a JSON array
+  pub allowed_durations: Json,
   pub created_at: DateTimeWithTimeZone,
   pub updated_at: DateTimeWithTimeZone,
 }
@@ -42,4 +57,18 @@ impl Related<super::users::Entity> for Entity {
   }
 }
 
+impl Related<super::categories::Entity> for Entity {
+  fn to() -> RelationDef {
+    super::expert_categories::Relation::Categories.def()
+  }
+
+  fn via() -> Option<RelationDef> {
+    Some(
+      super::expert_categories::Relation::ExpertProfiles
+        .def()
+        .rev(),
+    )
+  }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
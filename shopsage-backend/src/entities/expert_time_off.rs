@@ -0,0 +1,42 @@
+//! `SeaORM` Entity for expert_time_off table
+
+use super::sea_orm_active_enums::TimeOffRecurrence;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "expert_time_off")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub starts_at: DateTimeWithTimeZone,
+  pub ends_at: DateTimeWithTimeZone,
+  pub timezone: String,
+  pub recurrence: TimeOffRecurrence,
+  pub repeat_until: Option<DateTimeWithTimeZone>,
+  #[sea_orm(column_type = "Text")]
+  pub reason: Option<String>,
+  pub created_at: DateTimeWithTimeZone,
+  pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::UserId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Users,
+}
+
+impl Related<super::users::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Users.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
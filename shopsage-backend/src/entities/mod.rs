@@ -2,9 +2,21 @@
 
 pub mod prelude;
 
+pub mod auth_nonces;
+pub mod auth_sessions;
+pub mod categories;
+pub mod content_reports;
 pub mod expert_availability;
+pub mod expert_categories;
 pub mod expert_profiles;
 pub mod expert_stats;
+pub mod expert_time_off;
+pub mod notifications;
+pub mod payments;
+pub mod reviews;
+pub mod sea_orm_active_enums;
+pub mod session_events;
 pub mod sessions;
 pub mod shopper_profiles;
+pub mod shopper_saved_experts;
 pub mod users;
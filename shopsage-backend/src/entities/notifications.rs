@@ -0,0 +1,47 @@
+//! `SeaORM` Entity for notifications table
+
+use super::sea_orm_active_enums::NotificationKind;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "notifications")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub kind: NotificationKind,
+  #[sea_orm(column_type = "Text")]
+  pub message: String,
+  pub session_id: Option<Uuid>,
+  pub read_at: Option<DateTimeWithTimeZone>,
+  pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::UserId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Users,
+  #[sea_orm(
+    belongs_to = "super::sessions::Entity",
+    from = "Column::SessionId",
+    to = "super::sessions::Column::Id",
+    on_update = "NoAction",
+    on_delete = "SetNull"
+  )]
+  Sessions,
+}
+
+impl Related<super::users::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Users.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
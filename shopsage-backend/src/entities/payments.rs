@@ -0,0 +1,66 @@
+//! `SeaORM` Entity for payments table
+
+use super::sea_orm_active_enums::PaymentStatus;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "payments")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub session_id: Uuid,
+  /// The session's shopper
+  pub payer_id: Uuid,
+  /// The session's expert
+  pub payee_id: Uuid,
+  pub payer_wallet: String,
+  pub payee_wallet: String,
+  pub lamports: i64,
+  /// Always `SOL` for now
+  pub currency: String,
+  #[sea_orm(unique)]
+  pub tx_signature: String,
+  pub status: PaymentStatus,
+  #[sea_orm(column_type = "Text")]
+  pub failure_reason: Option<String>,
+  pub confirmed_at: Option<DateTimeWithTimeZone>,
+  pub created_at: DateTimeWithTimeZone,
+  pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::sessions::Entity",
+    from = "Column::SessionId",
+    to = "super::sessions::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Session,
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::PayerId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Payer,
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::PayeeId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Payee,
+}
+
+impl Related<super::sessions::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Session.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
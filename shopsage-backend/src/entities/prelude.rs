@@ -1,8 +1,19 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
+pub use super::auth_nonces::Entity as AuthNonces;
+pub use super::auth_sessions::Entity as AuthSessions;
+pub use super::categories::Entity as Categories;
+pub use super::content_reports::Entity as ContentReports;
 pub use super::expert_availability::Entity as ExpertAvailability;
+pub use super::expert_categories::Entity as ExpertCategories;
 pub use super::expert_profiles::Entity as ExpertProfiles;
 pub use super::expert_stats::Entity as ExpertStats;
+pub use super::expert_time_off::Entity as ExpertTimeOff;
+pub use super::notifications::Entity as Notifications;
+pub use super::payments::Entity as Payments;
+pub use super::reviews::Entity as Reviews;
+pub use super::session_events::Entity as SessionEvents;
 pub use super::sessions::Entity as Sessions;
 pub use super::shopper_profiles::Entity as ShopperProfiles;
+pub use super::shopper_saved_experts::Entity as ShopperSavedExperts;
 pub use super::users::Entity as Users;
@@ -0,0 +1,76 @@
+//! `SeaORM` Entity for reviews table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "reviews")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  #[sea_orm(unique)]
+  pub session_id: Uuid,
+  /// Expert profile the review is about
+  pub expert_id: Uuid,
+  /// User who took the session and wrote the review
+  pub shopper_id: Uuid,
+  /// 1 to 5 stars
+  pub rating: i16,
+  #[sea_orm(column_type = "Text")]
+  pub comment: String,
+  #[sea_orm(column_type = "Text")]
+  pub expert_reply: Option<String>,
+  pub replied_at: Option<DateTimeWithTimeZone>,
+  /// Set while moderation keeps the review out of listings and the expert's rating
+  pub hidden_at: Option<DateTimeWithTimeZone>,
+  pub created_at: DateTimeWithTimeZone,
+  pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::sessions::Entity",
+    from = "Column::SessionId",
+    to = "super::sessions::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Sessions,
+  #[sea_orm(
+    belongs_to = "super::expert_profiles::Entity",
+    from = "Column::ExpertId",
+    to = "super::expert_profiles::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  ExpertProfiles,
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::ShopperId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Shopper,
+}
+
+impl Related<super::sessions::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Sessions.def()
+  }
+}
+
+impl Related<super::expert_profiles::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ExpertProfiles.def()
+  }
+}
+
+impl Related<super::users::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Shopper.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
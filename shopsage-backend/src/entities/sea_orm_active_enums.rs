@@ -0,0 +1,121 @@
+//! `SeaORM` Entity active enums
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle state of a consultation session, stored as text behind `chk_sessions_status`
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStatus {
+  #[sea_orm(string_value = "scheduled")]
+  Scheduled,
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "active")]
+  Active,
+  #[sea_orm(string_value = "completed")]
+  Completed,
+  #[sea_orm(string_value = "cancelled")]
+  Cancelled,
+  #[sea_orm(string_value = "no_show")]
+  NoShow,
+  #[sea_orm(string_value = "disputed")]
+  Disputed,
+  #[sea_orm(string_value = "refunded")]
+  Refunded,
+}
+
+/// How an expert's `session_rate` is applied to a booking
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum PricingModel {
+  /// `session_rate` is the price of a whole session, whatever its length
+  #[sea_orm(string_value = "flat")]
+  Flat,
+  /// `session_rate` is charged for every booked minute
+  #[sea_orm(string_value = "per_minute")]
+  PerMinute,
+}
+
+/// How an `expert_time_off` entry repeats, in the entry's own timezone
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOffRecurrence {
+  #[sea_orm(string_value = "once")]
+  Once,
+  /// Same weekday and wall-clock time every week, e.g. Friday afternoons
+  #[sea_orm(string_value = "weekly")]
+  Weekly,
+  /// Same date and wall-clock time every year, e.g. a national holiday
+  #[sea_orm(string_value = "yearly")]
+  Yearly,
+}
+
+/// What a notification is about
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+  #[sea_orm(string_value = "session_cancelled")]
+  SessionCancelled,
+}
+
+/// Kind of user-generated content a moderation report points at
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+  /// A row in `reviews`
+  #[sea_orm(string_value = "review")]
+  Review,
+  /// The `bio` of an `expert_profiles` row
+  #[sea_orm(string_value = "expert_bio")]
+  ExpertBio,
+}
+
+/// Where a content report is in the moderation queue
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+  #[sea_orm(string_value = "open")]
+  Open,
+  /// The content was hidden or its author banned
+  #[sea_orm(string_value = "resolved")]
+  Resolved,
+  /// A moderator kept the content up
+  #[sea_orm(string_value = "dismissed")]
+  Dismissed,
+}
+
+/// Settlement of an on-chain session payment
+#[derive(
+  Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+  /// The transaction was submitted but is not finalized yet
+  #[sea_orm(string_value = "pending")]
+  Pending,
+  #[sea_orm(string_value = "confirmed")]
+  Confirmed,
+  /// The transaction failed or doesn't pay for the session
+  #[sea_orm(string_value = "failed")]
+  Failed,
+}
@@ -0,0 +1,49 @@
+//! `SeaORM` Entity for session_events table
+
+use super::sea_orm_active_enums::SessionStatus;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "session_events")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub id: Uuid,
+  pub session_id: Uuid,
+  pub actor_id: Option<Uuid>,
+  pub old_status: Option<SessionStatus>,
+  pub new_status: SessionStatus,
+  #[sea_orm(column_type = "Text")]
+  pub reason: Option<String>,
+  pub client_ip: Option<String>,
+  pub tx_signature: Option<String>,
+  pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::sessions::Entity",
+    from = "Column::SessionId",
+    to = "super::sessions::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  Sessions,
+  #[sea_orm(
+    belongs_to = "super::users::Entity",
+    from = "Column::ActorId",
+    to = "super::users::Column::Id",
+    on_update = "NoAction",
+    on_delete = "SetNull"
+  )]
+  Users,
+}
+
+impl Related<super::sessions::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::Sessions.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
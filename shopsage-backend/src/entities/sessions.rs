@@ -1,5 +1,6 @@
 //! `SeaORM` Entity for sessions table
 
+use super::sea_orm_active_enums::SessionStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -10,13 +11,24 @@ pub struct Model {
   pub id: Uuid,
   pub expert_id: Uuid,
   pub shopper_id: Uuid,
-  pub status: String,
+  pub status: SessionStatus,
   #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
   pub amount: Decimal,
+  /// When the session actually started; stamped on the move to active
   pub start_time: Option<DateTimeWithTimeZone>,
+  /// When the session actually ended; stamped on the move out of active
   pub end_time: Option<DateTimeWithTimeZone>,
+  /// Booked window, covered by the `excl_sessions_expert_no_overlap` constraint
+  pub booked_start: Option<DateTimeWithTimeZone>,
+  pub booked_end: Option<DateTimeWithTimeZone>,
   #[sea_orm(column_type = "Text")]
   pub notes: Option<String>,
+  pub duration_minutes: Option<i32>,
+  #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
+  pub quoted_price: Option<Decimal>,
+  /// Solana Pay reference the paying transaction must include; assigned on first request
+  #[sea_orm(unique)]
+  pub payment_reference: Option<String>,
   pub created_at: DateTimeWithTimeZone,
   pub updated_at: DateTimeWithTimeZone,
 }
@@ -47,4 +59,4 @@ impl Related<super::users::Entity> for Entity {
   }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
+impl ActiveModelBehavior for ActiveModel {}
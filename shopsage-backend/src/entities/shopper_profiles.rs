@@ -14,8 +14,6 @@ pub struct Model {
   pub price_range_min: Decimal,
   #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
   pub price_range_max: Decimal,
-  pub preferred_experts: Json,
-  pub saved_experts: Json,
   pub interests: Json,
   pub total_sessions: i32,
   #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
@@ -36,6 +34,8 @@ pub enum Relation {
     on_delete = "Cascade"
   )]
   Users,
+  #[sea_orm(has_many = "super::shopper_saved_experts::Entity")]
+  ShopperSavedExperts,
 }
 
 impl Related<super::users::Entity> for Entity {
@@ -44,4 +44,10 @@ impl Related<super::users::Entity> for Entity {
   }
 }
 
+impl Related<super::shopper_saved_experts::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ShopperSavedExperts.def()
+  }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
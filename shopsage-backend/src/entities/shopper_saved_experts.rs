@@ -0,0 +1,50 @@
+//! `SeaORM` Entity for shopper_saved_experts table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "shopper_saved_experts")]
+pub struct Model {
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub shopper_profile_id: Uuid,
+  #[sea_orm(primary_key, auto_increment = false)]
+  pub expert_id: Uuid,
+  /// Favourite, shown ahead of the shopper's other saved experts
+  pub is_preferred: bool,
+  pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+  #[sea_orm(
+    belongs_to = "super::shopper_profiles::Entity",
+    from = "Column::ShopperProfileId",
+    to = "super::shopper_profiles::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  ShopperProfiles,
+  #[sea_orm(
+    belongs_to = "super::expert_profiles::Entity",
+    from = "Column::ExpertId",
+    to = "super::expert_profiles::Column::Id",
+    on_update = "NoAction",
+    on_delete = "Cascade"
+  )]
+  ExpertProfiles,
+}
+
+impl Related<super::shopper_profiles::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ShopperProfiles.def()
+  }
+}
+
+impl Related<super::expert_profiles::Entity> for Entity {
+  fn to() -> RelationDef {
+    Relation::ExpertProfiles.def()
+  }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
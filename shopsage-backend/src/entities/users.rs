@@ -12,6 +12,11 @@ pub struct Model {
   pub wallet_address: String,
   pub name: String,
   pub email: String,
+  pub is_admin: bool,
+  /// Banned users can't sign in and their expert profile is no longer listed
+  pub banned_at: Option<DateTimeWithTimeZone>,
+  #[sea_orm(column_type = "Text")]
+  pub ban_reason: Option<String>,
   pub created_at: DateTimeWithTimeZone,
   pub updated_at: DateTimeWithTimeZone,
 }
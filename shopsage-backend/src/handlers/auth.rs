@@ -3,17 +3,17 @@ use axum::{
   http::StatusCode,
   Json,
 };
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::AppState;
-use crate::{
-  entities::shopper_profiles,
-  services::user_service::{
-    self, CreateUserRequest, UserCompleteProfile, UserProfile, UserService,
-  },
+use crate::middleware::auth::AuthUser;
+use crate::services::{
+  auth_session::{AuthSessionError, AuthSessionService},
+  moderation::ModerationService,
+  siws::{SiwsError, SiwsService},
+  user_service::{CreateUserRequest, UserCompleteProfile, UserProfile, UserService},
 };
+use crate::AppState;
 
 #[derive(Debug, Deserialize)]
 pub struct RegisterUserRequest {
@@ -21,26 +21,70 @@ pub struct RegisterUserRequest {
   pub wallet_address: String,
   pub name: String,
   pub email: Option<String>,
+  pub nonce: String,
+  /// Base58 encoded ed25519 signature over the challenge message issued for `nonce`
+  pub signature: String,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct LoginUserRequest {
+pub struct NonceRequest {
   #[serde(rename = "walletAddress")]
   pub wallet_address: String,
 }
 
 #[derive(Debug, Serialize)]
-pub struct RegisterUserResponse {
+pub struct NonceResponse {
+  pub nonce: String,
+  pub message: String,
+  #[serde(rename = "expiresAt")]
+  pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifySignatureRequest {
+  #[serde(rename = "walletAddress")]
+  pub wallet_address: String,
+  pub nonce: String,
+  /// Base58 encoded ed25519 signature over the challenge message issued for `nonce`
+  pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+  #[serde(rename = "refreshToken")]
+  pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
   pub token: String,
+  #[serde(rename = "refreshToken")]
+  pub refresh_token: String,
+  /// Access token lifetime in seconds
+  #[serde(rename = "expiresIn")]
+  pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegisterUserResponse {
+  #[serde(flatten)]
+  pub tokens: TokenPair,
   pub user: UserCompleteProfile,
 }
 
 #[derive(Debug, Serialize)]
 pub struct LoginUserResponse {
-  pub token: String,
+  #[serde(flatten)]
+  pub tokens: TokenPair,
   pub user: UserCompleteProfile,
 }
 
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+  #[serde(rename = "revokedSessions")]
+  pub revoked_sessions: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
   pub id: String,
@@ -66,13 +110,6 @@ pub struct AuthError {
   pub error: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-  wallet_address: String,
-  user_id: String,
-  exp: usize,
-}
-
 fn user_to_response(user: &UserProfile) -> UserResponse {
   UserResponse {
     id: user.id.clone(),
@@ -98,6 +135,23 @@ pub async fn register_user(
     "Processing user registration"
   );
 
+  SiwsService::verify_challenge(
+    app_state.db.connection(),
+    &request_data.wallet_address,
+    &request_data.nonce,
+    &request_data.signature,
+  )
+  .await
+  .map_err(|err| {
+    tracing::warn!(
+      request_id = %request_id,
+      wallet_address = %request_data.wallet_address,
+      error = %err,
+      "Wallet ownership proof rejected during registration"
+    );
+    siws_error_response(err)
+  })?;
+
   // Check if user already exists
   let existing_user =
     UserService::find_by_wallet_address(app_state.db.connection(), &request_data.wallet_address)
@@ -133,22 +187,26 @@ pub async fn register_user(
       email: request_data.email,
     };
 
-    let new_user = UserService::create(app_state.db.connection(), create_request)
-      .await
-      .map_err(|err| {
-        tracing::error!(
-          request_id = %request_id,
-          wallet_address = %request_data.wallet_address,
-          error = %err,
-          "Failed to create new user"
-        );
-        (
-          StatusCode::INTERNAL_SERVER_ERROR,
-          Json(AuthError {
-            error: "Failed to create user".to_string(),
-          }),
-        )
-      })?;
+    let new_user = UserService::create(
+      app_state.db.connection(),
+      create_request,
+      &app_state.config.rating_prior,
+    )
+    .await
+    .map_err(|err| {
+      tracing::error!(
+        request_id = %request_id,
+        wallet_address = %request_data.wallet_address,
+        error = %err,
+        "Failed to create new user"
+      );
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError {
+          error: "Failed to create user".to_string(),
+        }),
+      )
+    })?;
 
     tracing::info!(
       request_id = %request_id,
@@ -160,22 +218,47 @@ pub async fn register_user(
     new_user
   };
 
-  // Generate JWT token
-  let token = generate_token(&user.user.wallet_address, &user.user.id).map_err(|_| {
+  // Start an auth session and issue its tokens
+  let tokens = start_session(&app_state, &user.user).await?;
+
+  Ok(Json(RegisterUserResponse { tokens, user }))
+}
+
+pub async fn request_nonce(
+  State(app_state): State<AppState>,
+  payload: Result<Json<NonceRequest>, JsonRejection>,
+) -> Result<Json<NonceResponse>, (StatusCode, Json<AuthError>)> {
+  let Json(request) = payload.map_err(|rejection| {
+    tracing::error!("JSON parsing error: {}", rejection);
     (
-      StatusCode::INTERNAL_SERVER_ERROR,
+      StatusCode::BAD_REQUEST,
       Json(AuthError {
-        error: "Failed to generate token".to_string(),
+        error: format!(
+          "Invalid JSON: {}. Required fields: walletAddress (string)",
+          rejection
+        ),
       }),
     )
   })?;
 
-  Ok(Json(RegisterUserResponse { token, user }))
+  let challenge = SiwsService::issue_challenge(
+    app_state.db.connection(),
+    &app_state.config,
+    &request.wallet_address,
+  )
+  .await
+  .map_err(siws_error_response)?;
+
+  Ok(Json(NonceResponse {
+    nonce: challenge.nonce,
+    message: challenge.message,
+    expires_at: challenge.expires_at.to_rfc3339(),
+  }))
 }
 
-pub async fn login_user(
+pub async fn verify_login(
   State(app_state): State<AppState>,
-  payload: Result<Json<LoginUserRequest>, JsonRejection>,
+  payload: Result<Json<VerifySignatureRequest>, JsonRejection>,
 ) -> Result<Json<LoginUserResponse>, (StatusCode, Json<AuthError>)> {
   let Json(request) = payload.map_err(|rejection| {
     tracing::error!("JSON parsing error: {}", rejection);
@@ -183,13 +266,29 @@ pub async fn login_user(
       StatusCode::BAD_REQUEST,
       Json(AuthError {
         error: format!(
-          "Invalid JSON: {}. Required fields: walletAddress (string)",
+          "Invalid JSON: {}. Required fields: walletAddress, nonce, signature (string)",
           rejection
         ),
       }),
     )
   })?;
 
+  SiwsService::verify_challenge(
+    app_state.db.connection(),
+    &request.wallet_address,
+    &request.nonce,
+    &request.signature,
+  )
+  .await
+  .map_err(|err| {
+    tracing::warn!(
+      wallet_address = %request.wallet_address,
+      error = %err,
+      "Wallet ownership proof rejected during login"
+    );
+    siws_error_response(err)
+  })?;
+
   // Check if user exists
   let existing_user =
     UserService::find_by_wallet_address(app_state.db.connection(), &request.wallet_address)
@@ -214,37 +313,109 @@ pub async fn login_user(
     ));
   };
 
-  // Generate JWT token
-  let token = generate_token(&user.user.wallet_address, &user.user.id).map_err(|_| {
+  // Start an auth session and issue its tokens
+  let tokens = start_session(&app_state, &user.user).await?;
+
+  Ok(Json(LoginUserResponse { tokens, user }))
+}
+
+pub async fn refresh_token(
+  State(app_state): State<AppState>,
+  payload: Result<Json<RefreshTokenRequest>, JsonRejection>,
+) -> Result<Json<TokenPair>, (StatusCode, Json<AuthError>)> {
+  let Json(request) = payload.map_err(|rejection| {
+    tracing::error!("JSON parsing error: {}", rejection);
     (
-      StatusCode::INTERNAL_SERVER_ERROR,
+      StatusCode::BAD_REQUEST,
       Json(AuthError {
-        error: "Failed to generate token".to_string(),
+        error: format!(
+          "Invalid JSON: {}. Required fields: refreshToken (string)",
+          rejection
+        ),
       }),
     )
   })?;
 
-  Ok(Json(LoginUserResponse { token, user }))
+  let issued = AuthSessionService::rotate(
+    app_state.db.connection(),
+    &app_state.config,
+    &request.refresh_token,
+  )
+  .await
+  .map_err(auth_session_error_response)?;
+
+  let user = UserService::find_by_id(app_state.db.connection(), issued.session.user_id)
+    .await
+    .map_err(|_| {
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError {
+          error: "Database error".to_string(),
+        }),
+      )
+    })?
+    .ok_or((
+      StatusCode::UNAUTHORIZED,
+      Json(AuthError {
+        error: "UserProfile not found".to_string(),
+      }),
+    ))?;
+
+  let token = issue_access_token(&app_state, &user, issued.session.id)?;
+
+  Ok(Json(TokenPair {
+    token,
+    refresh_token: issued.refresh_token,
+    expires_in: app_state.tokens.ttl_secs(),
+  }))
+}
+
+pub async fn logout(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+) -> Result<Json<LogoutResponse>, (StatusCode, Json<AuthError>)> {
+  AuthSessionService::revoke(app_state.db.connection(), auth_user.session_id, "logout")
+    .await
+    .map_err(auth_session_error_response)?;
+
+  Ok(Json(LogoutResponse {
+    revoked_sessions: 1,
+  }))
+}
+
+pub async fn logout_all(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+) -> Result<Json<LogoutResponse>, (StatusCode, Json<AuthError>)> {
+  let revoked_sessions =
+    AuthSessionService::revoke_all_for_user(app_state.db.connection(), auth_user.id, "logout_all")
+      .await
+      .map_err(auth_session_error_response)?;
+
+  tracing::info!(
+    user_id = %auth_user.id,
+    revoked_sessions = revoked_sessions,
+    "Revoked all sessions for user"
+  );
+
+  Ok(Json(LogoutResponse { revoked_sessions }))
 }
 
 pub async fn verify_token(
   State(app_state): State<AppState>,
   Json(request): Json<VerifyTokenRequest>,
 ) -> Json<VerifyTokenResponse> {
-  match verify_jwt_token(&request.token) {
+  match app_state.tokens.verify(&request.token) {
     Ok(claims) => {
-      // Parse user_id from string to UUID
-      if let Ok(user_id) = Uuid::parse_str(&claims.user_id) {
-        // Fetch user from database
-        if let Ok(Some(user)) = UserService::find_by_id(app_state.db.connection(), user_id).await {
-          let user_response = user_to_response(&user);
-          return Json(VerifyTokenResponse {
-            valid: true,
-            user: Some(user_response),
-          });
-        }
+      // Fetch user from database
+      if let Ok(Some(user)) = UserService::find_by_id(app_state.db.connection(), claims.sub).await {
+        let user_response = user_to_response(&user);
+        return Json(VerifyTokenResponse {
+          valid: true,
+          user: Some(user_response),
+        });
       }
-      // If user not found or UUID parsing failed
+      // If user not found
       Json(VerifyTokenResponse {
         valid: false,
         user: None,
@@ -257,36 +428,110 @@ pub async fn verify_token(
   }
 }
 
-fn generate_token(
-  wallet_address: &str,
-  user_id: &str,
-) -> Result<String, jsonwebtoken::errors::Error> {
-  let secret = "your-secret-key"; // In production, use proper secret from config
-
-  let expiration = chrono::Utc::now()
-    .checked_add_signed(chrono::Duration::days(7))
-    .expect("valid timestamp")
-    .timestamp();
-
-  let claims = Claims {
-    wallet_address: wallet_address.to_string(),
-    user_id: user_id.to_string(),
-    exp: expiration as usize,
+fn siws_error_response(err: SiwsError) -> (StatusCode, Json<AuthError>) {
+  let status = match err {
+    SiwsError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    SiwsError::InvalidWalletAddress(_) | SiwsError::InvalidSignatureEncoding => {
+      StatusCode::BAD_REQUEST
+    }
+    SiwsError::VerificationFailed | SiwsError::InvalidNonce => StatusCode::UNAUTHORIZED,
+  };
+  let error = match err {
+    SiwsError::DbError(_) => "Database error".to_string(),
+    other => other.to_string(),
   };
 
-  let header = Header::new(Algorithm::HS256);
-  encode(&header, &claims, &EncodingKey::from_secret(secret.as_ref()))
+  (status, Json(AuthError { error }))
 }
 
-fn verify_jwt_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-  let secret = "your-secret-key"; // Same secret as used for encoding
-  let validation = Validation::new(Algorithm::HS256);
+fn auth_session_error_response(err: AuthSessionError) -> (StatusCode, Json<AuthError>) {
+  match err {
+    AuthSessionError::DbError(err) => {
+      tracing::error!(error = %err, "Database error in auth session");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError {
+          error: "Database error".to_string(),
+        }),
+      )
+    }
+    other => (
+      StatusCode::UNAUTHORIZED,
+      Json(AuthError {
+        error: other.to_string(),
+      }),
+    ),
+  }
+}
 
-  let token_data = decode::<Claims>(
+async fn start_session(
+  app_state: &AppState,
+  user: &UserProfile,
+) -> Result<TokenPair, (StatusCode, Json<AuthError>)> {
+  let user_id = Uuid::parse_str(&user.id).map_err(|_| {
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      Json(AuthError {
+        error: "Invalid user ID".to_string(),
+      }),
+    )
+  })?;
+
+  // A ban revokes existing sessions; this keeps the user from starting new ones
+  let banned = ModerationService::is_banned(app_state.db.connection(), user_id)
+    .await
+    .map_err(|err| {
+      tracing::error!(user_id = %user_id, error = %err, "Failed to check ban status");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError {
+          error: "Database error".to_string(),
+        }),
+      )
+    })?;
+  if banned {
+    return Err((
+      StatusCode::FORBIDDEN,
+      Json(AuthError {
+        error: "This account has been suspended".to_string(),
+      }),
+    ));
+  }
+
+  let issued = AuthSessionService::start(app_state.db.connection(), &app_state.config, user_id)
+    .await
+    .map_err(auth_session_error_response)?;
+
+  let token = issue_access_token(app_state, user, issued.session.id)?;
+
+  Ok(TokenPair {
     token,
-    &DecodingKey::from_secret(secret.as_ref()),
-    &validation,
-  )?;
+    refresh_token: issued.refresh_token,
+    expires_in: app_state.tokens.ttl_secs(),
+  })
+}
 
-  Ok(token_data.claims)
+fn issue_access_token(
+  app_state: &AppState,
+  user: &UserProfile,
+  session_id: Uuid,
+) -> Result<String, (StatusCode, Json<AuthError>)> {
+  let token = Uuid::parse_str(&user.id)
+    .map_err(|err| err.to_string())
+    .and_then(|user_id| {
+      app_state
+        .tokens
+        .issue(user_id, session_id, &user.wallet_address)
+        .map_err(|err| err.to_string())
+    });
+
+  token.map_err(|err| {
+    tracing::error!(user_id = %user.id, error = %err, "Failed to generate token");
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      Json(AuthError {
+        error: "Failed to generate token".to_string(),
+      }),
+    )
+  })
 }
@@ -0,0 +1,113 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+
+use crate::{
+  handlers::profiles::ProfileError,
+  middleware::auth::AuthUser,
+  services::{
+    availability::{AvailabilityError, AvailabilityService, WindowInput},
+    policy::RequireRole,
+    user_service::{Availability, UserRole, UserService},
+  },
+  AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AvailabilityWindowRequest {
+  #[serde(rename = "dayOfWeek")]
+  pub day_of_week: String,
+  /// "HH:MM" in the schedule's timezone
+  pub start: String,
+  /// "HH:MM"; "24:00" or "00:00" for midnight at the end of the day
+  pub end: String,
+  /// `false` marks a blackout inside an available window
+  pub available: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplaceAvailabilityRequest {
+  pub timezone: String,
+  pub windows: Vec<AvailabilityWindowRequest>,
+}
+
+fn availability_error_response(err: AvailabilityError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    AvailabilityError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to manage expert availability");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    _ => StatusCode::BAD_REQUEST,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+pub async fn get_availability(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+) -> Result<Json<Availability>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  let rules = AvailabilityService::rules_for_expert(app_state.db.connection(), auth_user.id)
+    .await
+    .map_err(|err| availability_error_response(err.into()))?;
+
+  Ok(Json(UserService::availability_from_rules(rules)))
+}
+
+/// Replace the whole weekly schedule; an empty `windows` list clears it
+pub async fn replace_availability(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Json(request): Json<ReplaceAvailabilityRequest>,
+) -> Result<Json<Availability>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  let windows: Vec<WindowInput> = request
+    .windows
+    .into_iter()
+    .map(|window| WindowInput {
+      day_of_week: window.day_of_week,
+      start: window.start,
+      end: window.end,
+      available: window.available.unwrap_or(true),
+    })
+    .collect();
+
+  let rules = AvailabilityService::replace_schedule(
+    app_state.db.connection(),
+    auth_user.id,
+    &request.timezone,
+    &windows,
+  )
+  .await
+  .map_err(availability_error_response)?;
+
+  let mut availability = UserService::availability_from_rules(rules);
+  if availability.windows.is_empty() {
+    // Nothing stored to carry the timezone, echo back the validated one
+    if let Some(tz) = AvailabilityService::parse_timezone(&request.timezone) {
+      availability.timezone = tz.name().to_string();
+    }
+  }
+
+  Ok(Json(availability))
+}
+
+pub async fn clear_availability(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+) -> Result<StatusCode, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  AvailabilityService::replace_schedule(app_state.db.connection(), auth_user.id, "UTC", &[])
+    .await
+    .map_err(availability_error_response)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
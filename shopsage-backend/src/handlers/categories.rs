@@ -0,0 +1,54 @@
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  Json,
+};
+use serde::Serialize;
+
+use crate::{
+  handlers::experts::{run_search, ExpertError, ExpertListResponse, ExpertSearchQuery},
+  services::{
+    categories::{CategoryNode, CategoryService},
+    expert_search::ExpertSearchParams,
+  },
+  AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct CategoryTreeResponse {
+  pub categories: Vec<CategoryNode>,
+}
+
+pub async fn list_categories(
+  State(app_state): State<AppState>,
+) -> Result<Json<CategoryTreeResponse>, (StatusCode, Json<ExpertError>)> {
+  let categories = CategoryService::all(app_state.db.connection())
+    .await
+    .map_err(|err| {
+      tracing::error!(error = %err, "Failed to load categories");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ExpertError {
+          error: "Database error".to_string(),
+        }),
+      )
+    })?;
+
+  Ok(Json(CategoryTreeResponse {
+    categories: CategoryService::build_tree(categories),
+  }))
+}
+
+/// Experts filed under the category or any of its subcategories; takes the same filters as search
+pub async fn list_category_experts(
+  State(app_state): State<AppState>,
+  Path(slug): Path<String>,
+  Query(query): Query<ExpertSearchQuery>,
+) -> Result<Json<ExpertListResponse>, (StatusCode, Json<ExpertError>)> {
+  let params = ExpertSearchParams {
+    category: Some(slug),
+    ..query.into()
+  };
+
+  run_search(&app_state, params).await
+}
@@ -1,15 +1,62 @@
-use axum::{extract::{Path, State}, http::StatusCode, Json};
+use std::collections::BTreeMap;
+
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
 use sea_orm::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::entities::{expert_profiles, prelude::*, users};
+use crate::handlers::profiles::ProfileError;
+use crate::middleware::auth::AuthUser;
+use crate::services::booking::BookingService;
+use crate::services::categories::{CategoryService, ExpertCategory};
+use crate::services::expert_search::{
+  ExpertSearchError, ExpertSearchParams, ExpertSearchService, ExpertSort, SortDirection,
+};
+use crate::services::policy::RequireRole;
+use crate::services::pricing::PricingService;
+use crate::services::ratings::RatingService;
+use crate::services::recommendations::{RecommendationError, RecommendationService, ScoreReason};
+use crate::services::user_service::UserRole;
 use crate::AppState;
 
+/// Longest range a single slots request may cover
+const MAX_SLOT_RANGE_DAYS: i64 = 31;
+const DEFAULT_SLOT_RANGE_DAYS: i64 = 7;
+
 #[derive(Debug, Serialize)]
 pub struct ExpertListResponse {
   pub experts: Vec<ExpertBasicInfo>,
   pub total: i32,
+  /// Pass back as `cursor` for the next page; `null` on the last page
+  #[serde(rename = "nextCursor")]
+  pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExpertSearchQuery {
+  /// Free text matched against name, specialization and bio; results default to relevance order
+  pub q: Option<String>,
+  /// Category slug, including its subcategories
+  pub category: Option<String>,
+  #[serde(rename = "minRate")]
+  pub min_rate: Option<Decimal>,
+  #[serde(rename = "maxRate")]
+  pub max_rate: Option<Decimal>,
+  #[serde(rename = "minRating")]
+  pub min_rating: Option<Decimal>,
+  #[serde(rename = "isOnline")]
+  pub is_online: Option<bool>,
+  pub sort: Option<ExpertSort>,
+  pub order: Option<SortDirection>,
+  pub limit: Option<u64>,
+  pub cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -26,6 +73,29 @@ pub struct ExpertBasicInfo {
   pub is_verified: bool,
   #[serde(rename = "profileImageUrl")]
   pub profile_image_url: Option<String>,
+  /// HTML excerpt with the matched terms wrapped in `<mark>`, only for text searches. The
+  /// expert's text in it is escaped, so it is safe to render as HTML.
+  pub snippet: Option<String>,
+}
+
+impl From<(expert_profiles::Model, users::Model)> for ExpertBasicInfo {
+  fn from((expert, user): (expert_profiles::Model, users::Model)) -> Self {
+    ExpertBasicInfo {
+      id: expert.id.to_string(),
+      name: user.name,
+      specialization: expert.specialization,
+      session_rate: expert.session_rate.to_string().parse().unwrap_or(0.0),
+      rating: expert.rating.to_string().parse().unwrap_or(0.0),
+      is_online: expert.is_online,
+      is_verified: expert.is_verified,
+      profile_image_url: if expert.profile_image_url.is_empty() {
+        None
+      } else {
+        Some(expert.profile_image_url)
+      },
+      snippet: None,
+    }
+  }
 }
 
 #[derive(Debug, Serialize)]
@@ -42,6 +112,11 @@ pub struct ExpertDetailResponse {
   #[serde(rename = "sessionRate")]
   pub session_rate: f64,
   pub rating: f64,
+  #[serde(rename = "ratingCount")]
+  pub rating_count: i32,
+  /// Visible reviews per star, keyed "1" to "5"
+  #[serde(rename = "ratingHistogram")]
+  pub rating_histogram: BTreeMap<u8, u64>,
   #[serde(rename = "totalConsultations")]
   pub total_consultations: i32,
   #[serde(rename = "isOnline")]
@@ -52,6 +127,51 @@ pub struct ExpertDetailResponse {
   pub profile_image_url: Option<String>,
   #[serde(rename = "walletAddress")]
   pub wallet_address: String,
+  pub categories: Vec<ExpertCategory>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecommendedQuery {
+  pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecommendedExpert {
+  pub expert: ExpertBasicInfo,
+  /// Weighted score from 0 to 1
+  pub score: f64,
+  /// Per-factor breakdown of `score`
+  pub reasons: Vec<ScoreReason>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecommendedExpertsResponse {
+  pub experts: Vec<RecommendedExpert>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlotsQuery {
+  pub from: Option<String>,
+  pub to: Option<String>,
+  pub duration: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlotResponse {
+  pub start: String,
+  pub end: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExpertSlotsResponse {
+  #[serde(rename = "expertId")]
+  pub expert_id: Uuid,
+  #[serde(rename = "durationMinutes")]
+  pub duration_minutes: i32,
+  pub price: String,
+  pub from: String,
+  pub to: String,
+  pub slots: Vec<SlotResponse>,
 }
 
 pub async fn list_experts(
@@ -60,7 +180,8 @@ pub async fn list_experts(
   let experts_data = ExpertProfiles::find()
     .find_also_related(Users)
     .filter(expert_profiles::Column::IsVerified.eq(true))
-    .order_by_desc(expert_profiles::Column::Rating)
+    .filter(users::Column::BannedAt.is_null())
+    .order_by_desc(expert_profiles::Column::RatingScore)
     .all(app_state.db.connection())
     .await
     .map_err(|_| {
@@ -74,39 +195,131 @@ pub async fn list_experts(
 
   let experts: Vec<ExpertBasicInfo> = experts_data
     .into_iter()
-    .filter_map(|(expert_profile, user)| {
-      if expert_profile.is_verified {
-        user.map(|u| ExpertBasicInfo {
-          id: expert_profile.id.to_string(),
-          name: u.name,
-          specialization: expert_profile.specialization,
-          session_rate: expert_profile.session_rate.to_string().parse().unwrap_or(0.0),
-          rating: expert_profile.rating.to_string().parse().unwrap_or(0.0),
-          is_online: expert_profile.is_online,
-          is_verified: expert_profile.is_verified,
-          profile_image_url: if expert_profile.profile_image_url.is_empty() {
-            None
-          } else {
-            Some(expert_profile.profile_image_url)
-          },
-        })
-      } else {
-        None
-      }
-    })
+    .filter_map(|(expert_profile, user)| user.map(|user| (expert_profile, user).into()))
     .collect();
 
   let total = experts.len() as i32;
-  let response = ExpertListResponse { experts, total };
+  let response = ExpertListResponse {
+    experts,
+    total,
+    next_cursor: None,
+  };
 
   Ok(Json(response))
 }
 
+impl From<ExpertSearchQuery> for ExpertSearchParams {
+  fn from(query: ExpertSearchQuery) -> Self {
+    ExpertSearchParams {
+      text: query.q,
+      category: query.category,
+      min_rate: query.min_rate,
+      max_rate: query.max_rate,
+      min_rating: query.min_rating,
+      is_online: query.is_online,
+      sort: query.sort,
+      direction: query.order,
+      limit: query.limit,
+      cursor: query.cursor,
+    }
+  }
+}
+
 pub async fn search_experts(
   State(app_state): State<AppState>,
+  Query(query): Query<ExpertSearchQuery>,
 ) -> Result<Json<ExpertListResponse>, (StatusCode, Json<ExpertError>)> {
-  // For now, same as list_experts but could add search parameters later
-  list_experts(State(app_state)).await
+  run_search(&app_state, query.into()).await
+}
+
+/// Run an expert search and shape the page for the API; shared by search and category browsing
+pub(crate) async fn run_search(
+  app_state: &AppState,
+  params: ExpertSearchParams,
+) -> Result<Json<ExpertListResponse>, (StatusCode, Json<ExpertError>)> {
+  let page = ExpertSearchService::search(app_state.db.connection(), &params)
+    .await
+    .map_err(|err| match err {
+      ExpertSearchError::DbError(err) => {
+        tracing::error!(error = %err, "Expert search failed");
+        (
+          StatusCode::INTERNAL_SERVER_ERROR,
+          Json(ExpertError {
+            error: "Database error".to_string(),
+          }),
+        )
+      }
+      err @ ExpertSearchError::UnknownCategory(_) => (
+        StatusCode::NOT_FOUND,
+        Json(ExpertError {
+          error: err.to_string(),
+        }),
+      ),
+      err => (
+        StatusCode::BAD_REQUEST,
+        Json(ExpertError {
+          error: err.to_string(),
+        }),
+      ),
+    })?;
+
+  let experts = page
+    .experts
+    .into_iter()
+    .map(|hit| ExpertBasicInfo {
+      snippet: hit.snippet,
+      ..(hit.expert, hit.user).into()
+    })
+    .collect();
+
+  Ok(Json(ExpertListResponse {
+    experts,
+    total: i32::try_from(page.total).unwrap_or(i32::MAX),
+    next_cursor: page.next_cursor,
+  }))
+}
+
+/// Verified experts ranked for the signed-in shopper from their shopper profile preferences
+pub async fn get_recommended_experts(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Query(query): Query<RecommendedQuery>,
+) -> Result<Json<RecommendedExpertsResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
+
+  let recommendations = RecommendationService::recommend(
+    app_state.db.connection(),
+    auth_user.id,
+    &app_state.config.recommendation_weights,
+    query.limit,
+  )
+  .await
+  .map_err(|err| {
+    let status = match &err {
+      RecommendationError::DbError(err) => {
+        tracing::error!(error = %err, "Failed to recommend experts");
+        StatusCode::INTERNAL_SERVER_ERROR
+      }
+      RecommendationError::ShopperProfileNotFound => StatusCode::NOT_FOUND,
+    };
+    (
+      status,
+      Json(ProfileError {
+        error: err.to_string(),
+      }),
+    )
+  })?;
+
+  let experts = recommendations
+    .into_iter()
+    .map(|recommendation| RecommendedExpert {
+      expert: (recommendation.expert, recommendation.user).into(),
+      score: recommendation.score,
+      reasons: recommendation.reasons,
+    })
+    .collect();
+
+  Ok(Json(RecommendedExpertsResponse { experts }))
 }
 
 pub async fn get_expert_by_id(
@@ -137,20 +350,53 @@ pub async fn get_expert_by_id(
         ));
       }
 
-      let user = user.ok_or((
+      // Banned experts are gone from the public site, not just from listings
+      let user = user.filter(|user| user.banned_at.is_none()).ok_or((
         StatusCode::NOT_FOUND,
         Json(ExpertError {
           error: "User not found".to_string(),
         }),
       ))?;
 
+      let categories = CategoryService::for_expert(app_state.db.connection(), expert_profile.id)
+        .await
+        .map_err(|_| {
+          (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ExpertError {
+              error: "Database error".to_string(),
+            }),
+          )
+        })?;
+
+      let histogram = RatingService::histogram(app_state.db.connection(), expert_profile.id)
+        .await
+        .map_err(|_| {
+          (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ExpertError {
+              error: "Database error".to_string(),
+            }),
+          )
+        })?;
+
       let response = ExpertDetailResponse {
         id: expert_profile.id,
         name: user.name,
         specialization: expert_profile.specialization,
-        bio: expert_profile.bio,
-        session_rate: expert_profile.session_rate.to_string().parse().unwrap_or(0.0),
+        bio: if expert_profile.bio_hidden_at.is_none() {
+          expert_profile.bio
+        } else {
+          String::new()
+        },
+        session_rate: expert_profile
+          .session_rate
+          .to_string()
+          .parse()
+          .unwrap_or(0.0),
         rating: expert_profile.rating.to_string().parse().unwrap_or(0.0),
+        rating_count: expert_profile.rating_count,
+        rating_histogram: (1..).zip(histogram).collect(),
         total_consultations: expert_profile.total_consultations,
         is_online: expert_profile.is_online,
         is_verified: expert_profile.is_verified,
@@ -160,6 +406,7 @@ pub async fn get_expert_by_id(
           Some(expert_profile.profile_image_url)
         },
         wallet_address: user.wallet_address,
+        categories,
       };
 
       Ok(Json(response))
@@ -172,3 +419,96 @@ pub async fn get_expert_by_id(
     )),
   }
 }
+
+pub async fn get_expert_slots(
+  State(app_state): State<AppState>,
+  Path(expert_id): Path<Uuid>,
+  Query(query): Query<SlotsQuery>,
+) -> Result<Json<ExpertSlotsResponse>, (StatusCode, Json<ExpertError>)> {
+  let bad_request = |message: String| {
+    (
+      StatusCode::BAD_REQUEST,
+      Json(ExpertError { error: message }),
+    )
+  };
+
+  let parse_time = |value: &str, field: &str| {
+    DateTime::parse_from_rfc3339(value)
+      .map(|time| time.with_timezone(&Utc))
+      .map_err(|_| bad_request(format!("Invalid {} format, expected ISO 8601", field)))
+  };
+
+  let from = match &query.from {
+    Some(value) => parse_time(value, "from")?,
+    None => Utc::now(),
+  };
+  let to = match &query.to {
+    Some(value) => parse_time(value, "to")?,
+    None => from + Duration::days(DEFAULT_SLOT_RANGE_DAYS),
+  };
+
+  if to <= from {
+    return Err(bad_request("'to' must be after 'from'".to_string()));
+  }
+  if to - from > Duration::days(MAX_SLOT_RANGE_DAYS) {
+    return Err(bad_request(format!(
+      "Slot range cannot exceed {} days",
+      MAX_SLOT_RANGE_DAYS
+    )));
+  }
+
+  let expert_profile = ExpertProfiles::find_by_id(expert_id)
+    .one(app_state.db.connection())
+    .await
+    .map_err(|_| {
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ExpertError {
+          error: "Database error".to_string(),
+        }),
+      )
+    })?
+    .filter(|profile| profile.is_verified)
+    .ok_or((
+      StatusCode::NOT_FOUND,
+      Json(ExpertError {
+        error: "Expert not found or not verified".to_string(),
+      }),
+    ))?;
+
+  let quote = PricingService::quote(&expert_profile, query.duration)
+    .map_err(|err| bad_request(err.to_string()))?;
+
+  let slots = BookingService::open_slots(
+    app_state.db.connection(),
+    expert_profile.user_id,
+    from,
+    to,
+    Duration::minutes(quote.duration_minutes.into()),
+  )
+  .await
+  .map_err(|err| {
+    tracing::error!(error = %err, expert_id = %expert_id, "Failed to compute open slots");
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      Json(ExpertError {
+        error: "Database error".to_string(),
+      }),
+    )
+  })?;
+
+  Ok(Json(ExpertSlotsResponse {
+    expert_id,
+    duration_minutes: quote.duration_minutes,
+    price: quote.price.to_string(),
+    from: from.to_rfc3339(),
+    to: to.to_rfc3339(),
+    slots: slots
+      .into_iter()
+      .map(|slot| SlotResponse {
+        start: slot.start.to_rfc3339(),
+        end: slot.end.to_rfc3339(),
+      })
+      .collect(),
+  }))
+}
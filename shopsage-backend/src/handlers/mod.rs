@@ -1,4 +1,12 @@
 pub mod auth;
+pub mod availability;
+pub mod categories;
 pub mod experts;
+pub mod moderation;
+pub mod notifications;
+pub mod payments;
 pub mod profiles;
+pub mod reviews;
+pub mod saved_experts;
 pub mod sessions;
+pub mod time_off;
@@ -0,0 +1,283 @@
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  entities::{
+    content_reports,
+    sea_orm_active_enums::{ContentType, ReportStatus},
+  },
+  handlers::profiles::ProfileError,
+  middleware::auth::AuthUser,
+  services::{
+    auth_session::AuthSessionService,
+    moderation::{ModerationError, ModerationService, QueuedReport},
+    policy::RequireRole,
+    user_service::UserRole,
+  },
+  AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ReportsQuery {
+  /// Defaults to open reports
+  pub status: Option<ReportStatus>,
+  #[serde(rename = "contentType")]
+  pub content_type: Option<ContentType>,
+  pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportRequest {
+  pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BanRequest {
+  pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportedContentResponse {
+  #[serde(rename = "authorId")]
+  pub author_id: Uuid,
+  pub text: String,
+  #[serde(rename = "hiddenAt")]
+  pub hidden_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportResponse {
+  pub id: Uuid,
+  #[serde(rename = "contentType")]
+  pub content_type: ContentType,
+  #[serde(rename = "contentId")]
+  pub content_id: Uuid,
+  /// `None` when the content filter raised the report
+  #[serde(rename = "reporterId")]
+  pub reporter_id: Option<Uuid>,
+  pub reason: Option<String>,
+  pub status: ReportStatus,
+  #[serde(rename = "resolvedBy")]
+  pub resolved_by: Option<Uuid>,
+  #[serde(rename = "resolvedAt")]
+  pub resolved_at: Option<String>,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+  /// Absent once the content has been deleted
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub content: Option<ReportedContentResponse>,
+}
+
+impl From<content_reports::Model> for ReportResponse {
+  fn from(report: content_reports::Model) -> Self {
+    Self {
+      id: report.id,
+      content_type: report.content_type,
+      content_id: report.content_id,
+      reporter_id: report.reporter_id,
+      reason: report.reason,
+      status: report.status,
+      resolved_by: report.resolved_by,
+      resolved_at: report
+        .resolved_at
+        .map(|resolved_at| resolved_at.to_rfc3339()),
+      created_at: report.created_at.to_rfc3339(),
+      content: None,
+    }
+  }
+}
+
+impl From<QueuedReport> for ReportResponse {
+  fn from(queued: QueuedReport) -> Self {
+    Self {
+      content: queued.content.map(|content| ReportedContentResponse {
+        author_id: content.author_id,
+        text: content.text,
+        hidden_at: content.hidden_at.map(|hidden_at| hidden_at.to_rfc3339()),
+      }),
+      ..queued.report.into()
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportListResponse {
+  pub reports: Vec<ReportResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BanResponse {
+  #[serde(rename = "userId")]
+  pub user_id: Uuid,
+  #[serde(rename = "bannedAt")]
+  pub banned_at: Option<String>,
+  #[serde(rename = "banReason")]
+  pub ban_reason: Option<String>,
+}
+
+pub fn moderation_error_response(err: ModerationError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    ModerationError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to moderate content");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    ModerationError::ContentNotFound | ModerationError::UserNotFound => StatusCode::NOT_FOUND,
+    ModerationError::AlreadyReported => StatusCode::CONFLICT,
+    ModerationError::CannotBanAdmin => StatusCode::FORBIDDEN,
+    ModerationError::OwnContent | ModerationError::ReasonTooLong => StatusCode::BAD_REQUEST,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+/// Report an expert's bio; reviews are reported through `/api/reviews/{id}/report`
+pub async fn report_expert_bio(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(expert_id): Path<Uuid>,
+  request: Option<Json<ReportRequest>>,
+) -> Result<(StatusCode, Json<ReportResponse>), (StatusCode, Json<ProfileError>)> {
+  let reason = request.and_then(|Json(request)| request.reason);
+
+  let report = ModerationService::report(
+    app_state.db.connection(),
+    auth_user.id,
+    ContentType::ExpertBio,
+    expert_id,
+    reason.as_deref(),
+  )
+  .await
+  .map_err(moderation_error_response)?;
+
+  Ok((StatusCode::CREATED, Json(report.into())))
+}
+
+/// The moderation queue, oldest first
+pub async fn list_reports(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Query(query): Query<ReportsQuery>,
+) -> Result<Json<ReportListResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Admin), &())?;
+
+  let reports = ModerationService::queue(
+    app_state.db.connection(),
+    query.status.unwrap_or(ReportStatus::Open),
+    query.content_type,
+    query.limit,
+  )
+  .await
+  .map_err(|err| moderation_error_response(err.into()))?;
+
+  Ok(Json(ReportListResponse {
+    reports: reports.into_iter().map(ReportResponse::from).collect(),
+  }))
+}
+
+pub async fn hide_content(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path((content_type, content_id)): Path<(ContentType, Uuid)>,
+) -> Result<StatusCode, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Admin), &())?;
+
+  ModerationService::hide(
+    app_state.db.connection(),
+    &app_state.config.rating_prior,
+    auth_user.id,
+    content_type,
+    content_id,
+  )
+  .await
+  .map_err(moderation_error_response)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn restore_content(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path((content_type, content_id)): Path<(ContentType, Uuid)>,
+) -> Result<StatusCode, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Admin), &())?;
+
+  ModerationService::restore(
+    app_state.db.connection(),
+    &app_state.config.rating_prior,
+    auth_user.id,
+    content_type,
+    content_id,
+  )
+  .await
+  .map_err(moderation_error_response)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
+
+/// Ban a user, hide their content and sign them out everywhere
+pub async fn ban_user(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(user_id): Path<Uuid>,
+  request: Option<Json<BanRequest>>,
+) -> Result<Json<BanResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Admin), &())?;
+
+  let reason = request.and_then(|Json(request)| request.reason);
+  let user = ModerationService::ban(
+    app_state.db.connection(),
+    &app_state.config.rating_prior,
+    auth_user.id,
+    user_id,
+    reason.as_deref(),
+  )
+  .await
+  .map_err(moderation_error_response)?;
+
+  // Banning again is harmless, so a failure here can simply be retried
+  AuthSessionService::revoke_all_for_user(app_state.db.connection(), user_id, "banned")
+    .await
+    .map_err(|err| {
+      tracing::error!(error = %err, user_id = %user_id, "Failed to revoke sessions of banned user");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ProfileError {
+          error: "Failed to sign the user out".to_string(),
+        }),
+      )
+    })?;
+
+  Ok(Json(BanResponse {
+    user_id: user.id,
+    banned_at: user.banned_at.map(|banned_at| banned_at.to_rfc3339()),
+    ban_reason: user.ban_reason,
+  }))
+}
+
+pub async fn unban_user(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(user_id): Path<Uuid>,
+) -> Result<Json<BanResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Admin), &())?;
+
+  let user = ModerationService::unban(app_state.db.connection(), user_id)
+    .await
+    .map_err(moderation_error_response)?;
+
+  Ok(Json(BanResponse {
+    user_id: user.id,
+    banned_at: user.banned_at.map(|banned_at| banned_at.to_rfc3339()),
+    ban_reason: user.ban_reason,
+  }))
+}
@@ -0,0 +1,99 @@
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entities::{notifications, sea_orm_active_enums::NotificationKind};
+use crate::middleware::auth::{AuthError, AuthUser};
+use crate::services::notifications::NotificationService;
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationsQuery {
+  #[serde(rename = "unreadOnly")]
+  pub unread_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationResponse {
+  pub id: Uuid,
+  pub kind: NotificationKind,
+  pub message: String,
+  #[serde(rename = "sessionId")]
+  pub session_id: Option<Uuid>,
+  #[serde(rename = "readAt")]
+  pub read_at: Option<String>,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+}
+
+impl From<notifications::Model> for NotificationResponse {
+  fn from(notification: notifications::Model) -> Self {
+    Self {
+      id: notification.id,
+      kind: notification.kind,
+      message: notification.message,
+      session_id: notification.session_id,
+      read_at: notification.read_at.map(|read_at| read_at.to_rfc3339()),
+      created_at: notification.created_at.to_rfc3339(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationsResponse {
+  pub notifications: Vec<NotificationResponse>,
+}
+
+pub async fn list_notifications(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Query(query): Query<NotificationsQuery>,
+) -> Result<Json<NotificationsResponse>, (StatusCode, Json<AuthError>)> {
+  let notifications = NotificationService::list_for_user(
+    app_state.db.connection(),
+    auth_user.id,
+    query.unread_only.unwrap_or(false),
+  )
+  .await
+  .map_err(|err| {
+    tracing::error!(error = %err, "Failed to list notifications");
+    (
+      StatusCode::INTERNAL_SERVER_ERROR,
+      Json(AuthError::DatabaseError),
+    )
+  })?;
+
+  Ok(Json(NotificationsResponse {
+    notifications: notifications
+      .into_iter()
+      .map(NotificationResponse::from)
+      .collect(),
+  }))
+}
+
+pub async fn mark_notification_read(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(notification_id): Path<Uuid>,
+) -> Result<Json<NotificationResponse>, (StatusCode, Json<AuthError>)> {
+  let notification =
+    NotificationService::mark_read(app_state.db.connection(), auth_user.id, notification_id)
+      .await
+      .map_err(|err| {
+        tracing::error!(error = %err, "Failed to mark notification as read");
+        (
+          StatusCode::INTERNAL_SERVER_ERROR,
+          Json(AuthError::DatabaseError),
+        )
+      })?
+      .ok_or((
+        StatusCode::NOT_FOUND,
+        Json(AuthError::custom("Notification not found")),
+      ))?;
+
+  Ok(Json(notification.into()))
+}
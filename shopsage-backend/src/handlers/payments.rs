@@ -1,239 +1,341 @@
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
-    Json,
+  extract::{Query, State},
+  http::{HeaderMap, StatusCode},
+  Json,
 };
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
-use crate::middleware::auth::{extract_user, AuthError};
-use crate::services::solana::SolanaService;
-use crate::AppState;
+use crate::{
+  entities::{payments, sea_orm_active_enums::PaymentStatus},
+  handlers::{profiles::ProfileError, sessions::SessionResponse},
+  middleware::auth::AuthUser,
+  services::{
+    payments::{PaymentDetails, PaymentError, PaymentService, CURRENCY_SOL},
+    solana::SolanaService,
+  },
+  AppState,
+};
+
+/// Header carrying `PAYMENT_WEBHOOK_SECRET` on webhook deliveries
+const WEBHOOK_SECRET_HEADER: &str = "x-webhook-secret";
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize)]
+pub struct PaymentIntentRequest {
+  #[serde(rename = "sessionId", alias = "session_id")]
+  pub session_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
 pub struct ProcessPaymentRequest {
-    pub session_id: uuid::Uuid,
-    pub transaction_hash: String,
+  #[serde(rename = "sessionId", alias = "session_id")]
+  pub session_id: Uuid,
+  #[serde(rename = "txSignature", alias = "transaction_hash")]
+  pub tx_signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+  pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookRequest {
+  #[serde(rename = "txSignature")]
+  pub tx_signature: String,
+  pub status: PaymentStatus,
+  /// Why the transaction failed, if it did
+  pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentResponse {
+  pub id: Uuid,
+  #[serde(rename = "sessionId")]
+  pub session_id: Uuid,
+  #[serde(rename = "payerId")]
+  pub payer_id: Uuid,
+  #[serde(rename = "payeeId")]
+  pub payee_id: Uuid,
+  #[serde(rename = "payerWallet")]
+  pub payer_wallet: String,
+  #[serde(rename = "payeeWallet")]
+  pub payee_wallet: String,
+  pub lamports: i64,
+  /// `lamports` in `currency`, as a decimal string
+  pub amount: String,
+  pub currency: String,
+  pub status: PaymentStatus,
+  #[serde(rename = "txSignature")]
+  pub tx_signature: String,
+  #[serde(rename = "failureReason")]
+  pub failure_reason: Option<String>,
+  #[serde(rename = "confirmedAt")]
+  pub confirmed_at: Option<String>,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+  #[serde(rename = "updatedAt")]
+  pub updated_at: String,
+}
+
+impl From<payments::Model> for PaymentResponse {
+  fn from(payment: payments::Model) -> Self {
+    Self {
+      id: payment.id,
+      session_id: payment.session_id,
+      payer_id: payment.payer_id,
+      payee_id: payment.payee_id,
+      payer_wallet: payment.payer_wallet,
+      payee_wallet: payment.payee_wallet,
+      lamports: payment.lamports,
+      amount: PaymentService::to_sol(payment.lamports).to_string(),
+      currency: payment.currency,
+      status: payment.status,
+      tx_signature: payment.tx_signature,
+      failure_reason: payment.failure_reason,
+      confirmed_at: payment
+        .confirmed_at
+        .map(|confirmed_at| confirmed_at.to_rfc3339()),
+      created_at: payment.created_at.to_rfc3339(),
+      updated_at: payment.updated_at.to_rfc3339(),
+    }
+  }
+}
+
+/// How to build the transaction paying for a session
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentResponse {
+  #[serde(rename = "sessionId")]
+  pub session_id: Uuid,
+  /// Must be added to the transaction as a read-only, non-signer account
+  pub reference: String,
+  #[serde(rename = "payerWallet")]
+  pub payer_wallet: String,
+  #[serde(rename = "expertWallet")]
+  pub expert_wallet: String,
+  #[serde(rename = "treasuryWallet")]
+  pub treasury_wallet: String,
+  /// `shopsage_payment` program that may make the transfers instead
+  #[serde(rename = "programId")]
+  pub program_id: String,
+  pub lamports: u64,
+  #[serde(rename = "expertLamports")]
+  pub expert_lamports: u64,
+  #[serde(rename = "treasuryLamports")]
+  pub treasury_lamports: u64,
+  pub currency: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ProcessPaymentResponse {
-    pub payment: Payment,
-    pub session: Session,
+  pub payment: PaymentResponse,
+  pub session: SessionResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentHistoryItem {
+  #[serde(flatten)]
+  pub payment: PaymentResponse,
+  #[serde(rename = "expertName")]
+  pub expert_name: Option<String>,
+  #[serde(rename = "expertSpecialization")]
+  pub expert_specialization: Option<String>,
+  #[serde(rename = "sessionStartTime")]
+  pub session_start_time: Option<String>,
+}
+
+impl From<PaymentDetails> for PaymentHistoryItem {
+  fn from(details: PaymentDetails) -> Self {
+    Self {
+      payment: details.payment.into(),
+      expert_name: details.expert_name,
+      expert_specialization: details.expert_specialization,
+      session_start_time: details
+        .session_start_time
+        .map(|start_time| start_time.to_rfc3339()),
+    }
+  }
 }
 
 #[derive(Debug, Serialize)]
 pub struct PaymentHistoryResponse {
-    pub payments: Vec<PaymentWithDetails>,
+  pub payments: Vec<PaymentHistoryItem>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct WebhookRequest {
-    pub transaction_hash: String,
-    pub status: String,
+pub fn payment_error_response(err: PaymentError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    PaymentError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to process payment");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    PaymentError::UserNotFound(user_id) => {
+      tracing::error!(user_id = %user_id, "Session participant is missing");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    PaymentError::Unavailable(err) => {
+      tracing::warn!(error = %err, "Failed to verify payment transaction");
+      StatusCode::BAD_GATEWAY
+    }
+    PaymentError::NotYetConfirmed => StatusCode::ACCEPTED,
+    PaymentError::NotFound | PaymentError::SessionNotFound => StatusCode::NOT_FOUND,
+    PaymentError::NotSessionShopper => StatusCode::FORBIDDEN,
+    PaymentError::SessionNotPayable(_)
+    | PaymentError::AlreadyPaid
+    | PaymentError::DuplicateSignature
+    | PaymentError::MissingReference
+    | PaymentError::AlreadySettled(_) => StatusCode::CONFLICT,
+    PaymentError::InvalidSignature | PaymentError::InvalidAmount => StatusCode::BAD_REQUEST,
+    PaymentError::VerificationFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+/// The reference key and transfers the transaction paying for a session must contain
+pub async fn create_payment_intent(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Json(request): Json<PaymentIntentRequest>,
+) -> Result<Json<PaymentIntentResponse>, (StatusCode, Json<ProfileError>)> {
+  let (session, expected) = PaymentService::intent(
+    app_state.db.connection(),
+    &app_state.config.platform_treasury,
+    auth_user.id,
+    request.session_id,
+  )
+  .await
+  .map_err(payment_error_response)?;
+
+  let (expert_lamports, treasury_lamports) = SolanaService::split(expected.lamports);
+
+  Ok(Json(PaymentIntentResponse {
+    session_id: session.id,
+    reference: expected.reference,
+    payer_wallet: expected.payer,
+    expert_wallet: expected.expert,
+    treasury_wallet: expected.treasury,
+    program_id: app_state.config.payment_program_id.clone(),
+    lamports: expected.lamports,
+    expert_lamports,
+    treasury_lamports,
+    currency: CURRENCY_SOL.to_string(),
+  }))
 }
 
+/// Record the transaction paying for a session.
+///
+/// The transaction must include the reference from the session's payment intent. Responds 201
+/// once the payment is finalized on chain, or 202 while the transaction isn't finalized yet;
+/// submit it again or wait for the webhook to settle it. A transaction the chain doesn't show as
+/// confirmed yet gets a 202 without a payment and is not recorded. A signature already used for
+/// another payment gets a 409.
 pub async fn process_payment(
-    State(state): State<AppState>,
-    request: Request,
-    Json(payment_request): Json<ProcessPaymentRequest>,
-) -> Result<Json<ProcessPaymentResponse>, (StatusCode, Json<AuthError>)> {
-    let user = extract_user(&request)?;
-
-    // Validate input
-    payment_request.validate().map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(AuthError {
-                error: format!("Validation error: {}", e),
-            }),
-        )
-    })?;
-
-    // Get session to verify ownership and amount
-    let session = Session::find_by_id(&state.db.pool, payment_request.session_id)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError {
-                    error: "Database error".to_string(),
-                }),
-            )
-        })?
-        .ok_or((
-            StatusCode::NOT_FOUND,
-            Json(AuthError {
-                error: "Session not found".to_string(),
-            }),
-        ))?;
-
-    // Verify user is the shopper in the session
-    if session.shopper_id != user.id {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(AuthError {
-                error: "Access denied".to_string(),
-            }),
-        ));
-    }
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Json(request): Json<ProcessPaymentRequest>,
+) -> Result<(StatusCode, Json<ProcessPaymentResponse>), (StatusCode, Json<ProfileError>)> {
+  let solana = SolanaService::new(&app_state.config.solana_rpc_url)
+    .with_payment_program(&app_state.config.payment_program_id);
 
-    // Initialize Solana service
-    let solana_service = SolanaService::new(&state.config.solana_rpc_url);
-
-    // Verify transaction on Solana blockchain
-    let is_valid = solana_service
-        .verify_transaction(&payment_request.transaction_hash, &session.amount)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::BAD_REQUEST,
-                Json(AuthError {
-                    error: "Invalid transaction".to_string(),
-                }),
-            )
-        })?;
-
-    if !is_valid {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AuthError {
-                error: "Transaction verification failed".to_string(),
-            }),
-        ));
-    }
+  let (payment, session) = PaymentService::submit(
+    app_state.db.connection(),
+    &solana,
+    &app_state.config.platform_treasury,
+    auth_user.id,
+    request.session_id,
+    &request.tx_signature,
+  )
+  .await
+  .map_err(payment_error_response)?;
+
+  let status = match payment.status {
+    PaymentStatus::Confirmed => StatusCode::CREATED,
+    _ => StatusCode::ACCEPTED,
+  };
 
-    // Create payment record
-    let create_payment_request = CreatePaymentRequest {
-        session_id: payment_request.session_id,
-        amount: session.amount.clone(),
-        transaction_hash: Some(payment_request.transaction_hash.clone()),
-    };
-
-    let payment = Payment::create(&state.db.pool, create_payment_request)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError {
-                    error: "Failed to create payment".to_string(),
-                }),
-            )
-        })?;
-
-    // Update session payment status
-    let update_session_request = UpdateSessionRequest {
-        status: None,
-        end_time: None,
-        payment_status: Some(crate::models::session::PaymentStatus::Completed),
-        transaction_hash: Some(payment_request.transaction_hash),
-    };
-
-    let updated_session = Session::update(&state.db.pool, payment_request.session_id, update_session_request)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError {
-                    error: "Failed to update session".to_string(),
-                }),
-            )
-        })?;
-
-    // Update payment status to completed
-    let update_payment_request = UpdatePaymentRequest {
-        status: Some(PaymentStatus::Completed),
-        transaction_hash: None,
-    };
-
-    let final_payment = Payment::update(&state.db.pool, payment.id, update_payment_request)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError {
-                    error: "Failed to update payment".to_string(),
-                }),
-            )
-        })?;
-
-    Ok(Json(ProcessPaymentResponse {
-        payment: final_payment,
-        session: updated_session,
-    }))
+  Ok((
+    status,
+    Json(ProcessPaymentResponse {
+      payment: payment.into(),
+      session: session.into(),
+    }),
+  ))
 }
 
+/// Payments the signed-in user made or received, newest first
 pub async fn get_payment_history(
-    State(state): State<AppState>,
-    request: Request,
-) -> Result<Json<PaymentHistoryResponse>, (StatusCode, Json<AuthError>)> {
-    let user = extract_user(&request)?;
-
-    let payments = Payment::find_by_user_id(&state.db.pool, user.id)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError {
-                    error: "Failed to fetch payment history".to_string(),
-                }),
-            )
-        })?;
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Query(query): Query<HistoryQuery>,
+) -> Result<Json<PaymentHistoryResponse>, (StatusCode, Json<ProfileError>)> {
+  let payments = PaymentService::history(app_state.db.connection(), auth_user.id, query.limit)
+    .await
+    .map_err(|err| payment_error_response(err.into()))?;
 
-    Ok(Json(PaymentHistoryResponse { payments }))
+  Ok(Json(PaymentHistoryResponse {
+    payments: payments.into_iter().map(PaymentHistoryItem::from).collect(),
+  }))
 }
 
+/// Settlement notice from the transaction monitor, authenticated by a shared secret.
+///
+/// A reported confirmation is checked on chain first and responds 502 while our RPC node can't
+/// see the transaction yet, so the monitor retries it.
 pub async fn webhook(
-    State(state): State<AppState>,
-    Json(webhook_request): Json<WebhookRequest>,
-) -> Result<StatusCode, (StatusCode, Json<AuthError>)> {
-    // Find payment by transaction hash
-    let payments = sqlx::query!(
-        "SELECT id FROM payments WHERE transaction_hash = $1",
-        webhook_request.transaction_hash
-    )
-    .fetch_all(&state.db.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(AuthError {
-                error: "Database error".to_string(),
-            }),
-        )
-    })?;
-
-    if payments.is_empty() {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(AuthError {
-                error: "Payment not found".to_string(),
-            }),
-        ));
-    }
+  State(app_state): State<AppState>,
+  headers: HeaderMap,
+  Json(request): Json<WebhookRequest>,
+) -> Result<Json<PaymentResponse>, (StatusCode, Json<ProfileError>)> {
+  let Some(secret) = &app_state.config.payment_webhook_secret else {
+    return Err((
+      StatusCode::NOT_FOUND,
+      Json(ProfileError {
+        error: "Payment webhook is not enabled".to_string(),
+      }),
+    ));
+  };
+
+  // Compare digests so the check doesn't leak the secret through timing
+  let presented = headers
+    .get(WEBHOOK_SECRET_HEADER)
+    .map(|value| Sha256::digest(value.as_bytes()));
+  if presented != Some(Sha256::digest(secret.as_bytes())) {
+    return Err((
+      StatusCode::UNAUTHORIZED,
+      Json(ProfileError {
+        error: "Invalid webhook secret".to_string(),
+      }),
+    ));
+  }
+
+  if request.status == PaymentStatus::Pending {
+    return Err((
+      StatusCode::BAD_REQUEST,
+      Json(ProfileError {
+        error: "Status must be 'confirmed' or 'failed'".to_string(),
+      }),
+    ));
+  }
 
-    // Update payment status based on webhook
-    let status = match webhook_request.status.as_str() {
-        "completed" => PaymentStatus::Completed,
-        "failed" => PaymentStatus::Failed,
-        _ => PaymentStatus::Pending,
-    };
-
-    let update_request = UpdatePaymentRequest {
-        status: Some(status),
-        transaction_hash: None,
-    };
-
-    Payment::update(&state.db.pool, payments[0].id, update_request)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError {
-                    error: "Failed to update payment".to_string(),
-                }),
-            )
-        })?;
-
-    Ok(StatusCode::OK)
-}
\ No newline at end of file
+  let solana = SolanaService::new(&app_state.config.solana_rpc_url)
+    .with_payment_program(&app_state.config.payment_program_id);
+
+  let payment = PaymentService::settle(
+    app_state.db.connection(),
+    &solana,
+    &app_state.config.platform_treasury,
+    &request.tx_signature,
+    request.status,
+    request.reason.as_deref(),
+  )
+  .await
+  .map_err(payment_error_response)?;
+
+  Ok(Json(payment.into()))
+}
@@ -9,7 +9,16 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-  services::user_service::{UserProfile, UserService},
+  database::DatabaseError,
+  entities::sea_orm_active_enums::PricingModel,
+  middleware::auth::AuthUser,
+  services::{
+    categories::{CategoryError, CategoryService},
+    content_filter::FilterVerdict,
+    policy::{AccessDenied, RequireRole},
+    pricing::{PricingError, PricingService},
+    user_service::{ExpertProfile, ShopperProfile, UserProfile, UserRole, UserService},
+  },
   AppState,
 };
 
@@ -21,6 +30,12 @@ pub struct CreateExpertProfileRequest {
   pub session_rate: f64,
   #[serde(rename = "profileImageUrl")]
   pub profile_image_url: Option<String>,
+  #[serde(rename = "pricingModel")]
+  pub pricing_model: Option<PricingModel>,
+  #[serde(rename = "allowedDurations")]
+  pub allowed_durations: Option<Vec<i32>>,
+  /// Category slugs, primary first; replaces the expert's current categories
+  pub categories: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,6 +48,12 @@ pub struct UpdateExpertProfileRequest {
   pub profile_image_url: Option<String>,
   #[serde(rename = "isOnline")]
   pub is_online: Option<bool>,
+  #[serde(rename = "pricingModel")]
+  pub pricing_model: Option<PricingModel>,
+  #[serde(rename = "allowedDurations")]
+  pub allowed_durations: Option<Vec<i32>>,
+  /// Category slugs, primary first; replaces the expert's current categories
+  pub categories: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -79,6 +100,10 @@ pub struct ExpertProfileResponse {
   pub is_online: bool,
   #[serde(rename = "profileImageUrl")]
   pub profile_image_url: Option<String>,
+  #[serde(rename = "pricingModel")]
+  pub pricing_model: PricingModel,
+  #[serde(rename = "allowedDurations")]
+  pub allowed_durations: Vec<i32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -104,6 +129,24 @@ pub struct ShopperProfileResponse {
   pub average_rating: f64,
 }
 
+impl From<ShopperProfile> for ShopperProfileResponse {
+  fn from(profile: ShopperProfile) -> Self {
+    Self {
+      id: profile.id.to_string(),
+      user_id: profile.user_id.to_string(),
+      categories: profile.preferences.categories,
+      price_range_min: profile.preferences.price_range.min,
+      price_range_max: profile.preferences.price_range.max,
+      preferred_experts: profile.preferences.preferred_experts,
+      saved_experts: profile.saved_experts,
+      interests: profile.interests,
+      total_sessions: profile.consultation_history.total_sessions,
+      total_spent: profile.consultation_history.total_spent,
+      average_rating: profile.consultation_history.average_rating,
+    }
+  }
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserRoles {
   #[serde(rename = "canShop")]
@@ -142,6 +185,97 @@ pub struct ProfileError {
   pub error: String,
 }
 
+fn pricing_error_response(err: PricingError) -> (StatusCode, Json<ProfileError>) {
+  (
+    StatusCode::BAD_REQUEST,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+fn category_error_response(err: CategoryError) -> (StatusCode, Json<ProfileError>) {
+  let status = match err {
+    CategoryError::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    CategoryError::NotFound(_) => StatusCode::BAD_REQUEST,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+fn database_error_response(err: DatabaseError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    DatabaseError::DbError(err) => {
+      tracing::error!(error = %err, "Profile database operation failed");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    DatabaseError::ShopperProfileExists => StatusCode::CONFLICT,
+    DatabaseError::InvalidPriceRange => StatusCode::BAD_REQUEST,
+    _ => StatusCode::NOT_FOUND,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+/// Run a bio past the content filter, returning why it should be held for a moderator.
+///
+/// The hold itself is applied by `UserService` in the transaction that saves the bio.
+fn screen_bio(
+  app_state: &AppState,
+  bio: &str,
+) -> Result<Option<String>, (StatusCode, Json<ProfileError>)> {
+  match app_state.content_filter.check(bio) {
+    FilterVerdict::Allow => Ok(None),
+    FilterVerdict::Hold(reason) => Ok(Some(reason)),
+    FilterVerdict::Reject(reason) => Err((
+      StatusCode::BAD_REQUEST,
+      Json(ProfileError {
+        error: format!("Bio rejected: {}", reason),
+      }),
+    )),
+  }
+}
+
+impl From<ExpertProfile> for ExpertProfileResponse {
+  fn from(profile: ExpertProfile) -> Self {
+    Self {
+      id: profile.id,
+      user_id: profile.user_id,
+      specialization: profile.specialization,
+      bio: Some(profile.bio),
+      session_rate: profile.session_rate,
+      rating: profile.rating,
+      total_consultations: profile.total_consultations,
+      is_verified: profile.is_verified,
+      is_online: profile.is_online,
+      profile_image_url: profile.profile_image_url,
+      pricing_model: profile.pricing_model,
+      allowed_durations: profile.allowed_durations,
+    }
+  }
+}
+
+impl From<AccessDenied> for (StatusCode, Json<ProfileError>) {
+  fn from(denied: AccessDenied) -> Self {
+    (
+      StatusCode::FORBIDDEN,
+      Json(ProfileError {
+        error: denied.to_string(),
+      }),
+    )
+  }
+}
+
 #[axum::debug_handler]
 pub async fn create_expert_profile(
   State(app_state): State<AppState>,
@@ -162,23 +296,39 @@ pub async fn create_expert_profile(
     }
   };
 
+  let pricing = PricingService::update(payload.pricing_model, payload.allowed_durations)
+    .map_err(pricing_error_response)?;
+
+  let bio = payload.bio.unwrap_or_else(|| "".to_string());
+  let held_bio = screen_bio(&app_state, &bio)?;
+
+  // Unknown slugs are refused before anything is saved
+  let category_ids = match &payload.categories {
+    Some(slugs) => Some(
+      CategoryService::resolve_slugs(app_state.db.connection(), slugs)
+        .await
+        .map_err(category_error_response)?,
+    ),
+    None => None,
+  };
+
   let response = UserService::create_expert_profile_only(
     app_state.db.connection(),
+    &app_state.config.rating_prior,
     user_id,
     payload.specialization,
-    payload.bio.unwrap_or_else(|| "".to_string()),
+    bio,
+    held_bio.as_deref(),
     payload.session_rate,
     payload.profile_image_url,
+    pricing,
+    category_ids.as_deref(),
   )
   .await;
 
   match response {
     Ok(_) => {
-      let profile = match UserService::get_expert_profile(
-        app_state.db.connection(),
-        user_id,
-      )
-      .await
+      let profile = match UserService::get_expert_profile(app_state.db.connection(), user_id).await
       {
         Ok(Some(profile)) => profile,
         Ok(None) => {
@@ -210,6 +360,8 @@ pub async fn create_expert_profile(
         is_verified: profile.is_verified,
         is_online: profile.is_online,
         profile_image_url: profile.profile_image_url,
+        pricing_model: profile.pricing_model,
+        allowed_durations: profile.allowed_durations,
       };
 
       return Ok(Json(expert_profile));
@@ -228,8 +380,11 @@ pub async fn create_expert_profile(
 
 pub async fn get_expert_profile(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
   Extension(user): Extension<UserProfile>,
 ) -> Result<Json<ExpertProfileResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
   let user_id = match Uuid::from_str(&user.id) {
     Ok(id) => id,
     Err(_) => {
@@ -242,19 +397,18 @@ pub async fn get_expert_profile(
     }
   };
 
-  let expert_profile = match UserService::get_expert_profile(app_state.db.connection(), user_id)
-    .await
-  {
-    Ok(profile) => profile,
-    Err(err) => {
-      return Err((
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(ProfileError {
-          error: format!("Database error: {}", err),
-        }),
-      ))
-    }
-  };
+  let expert_profile =
+    match UserService::get_expert_profile(app_state.db.connection(), user_id).await {
+      Ok(profile) => profile,
+      Err(err) => {
+        return Err((
+          StatusCode::INTERNAL_SERVER_ERROR,
+          Json(ProfileError {
+            error: format!("Database error: {}", err),
+          }),
+        ))
+      }
+    };
 
   if let Some(profile) = expert_profile {
     let response = ExpertProfileResponse {
@@ -268,6 +422,8 @@ pub async fn get_expert_profile(
       is_verified: profile.is_verified,
       is_online: profile.is_online,
       profile_image_url: profile.profile_image_url,
+      pricing_model: profile.pricing_model,
+      allowed_durations: profile.allowed_durations,
     };
     Ok(Json(response))
   } else {
@@ -278,93 +434,70 @@ pub async fn get_expert_profile(
       }),
     ))
   }
-
-
 }
 
 pub async fn create_shopper_profile(
-  Extension(user): Extension<UserProfile>,
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
   Json(request): Json<CreateShopperProfileRequest>,
 ) -> Result<Json<ShopperProfileResponse>, (StatusCode, Json<ProfileError>)> {
-  // Mock response for now
-  let response = ShopperProfileResponse {
-    id: "shopper-123".to_string(),
-    user_id: user.id.clone(),
-    categories: request.categories,
-    price_range_min: request.price_range_min,
-    price_range_max: request.price_range_max,
-    preferred_experts: vec![],
-    saved_experts: vec![],
-    interests: request.interests,
-    total_sessions: 0,
-    total_spent: 0.0,
-    average_rating: 0.0,
-  };
+  let profile = UserService::create_shopper_profile_only(
+    app_state.db.connection(),
+    auth_user.id,
+    request.categories,
+    request.price_range_min,
+    request.price_range_max,
+    request.interests,
+  )
+  .await
+  .map_err(database_error_response)?;
 
-  Ok(Json(response))
+  Ok(Json(profile.into()))
 }
 
 pub async fn get_shopper_profile(
-  Extension(user): Extension<UserProfile>,
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
 ) -> Result<Json<ShopperProfileResponse>, (StatusCode, Json<ProfileError>)> {
-  // Mock response for now
-  let response = ShopperProfileResponse {
-    id: "shopper-123".to_string(),
-    user_id: user.id.clone(),
-    categories: vec!["Electronics".to_string(), "Fashion".to_string()],
-    price_range_min: 10.0,
-    price_range_max: 500.0,
-    preferred_experts: vec!["expert-1".to_string()],
-    saved_experts: vec!["expert-2".to_string(), "expert-3".to_string()],
-    interests: vec!["Tech".to_string(), "Style".to_string()],
-    total_sessions: 5,
-    total_spent: 250.0,
-    average_rating: 4.2,
-  };
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
 
-  Ok(Json(response))
+  let profile = UserService::get_shopper_profile(app_state.db.connection(), auth_user.id)
+    .await
+    .map_err(database_error_response)?
+    .ok_or_else(|| database_error_response(DatabaseError::ShopperProfileNotFound))?;
+
+  Ok(Json(profile.into()))
 }
 
 pub async fn get_complete_user_profile(
-  Extension(user): Extension<UserProfile>,
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
 ) -> Result<Json<CompleteUserProfileResponse>, (StatusCode, Json<ProfileError>)> {
-  // Mock response for now - in real implementation, fetch user and both profiles
+  let db = app_state.db.connection();
+
+  let user = UserService::find_by_id(db, auth_user.id)
+    .await
+    .map_err(database_error_response)?
+    .ok_or_else(|| database_error_response(DatabaseError::UserNotFound))?;
+
   let user_info = UserBasicInfo {
-    id: user.id.clone(),
-    wallet_address: user.wallet_address.clone(),
-    name: user.name.clone(),
-    email: user.email.clone().unwrap_or_default(),
-    created_at: user.created_at.clone(),
-    updated_at: user.updated_at.clone(),
+    id: user.id,
+    wallet_address: user.wallet_address,
+    name: user.name,
+    email: user.email.unwrap_or_default(),
+    created_at: user.created_at,
+    updated_at: user.updated_at,
   };
 
-  // Mock both profiles exist (dual role user)
-  let shopper_profile = Some(ShopperProfileResponse {
-    id: "shopper-123".to_string(),
-    user_id: user.id.clone(),
-    categories: vec!["Electronics".to_string(), "Fashion".to_string()],
-    price_range_min: 10.0,
-    price_range_max: 500.0,
-    preferred_experts: vec!["expert-1".to_string()],
-    saved_experts: vec!["expert-2".to_string()],
-    interests: vec!["Tech".to_string(), "Style".to_string()],
-    total_sessions: 5,
-    total_spent: 250.0,
-    average_rating: 4.2,
-  });
-
-  let expert_profile = Some(ExpertProfileResponse {
-    id: Uuid::new_v4(),
-    user_id: Uuid::from_str(&user.id).unwrap(),
-    specialization: "Tech Support".to_string(),
-    bio: Some("I help with technical issues".to_string()),
-    session_rate: 50.0,
-    rating: 4.5,
-    total_consultations: 10,
-    is_verified: true,
-    is_online: true,
-    profile_image_url: None,
-  });
+  let shopper_profile = UserService::get_shopper_profile(db, auth_user.id)
+    .await
+    .map_err(database_error_response)?
+    .map(ShopperProfileResponse::from);
+
+  let expert_profile = UserService::get_expert_profile(db, auth_user.id)
+    .await
+    .map_err(database_error_response)?
+    .map(ExpertProfileResponse::from);
 
   let roles = UserRoles {
     can_shop: shopper_profile.is_some(),
@@ -390,9 +523,12 @@ pub async fn get_complete_user_profile(
 #[axum::debug_handler]
 pub async fn update_expert_profile(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
   Extension(user): Extension<UserProfile>,
   Json(payload): Json<UpdateExpertProfileRequest>,
 ) -> Result<Json<ExpertProfileResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
   let user_id = Uuid::from_str(&user.id).ok();
 
   let user_id = match user_id {
@@ -407,24 +543,42 @@ pub async fn update_expert_profile(
     }
   };
 
+  let held_bio = match &payload.bio {
+    Some(bio) => screen_bio(&app_state, bio)?,
+    None => None,
+  };
+
+  // Unknown slugs are refused before anything is saved
+  let category_ids = match &payload.categories {
+    Some(slugs) => Some(
+      CategoryService::resolve_slugs(app_state.db.connection(), slugs)
+        .await
+        .map_err(category_error_response)?,
+    ),
+    None => None,
+  };
+
+  let pricing = PricingService::update(payload.pricing_model, payload.allowed_durations)
+    .map_err(pricing_error_response)?;
+
   let response = UserService::update_expert_profile(
     app_state.db.connection(),
+    &app_state.config.rating_prior,
     user_id,
     payload.specialization,
     payload.bio,
+    held_bio.as_deref(),
     payload.session_rate,
     payload.profile_image_url,
     payload.is_online,
+    pricing,
+    category_ids.as_deref(),
   )
   .await;
 
   match response {
     Ok(_) => {
-      let profile = match UserService::get_expert_profile(
-        app_state.db.connection(),
-        user_id,
-      )
-      .await
+      let profile = match UserService::get_expert_profile(app_state.db.connection(), user_id).await
       {
         Ok(Some(profile)) => profile,
         Ok(None) => {
@@ -456,6 +610,8 @@ pub async fn update_expert_profile(
         is_verified: profile.is_verified,
         is_online: profile.is_online,
         profile_image_url: profile.profile_image_url,
+        pricing_model: profile.pricing_model,
+        allowed_durations: profile.allowed_durations,
       };
 
       return Ok(Json(expert_profile));
@@ -526,62 +682,21 @@ pub async fn update_user_profile(
 #[axum::debug_handler]
 pub async fn update_shopper_profile(
   State(app_state): State<AppState>,
-  Extension(user): Extension<UserProfile>,
+  auth_user: AuthUser,
   Json(payload): Json<UpdateShopperProfileRequest>,
 ) -> Result<Json<ShopperProfileResponse>, (StatusCode, Json<ProfileError>)> {
-  let user_id = Uuid::from_str(&user.id).ok();
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
 
-  let user_id = match user_id {
-    Some(id) => id,
-    None => {
-      return Err((
-        StatusCode::BAD_REQUEST,
-        Json(ProfileError {
-          error: "Invalid user ID".to_string(),
-        }),
-      ))
-    }
-  };
-
-  // Clone the values before using them in the service call
-  let categories_clone = payload.categories.clone();
-  let interests_clone = payload.interests.clone();
-
-  let response = UserService::update_shopper_profile(
+  let profile = UserService::update_shopper_profile(
     app_state.db.connection(),
-    user_id,
+    auth_user.id,
     payload.categories,
     payload.price_range_min,
     payload.price_range_max,
     payload.interests,
   )
-  .await;
+  .await
+  .map_err(database_error_response)?;
 
-  match response {
-    Ok(_) => {
-      // Mock response for now - would fetch updated profile from DB
-      let response = ShopperProfileResponse {
-        id: user_id.to_string(),
-        user_id: user.id.clone(),
-        categories: categories_clone.unwrap_or_default(),
-        price_range_min: payload.price_range_min.unwrap_or(0.0),
-        price_range_max: payload.price_range_max.unwrap_or(1000.0),
-        preferred_experts: vec![],
-        saved_experts: vec![],
-        interests: interests_clone.unwrap_or_default(),
-        total_sessions: 0,
-        total_spent: 0.0,
-        average_rating: 0.0,
-      };
-      Ok(Json(response))
-    }
-    Err(err) => {
-      return Err((
-        StatusCode::BAD_REQUEST,
-        Json(ProfileError {
-          error: err.to_string(),
-        }),
-      ))
-    }
-  }
-}
\ No newline at end of file
+  Ok(Json(profile.into()))
+}
@@ -0,0 +1,245 @@
+use axum::{
+  extract::{Path, Query, State},
+  http::StatusCode,
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  entities::{reviews, sea_orm_active_enums::ContentType},
+  handlers::{moderation::moderation_error_response, profiles::ProfileError},
+  middleware::auth::AuthUser,
+  services::{
+    moderation::ModerationService,
+    policy::{OwnerOrAdmin, RequireRole},
+    reviews::{ReviewError, ReviewService, ReviewWithAuthor},
+    user_service::UserRole,
+  },
+  AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateReviewRequest {
+  #[serde(rename = "sessionId")]
+  pub session_id: Uuid,
+  /// 1 to 5 stars
+  pub rating: i16,
+  #[serde(default)]
+  pub comment: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplyRequest {
+  pub reply: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportRequest {
+  pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewsQuery {
+  pub limit: Option<u64>,
+  pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewResponse {
+  pub id: Uuid,
+  #[serde(rename = "sessionId")]
+  pub session_id: Uuid,
+  #[serde(rename = "expertId")]
+  pub expert_id: Uuid,
+  #[serde(rename = "shopperId")]
+  pub shopper_id: Uuid,
+  #[serde(rename = "shopperName", skip_serializing_if = "Option::is_none")]
+  pub shopper_name: Option<String>,
+  pub rating: i16,
+  pub comment: String,
+  #[serde(rename = "expertReply")]
+  pub expert_reply: Option<String>,
+  #[serde(rename = "repliedAt")]
+  pub replied_at: Option<String>,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+}
+
+impl From<reviews::Model> for ReviewResponse {
+  fn from(review: reviews::Model) -> Self {
+    Self {
+      id: review.id,
+      session_id: review.session_id,
+      expert_id: review.expert_id,
+      shopper_id: review.shopper_id,
+      shopper_name: None,
+      rating: review.rating,
+      comment: review.comment,
+      expert_reply: review.expert_reply,
+      replied_at: review.replied_at.map(|replied_at| replied_at.to_rfc3339()),
+      created_at: review.created_at.to_rfc3339(),
+    }
+  }
+}
+
+impl From<ReviewWithAuthor> for ReviewResponse {
+  fn from(entry: ReviewWithAuthor) -> Self {
+    Self {
+      shopper_name: Some(entry.shopper_name),
+      ..entry.review.into()
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewListResponse {
+  pub reviews: Vec<ReviewResponse>,
+  pub total: u64,
+  #[serde(rename = "nextCursor")]
+  pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportResponse {
+  pub id: Uuid,
+  #[serde(rename = "reviewId")]
+  pub review_id: Uuid,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+}
+
+fn review_error_response(err: ReviewError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    ReviewError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to manage reviews");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    ReviewError::NotFound | ReviewError::SessionNotFound => StatusCode::NOT_FOUND,
+    ReviewError::NotSessionShopper | ReviewError::NotReviewedExpert => StatusCode::FORBIDDEN,
+    ReviewError::AlreadyReviewed => StatusCode::CONFLICT,
+    _ => StatusCode::BAD_REQUEST,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+pub async fn create_review(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Json(request): Json<CreateReviewRequest>,
+) -> Result<(StatusCode, Json<ReviewResponse>), (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
+
+  let review = ReviewService::create(
+    app_state.db.connection(),
+    app_state.content_filter.as_ref(),
+    &app_state.config.rating_prior,
+    auth_user.id,
+    request.session_id,
+    request.rating,
+    &request.comment,
+  )
+  .await
+  .map_err(review_error_response)?;
+
+  Ok((StatusCode::CREATED, Json(review.into())))
+}
+
+/// Public, newest first
+pub async fn list_expert_reviews(
+  State(app_state): State<AppState>,
+  Path(expert_id): Path<Uuid>,
+  Query(query): Query<ReviewsQuery>,
+) -> Result<Json<ReviewListResponse>, (StatusCode, Json<ProfileError>)> {
+  let page = ReviewService::list_for_expert(
+    app_state.db.connection(),
+    expert_id,
+    query.limit,
+    query.cursor.as_deref(),
+  )
+  .await
+  .map_err(review_error_response)?;
+
+  Ok(Json(ReviewListResponse {
+    reviews: page.reviews.into_iter().map(ReviewResponse::from).collect(),
+    total: page.total,
+    next_cursor: page.next_cursor,
+  }))
+}
+
+pub async fn reply_to_review(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(review_id): Path<Uuid>,
+  Json(request): Json<ReplyRequest>,
+) -> Result<Json<ReviewResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  let review = ReviewService::reply(
+    app_state.db.connection(),
+    auth_user.id,
+    review_id,
+    &request.reply,
+  )
+  .await
+  .map_err(review_error_response)?;
+
+  Ok(Json(review.into()))
+}
+
+pub async fn report_review(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(review_id): Path<Uuid>,
+  request: Option<Json<ReportRequest>>,
+) -> Result<(StatusCode, Json<ReportResponse>), (StatusCode, Json<ProfileError>)> {
+  let reason = request.and_then(|Json(request)| request.reason);
+
+  let report = ModerationService::report(
+    app_state.db.connection(),
+    auth_user.id,
+    ContentType::Review,
+    review_id,
+    reason.as_deref(),
+  )
+  .await
+  .map_err(moderation_error_response)?;
+
+  Ok((
+    StatusCode::CREATED,
+    Json(ReportResponse {
+      id: report.id,
+      review_id: report.content_id,
+      created_at: report.created_at.to_rfc3339(),
+    }),
+  ))
+}
+
+/// Reviews can be withdrawn by their author or taken down by an admin
+pub async fn delete_review(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(review_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ProfileError>)> {
+  let review = ReviewService::get(app_state.db.connection(), review_id)
+    .await
+    .map_err(review_error_response)?;
+
+  auth_user.authorize(OwnerOrAdmin, &review.shopper_id)?;
+
+  ReviewService::remove(
+    app_state.db.connection(),
+    &app_state.config.rating_prior,
+    &review,
+  )
+  .await
+  .map_err(review_error_response)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,136 @@
+use axum::{
+  extract::{Path, State},
+  http::StatusCode,
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  handlers::profiles::ProfileError,
+  middleware::auth::AuthUser,
+  services::{
+    policy::RequireRole,
+    saved_experts::{SavedExpert, SavedExpertError, SavedExpertService},
+    user_service::UserRole,
+  },
+  AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SaveExpertRequest {
+  /// Mark the expert as a favourite; left unchanged when omitted
+  pub preferred: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedExpertResponse {
+  #[serde(rename = "expertId")]
+  pub expert_id: Uuid,
+  pub name: String,
+  pub specialization: String,
+  #[serde(rename = "sessionRate")]
+  pub session_rate: f64,
+  pub rating: f64,
+  #[serde(rename = "isOnline")]
+  pub is_online: bool,
+  #[serde(rename = "profileImageUrl")]
+  pub profile_image_url: Option<String>,
+  #[serde(rename = "isPreferred")]
+  pub is_preferred: bool,
+  #[serde(rename = "savedAt")]
+  pub saved_at: String,
+}
+
+impl From<SavedExpert> for SavedExpertResponse {
+  fn from(saved: SavedExpert) -> Self {
+    Self {
+      expert_id: saved.expert.id,
+      name: saved.user.name,
+      specialization: saved.expert.specialization,
+      session_rate: saved.expert.session_rate.to_string().parse().unwrap_or(0.0),
+      rating: saved.expert.rating.to_string().parse().unwrap_or(0.0),
+      is_online: saved.expert.is_online,
+      profile_image_url: if saved.expert.profile_image_url.is_empty() {
+        None
+      } else {
+        Some(saved.expert.profile_image_url)
+      },
+      is_preferred: saved.is_preferred,
+      saved_at: saved.saved_at.to_rfc3339(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedExpertListResponse {
+  #[serde(rename = "savedExperts")]
+  pub saved_experts: Vec<SavedExpertResponse>,
+}
+
+fn saved_expert_error_response(err: SavedExpertError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    SavedExpertError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to manage saved experts");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    _ => StatusCode::NOT_FOUND,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+pub async fn list_saved_experts(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+) -> Result<Json<SavedExpertListResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
+
+  let saved = SavedExpertService::list(app_state.db.connection(), auth_user.id)
+    .await
+    .map_err(saved_expert_error_response)?;
+
+  Ok(Json(SavedExpertListResponse {
+    saved_experts: saved.into_iter().map(SavedExpertResponse::from).collect(),
+  }))
+}
+
+pub async fn save_expert(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(expert_id): Path<Uuid>,
+  request: Option<Json<SaveExpertRequest>>,
+) -> Result<Json<SavedExpertResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
+
+  let preferred = request.and_then(|Json(request)| request.preferred);
+  let saved = SavedExpertService::save(
+    app_state.db.connection(),
+    auth_user.id,
+    expert_id,
+    preferred,
+  )
+  .await
+  .map_err(saved_expert_error_response)?;
+
+  Ok(Json(saved.into()))
+}
+
+pub async fn unsave_expert(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(expert_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Shopper), &())?;
+
+  SavedExpertService::unsave(app_state.db.connection(), auth_user.id, expert_id)
+    .await
+    .map_err(saved_expert_error_response)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
@@ -8,8 +8,16 @@ use sea_orm::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::entities::{prelude::*, sessions, users, expert_profiles};
-use crate::middleware::auth::AuthError;
+use crate::entities::{
+  expert_profiles, prelude::*, sea_orm_active_enums::SessionStatus, sessions, users,
+};
+use crate::middleware::auth::{AuthError, AuthUser};
+use crate::middleware::client_ip::ClientIp;
+use crate::services::booking::{BookingError, BookingService};
+use crate::services::policy::{OwnerOrAdmin, SessionPolicy};
+use crate::services::pricing::PricingService;
+use crate::services::session_events::{AuditContext, SessionEventService};
+use crate::services::session_lifecycle::{SessionLifecycle, SessionLifecycleError};
 use crate::AppState;
 
 #[derive(Debug, Serialize)]
@@ -19,16 +27,46 @@ pub struct SessionResponse {
   pub expert_id: String,
   #[serde(rename = "shopperId")]
   pub shopper_id: String,
-  pub status: String,
+  pub status: SessionStatus,
   pub amount: String,
+  /// Booked start, as requested when the session was created
   #[serde(rename = "startTime")]
   pub start_time: Option<String>,
   #[serde(rename = "endTime")]
   pub end_time: Option<String>,
+  /// When the session actually started and ended
+  #[serde(rename = "startedAt")]
+  pub started_at: Option<String>,
+  #[serde(rename = "endedAt")]
+  pub ended_at: Option<String>,
   #[serde(rename = "createdAt")]
   pub created_at: String,
   #[serde(rename = "updatedAt")]
   pub updated_at: String,
+  #[serde(rename = "durationMinutes")]
+  pub duration_minutes: Option<i32>,
+  #[serde(rename = "quotedPrice")]
+  pub quoted_price: Option<String>,
+}
+
+impl From<sessions::Model> for SessionResponse {
+  fn from(session: sessions::Model) -> Self {
+    Self {
+      id: session.id.to_string(),
+      expert_id: session.expert_id.to_string(),
+      shopper_id: session.shopper_id.to_string(),
+      status: session.status,
+      amount: session.amount.to_string(),
+      start_time: session.booked_start.map(|dt| dt.to_rfc3339()),
+      end_time: session.booked_end.map(|dt| dt.to_rfc3339()),
+      started_at: session.start_time.map(|dt| dt.to_rfc3339()),
+      ended_at: session.end_time.map(|dt| dt.to_rfc3339()),
+      created_at: session.created_at.to_rfc3339(),
+      updated_at: session.updated_at.to_rfc3339(),
+      duration_minutes: session.duration_minutes,
+      quoted_price: session.quoted_price.map(|price| price.to_string()),
+    }
+  }
 }
 
 #[derive(Debug, Serialize)]
@@ -36,26 +74,63 @@ pub struct SessionsResponse {
   pub sessions: Vec<SessionResponse>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct SessionEventResponse {
+  pub id: String,
+  #[serde(rename = "sessionId")]
+  pub session_id: String,
+  #[serde(rename = "actorId")]
+  pub actor_id: Option<String>,
+  #[serde(rename = "oldStatus")]
+  pub old_status: Option<SessionStatus>,
+  #[serde(rename = "newStatus")]
+  pub new_status: SessionStatus,
+  pub reason: Option<String>,
+  #[serde(rename = "clientIp", skip_serializing_if = "Option::is_none")]
+  pub client_ip: Option<String>,
+  #[serde(rename = "txSignature")]
+  pub tx_signature: Option<String>,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionEventsResponse {
+  pub events: Vec<SessionEventResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSessionRequest {
+  pub status: Option<SessionStatus>,
+  pub reason: Option<String>,
+  #[serde(rename = "txSignature")]
+  pub tx_signature: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateSessionRequest {
   #[serde(rename = "expertId")]
   pub expert_id: String,
-  #[serde(rename = "shopperId")]  
+  #[serde(rename = "shopperId")]
   pub shopper_id: String,
   #[serde(rename = "startTime")]
   pub start_time: String, // ISO 8601 format
-  pub amount: String, // BigDecimal as string
+  #[serde(rename = "durationMinutes")]
+  pub duration_minutes: Option<i32>, // Defaults to the expert's shortest duration
+  pub amount: Option<String>, // BigDecimal as string, must match the server quote if given
 }
 
 pub async fn create_session(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  ClientIp(client_ip): ClientIp,
   payload: Result<Json<CreateSessionRequest>, axum::extract::rejection::JsonRejection>,
 ) -> Result<Json<SessionResponse>, (StatusCode, Json<AuthError>)> {
   let Json(request) = payload.map_err(|rejection| {
     tracing::error!("JSON parsing error from React Native: {}", rejection);
     (
       StatusCode::BAD_REQUEST,
-      Json(AuthError::custom(&format!("Invalid JSON: {}. Expected fields: expertId, shopperId, startTime, amount", rejection))),
+      Json(AuthError::custom(&format!("Invalid JSON: {}. Expected fields: expertId, shopperId, startTime, durationMinutes, amount", rejection))),
     )
   })?;
 
@@ -64,14 +139,13 @@ pub async fn create_session(
     expert_id_len = request.expert_id.len(),
     expert_id_debug = ?request.expert_id,
     shopper_id = %request.shopper_id,
-    shopper_id_len = request.shopper_id.len(), 
+    shopper_id_len = request.shopper_id.len(),
     shopper_id_debug = ?request.shopper_id,
     start_time = %request.start_time,
     start_time_len = request.start_time.len(),
     start_time_debug = ?request.start_time,
-    amount = %request.amount,
-    amount_len = request.amount.len(),
-    amount_debug = ?request.amount,
+    duration_minutes = ?request.duration_minutes,
+    amount = ?request.amount,
     "Creating new session - detailed request data"
   );
 
@@ -90,21 +164,31 @@ pub async fn create_session(
     )
   })?;
 
+  // Shoppers can only book for themselves
+  auth_user.authorize(OwnerOrAdmin, &shopper_user_id)?;
+
   // Parse start time from ISO 8601 string
   let start_time = DateTime::parse_from_rfc3339(&request.start_time).map_err(|_| {
     (
       StatusCode::BAD_REQUEST,
-      Json(AuthError::custom("Invalid startTime format, expected ISO 8601")),
+      Json(AuthError::custom(
+        "Invalid startTime format, expected ISO 8601",
+      )),
     )
   })?;
 
   // Parse amount from string to decimal
-  let amount = request.amount.parse::<rust_decimal::Decimal>().map_err(|_| {
-    (
-      StatusCode::BAD_REQUEST,
-      Json(AuthError::custom("Invalid amount format")),
-    )
-  })?;
+  let supplied_amount = request
+    .amount
+    .as_deref()
+    .map(str::parse::<rust_decimal::Decimal>)
+    .transpose()
+    .map_err(|_| {
+      (
+        StatusCode::BAD_REQUEST,
+        Json(AuthError::custom("Invalid amount format")),
+      )
+    })?;
 
   // Look up expert profile and get the user_id
   let expert_profile = expert_profiles::Entity::find_by_id(expert_profile_id)
@@ -118,8 +202,8 @@ pub async fn create_session(
       )
     })?;
 
-  let expert_user_id = match expert_profile {
-    Some(profile) => profile.user_id,
+  let expert_profile = match expert_profile {
+    Some(profile) => profile,
     None => {
       tracing::warn!(expert_profile_id = %expert_profile_id, "Expert profile not found");
       return Err((
@@ -149,26 +233,81 @@ pub async fn create_session(
     ));
   }
 
+  // The price is always computed here; a client amount is only accepted if it agrees
+  let quote = PricingService::quote(&expert_profile, request.duration_minutes)
+    .and_then(|quote| match supplied_amount {
+      Some(amount) => PricingService::check_amount(&quote, amount).map(|_| quote),
+      None => Ok(quote),
+    })
+    .map_err(|err| {
+      tracing::warn!(error = %err, expert_profile_id = %expert_profile_id, "Rejected session price");
+      (
+        StatusCode::BAD_REQUEST,
+        Json(AuthError::custom(&err.to_string())),
+      )
+    })?;
+  let expert_user_id = expert_profile.user_id;
+  let amount = quote.price;
+
   let session_id = Uuid::new_v4();
   let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
-  
-  // Set end_time to +5 minutes after start_time
-  let end_time = start_time + Duration::minutes(5);
-  
+
+  let end_time = start_time + Duration::minutes(quote.duration_minutes.into());
+
+  // Must fall inside the expert's weekly availability and not clash with their other sessions
+  BookingService::check_slot(
+    app_state.db.connection(),
+    expert_user_id,
+    start_time.with_timezone(&Utc),
+    end_time.with_timezone(&Utc),
+  )
+  .await
+  .map_err(booking_error_response)?;
+
   let session = sessions::ActiveModel {
     id: Set(session_id),
     expert_id: Set(expert_user_id),
     shopper_id: Set(shopper_user_id),
-    status: Set("pending".to_string()),
+    status: Set(SessionStatus::Pending),
     amount: Set(amount),
-    start_time: Set(Some(start_time.with_timezone(&FixedOffset::east_opt(0).unwrap()))),
-    end_time: Set(Some(end_time.with_timezone(&FixedOffset::east_opt(0).unwrap()))),
+    start_time: Set(None),
+    end_time: Set(None),
+    booked_start: Set(Some(
+      start_time.with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    )),
+    booked_end: Set(Some(
+      end_time.with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    )),
     notes: Set(Some("".to_string())),
+    duration_minutes: Set(Some(quote.duration_minutes)),
+    quoted_price: Set(Some(quote.price)),
+    payment_reference: Set(None),
     created_at: Set(now),
     updated_at: Set(now),
   };
 
-  let created_session = session.insert(app_state.db.connection()).await.map_err(|err| {
+  let audit = AuditContext {
+    actor_id: Some(auth_user.id),
+    reason: Some("created".to_string()),
+    client_ip,
+    tx_signature: None,
+  };
+
+  // The session row and its first audit event are committed together
+  let created_session = async {
+    let txn = app_state.db.connection().begin().await?;
+    let created = session.insert(&txn).await?;
+    SessionEventService::record(&txn, created.id, None, created.status, &audit).await?;
+    txn.commit().await?;
+    Ok::<_, DbErr>(created)
+  }
+  .await
+  .map_err(|err| {
+    // Lost a race against a concurrent booking for the same slot
+    if BookingService::is_overlap_violation(&err) {
+      return booking_error_response(BookingError::SlotTaken);
+    }
+
     tracing::error!(
       error = %err,
       expert_user_id = %expert_user_id,
@@ -183,23 +322,14 @@ pub async fn create_session(
     )
   })?;
 
-  let response = SessionResponse {
-    id: created_session.id.to_string(),
-    expert_id: created_session.expert_id.to_string(),
-    shopper_id: created_session.shopper_id.to_string(),
-    status: created_session.status,
-    amount: created_session.amount.to_string(),
-    start_time: created_session.start_time.map(|dt| dt.to_rfc3339()),
-    end_time: created_session.end_time.map(|dt| dt.to_rfc3339()),
-    created_at: created_session.created_at.to_rfc3339(),
-    updated_at: created_session.updated_at.to_rfc3339(),
-  };
+  let response = SessionResponse::from(created_session);
 
   Ok(Json(response))
 }
 
 pub async fn get_session(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
   Path(session_id): Path<Uuid>,
 ) -> Result<Json<SessionResponse>, (StatusCode, Json<AuthError>)> {
   let session = Sessions::find_by_id(session_id)
@@ -214,25 +344,21 @@ pub async fn get_session(
 
   match session {
     Some(session_data) => {
-      let response = SessionResponse {
-        id: session_data.id.to_string(),
-        expert_id: session_data.expert_id.to_string(),
-        shopper_id: session_data.shopper_id.to_string(),
-        status: session_data.status,
-        amount: session_data.amount.to_string(),
-        start_time: session_data.start_time.map(|dt| dt.to_rfc3339()),
-        end_time: session_data.end_time.map(|dt| dt.to_rfc3339()),
-        created_at: session_data.created_at.to_rfc3339(),
-        updated_at: session_data.updated_at.to_rfc3339(),
-      };
+      auth_user.authorize(SessionPolicy::View, &session_data)?;
+
+      let response = SessionResponse::from(session_data);
       Ok(Json(response))
     }
-    None => Err((StatusCode::NOT_FOUND, Json(AuthError::InvalidToken))),
+    None => Err((
+      StatusCode::NOT_FOUND,
+      Json(AuthError::custom("Session not found")),
+    )),
   }
 }
 
 pub async fn list_sessions_by_expert(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
   Path(expert_id): Path<String>,
 ) -> Result<Json<SessionsResponse>, (StatusCode, Json<AuthError>)> {
   let expert_uuid = Uuid::parse_str(&expert_id).map_err(|_| {
@@ -242,6 +368,8 @@ pub async fn list_sessions_by_expert(
     )
   })?;
 
+  auth_user.authorize(OwnerOrAdmin, &expert_uuid)?;
+
   let sessions = Sessions::find()
     .filter(sessions::Column::ExpertId.eq(expert_uuid))
     .order_by_desc(sessions::Column::CreatedAt)
@@ -254,20 +382,8 @@ pub async fn list_sessions_by_expert(
       )
     })?;
 
-  let session_responses: Vec<SessionResponse> = sessions
-    .into_iter()
-    .map(|session| SessionResponse {
-      id: session.id.to_string(),
-      expert_id: session.expert_id.to_string(),
-      shopper_id: session.shopper_id.to_string(),
-      status: session.status,
-      amount: session.amount.to_string(),
-      start_time: session.start_time.map(|dt| dt.to_rfc3339()),
-      end_time: session.end_time.map(|dt| dt.to_rfc3339()),
-      created_at: session.created_at.to_rfc3339(),
-      updated_at: session.updated_at.to_rfc3339(),
-    })
-    .collect();
+  let session_responses: Vec<SessionResponse> =
+    sessions.into_iter().map(SessionResponse::from).collect();
 
   Ok(Json(SessionsResponse {
     sessions: session_responses,
@@ -276,6 +392,7 @@ pub async fn list_sessions_by_expert(
 
 pub async fn list_sessions_by_shopper(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
   Path(shopper_id): Path<String>,
 ) -> Result<Json<SessionsResponse>, (StatusCode, Json<AuthError>)> {
   let shopper_uuid = Uuid::parse_str(&shopper_id).map_err(|_| {
@@ -285,6 +402,8 @@ pub async fn list_sessions_by_shopper(
     )
   })?;
 
+  auth_user.authorize(OwnerOrAdmin, &shopper_uuid)?;
+
   let sessions = Sessions::find()
     .filter(sessions::Column::ShopperId.eq(shopper_uuid))
     .order_by_desc(sessions::Column::CreatedAt)
@@ -297,20 +416,8 @@ pub async fn list_sessions_by_shopper(
       )
     })?;
 
-  let session_responses: Vec<SessionResponse> = sessions
-    .into_iter()
-    .map(|session| SessionResponse {
-      id: session.id.to_string(),
-      expert_id: session.expert_id.to_string(),
-      shopper_id: session.shopper_id.to_string(),
-      status: session.status,
-      amount: session.amount.to_string(),
-      start_time: session.start_time.map(|dt| dt.to_rfc3339()),
-      end_time: session.end_time.map(|dt| dt.to_rfc3339()),
-      created_at: session.created_at.to_rfc3339(),
-      updated_at: session.updated_at.to_rfc3339(),
-    })
-    .collect();
+  let session_responses: Vec<SessionResponse> =
+    sessions.into_iter().map(SessionResponse::from).collect();
 
   Ok(Json(SessionsResponse {
     sessions: session_responses,
@@ -319,9 +426,34 @@ pub async fn list_sessions_by_shopper(
 
 pub async fn update_session(
   State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  ClientIp(client_ip): ClientIp,
   Path(session_id): Path<Uuid>,
-  Json(request): Json<serde_json::Value>,
+  payload: Result<Json<UpdateSessionRequest>, axum::extract::rejection::JsonRejection>,
 ) -> Result<Json<SessionResponse>, (StatusCode, Json<AuthError>)> {
+  let Json(request) = payload.map_err(|rejection| {
+    (
+      StatusCode::BAD_REQUEST,
+      Json(AuthError::custom(&format!(
+        "Invalid session update: {}",
+        rejection
+      ))),
+    )
+  })?;
+
+  if let Some(signature) = &request.tx_signature {
+    let is_signature = bs58::decode(signature)
+      .into_vec()
+      .map(|bytes| bytes.len() == 64)
+      .unwrap_or(false);
+    if !is_signature {
+      return Err((
+        StatusCode::BAD_REQUEST,
+        Json(AuthError::custom("Invalid txSignature")),
+      ));
+    }
+  }
+
   let session = Sessions::find_by_id(session_id)
     .one(app_state.db.connection())
     .await
@@ -333,35 +465,121 @@ pub async fn update_session(
     })?;
 
   if let Some(session_data) = session {
-    let mut active_model: sessions::ActiveModel = session_data.into();
+    auth_user.authorize(SessionPolicy::View, &session_data)?;
+
+    let updated_session = match request.status {
+      Some(status) => {
+        auth_user.authorize(SessionPolicy::ChangeStatus(status), &session_data)?;
+
+        let audit = AuditContext {
+          actor_id: Some(auth_user.id),
+          reason: request.reason,
+          client_ip,
+          tx_signature: request.tx_signature,
+        };
+
+        SessionLifecycle::transition(app_state.db.connection(), &session_data, status, &audit)
+          .await
+          .map_err(lifecycle_error_response)?
+      }
+      None => session_data,
+    };
 
-    if let Some(status) = request.get("status").and_then(|v| v.as_str()) {
-      active_model.status = Set(status.to_string());
-    }
+    let response = SessionResponse::from(updated_session);
+
+    Ok(Json(response))
+  } else {
+    Err((
+      StatusCode::NOT_FOUND,
+      Json(AuthError::custom("Session not found")),
+    ))
+  }
+}
+
+pub async fn list_session_events(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(session_id): Path<Uuid>,
+) -> Result<Json<SessionEventsResponse>, (StatusCode, Json<AuthError>)> {
+  let session = Sessions::find_by_id(session_id)
+    .one(app_state.db.connection())
+    .await
+    .map_err(|_| {
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError::DatabaseError),
+      )
+    })?
+    .ok_or((
+      StatusCode::NOT_FOUND,
+      Json(AuthError::custom("Session not found")),
+    ))?;
 
-    active_model.updated_at = Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
+  auth_user.authorize(SessionPolicy::View, &session)?;
 
-    let updated_session = active_model.update(app_state.db.connection()).await.map_err(|_| {
+  let events = SessionEventService::list_for_session(app_state.db.connection(), session_id)
+    .await
+    .map_err(|err| {
+      tracing::error!(error = %err, session_id = %session_id, "Failed to load session events");
       (
         StatusCode::INTERNAL_SERVER_ERROR,
         Json(AuthError::DatabaseError),
       )
     })?;
 
-    let response = SessionResponse {
-      id: updated_session.id.to_string(),
-      expert_id: updated_session.expert_id.to_string(),
-      shopper_id: updated_session.shopper_id.to_string(),
-      status: updated_session.status,
-      amount: updated_session.amount.to_string(),
-      start_time: updated_session.start_time.map(|dt| dt.to_rfc3339()),
-      end_time: updated_session.end_time.map(|dt| dt.to_rfc3339()),
-      created_at: updated_session.created_at.to_rfc3339(),
-      updated_at: updated_session.updated_at.to_rfc3339(),
-    };
+  // Client addresses are only disclosed to admins
+  let show_client_ip = auth_user.is_admin();
+
+  Ok(Json(SessionEventsResponse {
+    events: events
+      .into_iter()
+      .map(|event| SessionEventResponse {
+        id: event.id.to_string(),
+        session_id: event.session_id.to_string(),
+        actor_id: event.actor_id.map(|id| id.to_string()),
+        old_status: event.old_status,
+        new_status: event.new_status,
+        reason: event.reason,
+        client_ip: event.client_ip.filter(|_| show_client_ip),
+        tx_signature: event.tx_signature,
+        created_at: event.created_at.to_rfc3339(),
+      })
+      .collect(),
+  }))
+}
 
-    Ok(Json(response))
-  } else {
-    Err((StatusCode::NOT_FOUND, Json(AuthError::InvalidToken)))
+fn booking_error_response(err: BookingError) -> (StatusCode, Json<AuthError>) {
+  match err {
+    BookingError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to check expert availability");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError::DatabaseError),
+      )
+    }
+    BookingError::InPast => (
+      StatusCode::BAD_REQUEST,
+      Json(AuthError::custom(&err.to_string())),
+    ),
+    err => (
+      StatusCode::CONFLICT,
+      Json(AuthError::custom(&err.to_string())),
+    ),
+  }
+}
+
+fn lifecycle_error_response(err: SessionLifecycleError) -> (StatusCode, Json<AuthError>) {
+  match err {
+    SessionLifecycleError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to update session status");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError::DatabaseError),
+      )
+    }
+    err => (
+      StatusCode::CONFLICT,
+      Json(AuthError::custom(&err.to_string())),
+    ),
   }
 }
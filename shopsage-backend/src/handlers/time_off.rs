@@ -0,0 +1,213 @@
+use axum::{
+  extract::{Path, State},
+  http::StatusCode,
+  Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  entities::{expert_time_off, sea_orm_active_enums::TimeOffRecurrence},
+  handlers::profiles::ProfileError,
+  middleware::auth::AuthUser,
+  services::{
+    policy::RequireRole,
+    time_off::{TimeOffChange, TimeOffError, TimeOffInput, TimeOffService},
+    user_service::UserRole,
+  },
+  AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TimeOffRequest {
+  #[serde(rename = "startsAt")]
+  pub starts_at: String,
+  #[serde(rename = "endsAt")]
+  pub ends_at: String,
+  /// IANA timezone recurring entries repeat in; defaults to the expert's availability timezone
+  pub timezone: Option<String>,
+  pub recurrence: Option<TimeOffRecurrence>,
+  #[serde(rename = "repeatUntil")]
+  pub repeat_until: Option<String>,
+  pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeOffResponse {
+  pub id: Uuid,
+  #[serde(rename = "startsAt")]
+  pub starts_at: String,
+  #[serde(rename = "endsAt")]
+  pub ends_at: String,
+  pub timezone: String,
+  pub recurrence: TimeOffRecurrence,
+  #[serde(rename = "repeatUntil")]
+  pub repeat_until: Option<String>,
+  pub reason: Option<String>,
+  #[serde(rename = "createdAt")]
+  pub created_at: String,
+  #[serde(rename = "updatedAt")]
+  pub updated_at: String,
+}
+
+impl From<expert_time_off::Model> for TimeOffResponse {
+  fn from(time_off: expert_time_off::Model) -> Self {
+    Self {
+      id: time_off.id,
+      starts_at: time_off.starts_at.to_rfc3339(),
+      ends_at: time_off.ends_at.to_rfc3339(),
+      timezone: time_off.timezone,
+      recurrence: time_off.recurrence,
+      repeat_until: time_off.repeat_until.map(|until| until.to_rfc3339()),
+      reason: time_off.reason,
+      created_at: time_off.created_at.to_rfc3339(),
+      updated_at: time_off.updated_at.to_rfc3339(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeOffListResponse {
+  #[serde(rename = "timeOff")]
+  pub time_off: Vec<TimeOffResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeOffChangeResponse {
+  #[serde(rename = "timeOff")]
+  pub time_off: TimeOffResponse,
+  /// Pending bookings cancelled because they fall inside the time off
+  #[serde(rename = "cancelledSessions")]
+  pub cancelled_sessions: Vec<Uuid>,
+}
+
+impl From<TimeOffChange> for TimeOffChangeResponse {
+  fn from(change: TimeOffChange) -> Self {
+    Self {
+      time_off: change.time_off.into(),
+      cancelled_sessions: change.cancelled_sessions,
+    }
+  }
+}
+
+fn time_off_error_response(err: TimeOffError) -> (StatusCode, Json<ProfileError>) {
+  let status = match &err {
+    TimeOffError::DbError(err) => {
+      tracing::error!(error = %err, "Failed to manage expert time off");
+      StatusCode::INTERNAL_SERVER_ERROR
+    }
+    TimeOffError::NotFound => StatusCode::NOT_FOUND,
+    _ => StatusCode::BAD_REQUEST,
+  };
+
+  (
+    status,
+    Json(ProfileError {
+      error: err.to_string(),
+    }),
+  )
+}
+
+async fn parse_request(
+  app_state: &AppState,
+  user_id: Uuid,
+  request: TimeOffRequest,
+) -> Result<TimeOffInput, (StatusCode, Json<ProfileError>)> {
+  let parse_time = |value: &str, field: &str| {
+    DateTime::parse_from_rfc3339(value)
+      .map(|time| time.with_timezone(&Utc))
+      .map_err(|_| {
+        (
+          StatusCode::BAD_REQUEST,
+          Json(ProfileError {
+            error: format!("Invalid {} format, expected ISO 8601", field),
+          }),
+        )
+      })
+  };
+
+  let starts_at = parse_time(&request.starts_at, "startsAt")?;
+  let ends_at = parse_time(&request.ends_at, "endsAt")?;
+  let repeat_until = request
+    .repeat_until
+    .as_deref()
+    .map(|until| parse_time(until, "repeatUntil"))
+    .transpose()?;
+
+  let timezone = match request.timezone {
+    Some(timezone) => timezone,
+    None => TimeOffService::default_timezone(app_state.db.connection(), user_id)
+      .await
+      .map_err(|err| time_off_error_response(err.into()))?,
+  };
+
+  Ok(TimeOffInput {
+    starts_at,
+    ends_at,
+    timezone,
+    recurrence: request.recurrence.unwrap_or(TimeOffRecurrence::Once),
+    repeat_until,
+    reason: request.reason,
+  })
+}
+
+pub async fn list_time_off(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+) -> Result<Json<TimeOffListResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  let time_off = TimeOffService::list_for_expert(app_state.db.connection(), auth_user.id)
+    .await
+    .map_err(|err| time_off_error_response(err.into()))?;
+
+  Ok(Json(TimeOffListResponse {
+    time_off: time_off.into_iter().map(TimeOffResponse::from).collect(),
+  }))
+}
+
+pub async fn create_time_off(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Json(request): Json<TimeOffRequest>,
+) -> Result<(StatusCode, Json<TimeOffChangeResponse>), (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  let input = parse_request(&app_state, auth_user.id, request).await?;
+  let change = TimeOffService::create(app_state.db.connection(), auth_user.id, input)
+    .await
+    .map_err(time_off_error_response)?;
+
+  Ok((StatusCode::CREATED, Json(change.into())))
+}
+
+pub async fn update_time_off(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(time_off_id): Path<Uuid>,
+  Json(request): Json<TimeOffRequest>,
+) -> Result<Json<TimeOffChangeResponse>, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  let input = parse_request(&app_state, auth_user.id, request).await?;
+  let change = TimeOffService::update(app_state.db.connection(), auth_user.id, time_off_id, input)
+    .await
+    .map_err(time_off_error_response)?;
+
+  Ok(Json(change.into()))
+}
+
+pub async fn delete_time_off(
+  State(app_state): State<AppState>,
+  auth_user: AuthUser,
+  Path(time_off_id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, Json<ProfileError>)> {
+  auth_user.authorize(RequireRole(UserRole::Expert), &())?;
+
+  TimeOffService::delete(app_state.db.connection(), auth_user.id, time_off_id)
+    .await
+    .map_err(time_off_error_response)?;
+
+  Ok(StatusCode::NO_CONTENT)
+}
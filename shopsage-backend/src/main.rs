@@ -1,7 +1,7 @@
 use axum::{
   extract::Path,
-  middleware::from_fn,
-  routing::{get, post, put},
+  middleware::{from_fn, from_fn_with_state},
+  routing::{delete, get, post, put},
   Json, Router,
 };
 use clap::{Parser, Subcommand};
@@ -19,14 +19,26 @@ mod services;
 
 use config::Config;
 use database::Database;
-use handlers::{auth, experts, profiles, sessions};
+use handlers::{
+  auth, availability, categories, experts, moderation, notifications, payments, profiles, reviews,
+  saved_experts, sessions, time_off,
+};
 use middleware::logging;
 use seeders::Seeder;
+use services::content_filter::{ContentFilter, WordListFilter};
+use services::ratings::RatingService;
 use services::solana::SolanaService;
+use services::stats::StatsService;
+use services::token::TokenService;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
   pub db: Database,
+  pub config: Config,
+  pub tokens: Arc<TokenService>,
+  /// Screens reviews and expert bios before they are published
+  pub content_filter: Arc<dyn ContentFilter>,
 }
 
 #[derive(Parser)]
@@ -49,6 +61,8 @@ enum Commands {
   ClearSeeds,
   /// Reset database (migrate + seed)
   Reset,
+  /// Rebuild shopper and expert statistics from sessions, payments and reviews
+  RecomputeStats,
 }
 
 #[tokio::main]
@@ -56,8 +70,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
   // Initialize tracing with enhanced logging
   tracing_subscriber::registry()
     .with(
-      tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| "shopsage_backend=debug,tower_http=debug,axum=debug,sea_orm=debug".into()),
+      tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        "shopsage_backend=debug,tower_http=debug,axum=debug,sea_orm=debug".into()
+      }),
     )
     .with(
       tracing_subscriber::fmt::layer()
@@ -98,6 +113,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
       Seeder::run_all(database.connection()).await?;
       tracing::info!("✅ Database reset completed!");
     }
+    Commands::RecomputeStats => {
+      let (shoppers, experts) = StatsService::recompute_all(database.connection()).await?;
+      tracing::info!(
+        "✅ Recomputed stats for {} shoppers and {} experts",
+        shoppers,
+        experts
+      );
+      let rated = RatingService::recompute_all(database.connection(), &config.rating_prior).await?;
+      tracing::info!("✅ Recomputed ratings for {} experts", rated);
+    }
   }
 
   Ok(())
@@ -107,8 +132,17 @@ async fn start_server(
   database: Database,
   config: Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
+  // Fail fast on a bad JWT setup rather than on the first login
+  let tokens = Arc::new(TokenService::from_config(&config)?);
+  let content_filter = Arc::new(WordListFilter::from_config(&config)?);
+
   // Create application state
-  let state = AppState { db: database };
+  let state = AppState {
+    db: database,
+    config,
+    tokens,
+    content_filter,
+  };
 
   // Build application router
   let app = Router::new()
@@ -118,10 +152,15 @@ async fn start_server(
       "/api/solana/validate-address/{address}",
       get(validate_solana_address),
     )
-    .nest("/api/auth", auth_routes())
-    .nest("/api/experts", expert_routes())
-    .nest("/api/profiles", profile_routes())
-    .nest("/api/sessions", session_routes())
+    .nest("/api/auth", auth_routes(state.clone()))
+    .nest("/api/experts", expert_routes(state.clone()))
+    .nest("/api/categories", category_routes())
+    .nest("/api/profiles", profile_routes(state.clone()))
+    .nest("/api/sessions", session_routes(state.clone()))
+    .nest("/api/notifications", notification_routes(state.clone()))
+    .nest("/api/reviews", review_routes(state.clone()))
+    .nest("/api/admin/moderation", moderation_routes(state.clone()))
+    .nest("/api/payments", payment_routes(state.clone()))
     .with_state(state)
     .layer(from_fn(logging::logging_middleware))
     .layer(CorsLayer::permissive());
@@ -167,37 +206,136 @@ async fn validate_solana_address(Path(address): Path<String>) -> Json<Value> {
   }))
 }
 
-fn profile_routes() -> Router<AppState> {
+fn profile_routes(state: AppState) -> Router<AppState> {
   Router::new()
     .route("/complete", get(profiles::get_complete_user_profile))
     .route("/profile", put(profiles::update_user_profile))
     .route("/shopper", post(profiles::create_shopper_profile))
     .route("/shopper", get(profiles::get_shopper_profile))
     .route("/shopper", put(profiles::update_shopper_profile))
+    .route(
+      "/shopper/saved-experts",
+      get(saved_experts::list_saved_experts),
+    )
+    .route(
+      "/shopper/saved-experts/{expert_id}",
+      put(saved_experts::save_expert),
+    )
+    .route(
+      "/shopper/saved-experts/{expert_id}",
+      delete(saved_experts::unsave_expert),
+    )
     .route("/expert", post(profiles::create_expert_profile))
     .route("/expert", get(profiles::get_expert_profile))
     .route("/expert", put(profiles::update_expert_profile))
-    .layer(from_fn(middleware::auth::auth_middleware))
+    .route("/expert/availability", get(availability::get_availability))
+    .route(
+      "/expert/availability",
+      put(availability::replace_availability),
+    )
+    .route(
+      "/expert/availability",
+      delete(availability::clear_availability),
+    )
+    .route("/expert/time-off", get(time_off::list_time_off))
+    .route("/expert/time-off", post(time_off::create_time_off))
+    .route("/expert/time-off/{id}", put(time_off::update_time_off))
+    .route("/expert/time-off/{id}", delete(time_off::delete_time_off))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware))
 }
 
-fn session_routes() -> Router<AppState> {
+fn session_routes(state: AppState) -> Router<AppState> {
   Router::new()
     .route("/", post(sessions::create_session))
-    .route("/expert/{expert_id}", get(sessions::list_sessions_by_expert))
-    .route("/shopper/{shopper_id}", get(sessions::list_sessions_by_shopper))
+    .route(
+      "/expert/{expert_id}",
+      get(sessions::list_sessions_by_expert),
+    )
+    .route(
+      "/shopper/{shopper_id}",
+      get(sessions::list_sessions_by_shopper),
+    )
     .route("/{id}", get(sessions::get_session))
     .route("/{id}", put(sessions::update_session))
+    .route("/{id}/events", get(sessions::list_session_events))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware))
 }
 
-fn auth_routes() -> Router<AppState> {
+fn notification_routes(state: AppState) -> Router<AppState> {
+  Router::new()
+    .route("/", get(notifications::list_notifications))
+    .route("/{id}/read", put(notifications::mark_notification_read))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware))
+}
+
+fn review_routes(state: AppState) -> Router<AppState> {
+  Router::new()
+    .route("/", post(reviews::create_review))
+    .route("/{id}", delete(reviews::delete_review))
+    .route("/{id}/reply", put(reviews::reply_to_review))
+    .route("/{id}/report", post(reviews::report_review))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware))
+}
+
+fn moderation_routes(state: AppState) -> Router<AppState> {
+  Router::new()
+    .route("/reports", get(moderation::list_reports))
+    .route(
+      "/content/{content_type}/{id}/hide",
+      post(moderation::hide_content),
+    )
+    .route(
+      "/content/{content_type}/{id}/restore",
+      post(moderation::restore_content),
+    )
+    .route("/users/{id}/ban", post(moderation::ban_user))
+    .route("/users/{id}/ban", delete(moderation::unban_user))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware))
+}
+
+fn payment_routes(state: AppState) -> Router<AppState> {
+  let user_routes = Router::new()
+    .route("/intent", post(payments::create_payment_intent))
+    .route("/process", post(payments::process_payment))
+    .route("/history", get(payments::get_payment_history))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware));
+
+  Router::new()
+    .route("/webhook", post(payments::webhook))
+    .merge(user_routes)
+}
+
+fn auth_routes(state: AppState) -> Router<AppState> {
+  let session_routes = Router::new()
+    .route("/logout", post(auth::logout))
+    .route("/logout-all", post(auth::logout_all))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware));
+
   Router::new()
     .route("/register", post(auth::register_user))
-    .route("/login", post(auth::login_user))
+    .route("/nonce", post(auth::request_nonce))
+    .route("/verify", post(auth::verify_login))
+    .route("/refresh", post(auth::refresh_token))
+    .merge(session_routes)
 }
 
-fn expert_routes() -> Router<AppState> {
+fn expert_routes(state: AppState) -> Router<AppState> {
+  let shopper_routes = Router::new()
+    .route("/recommended", get(experts::get_recommended_experts))
+    .route("/{id}/report", post(moderation::report_expert_bio))
+    .layer(from_fn_with_state(state, middleware::auth::auth_middleware));
+
   Router::new()
     .route("/list", get(experts::list_experts))
     .route("/search", get(experts::search_experts))
     .route("/{id}", get(experts::get_expert_by_id))
+    .route("/{id}/reviews", get(reviews::list_expert_reviews))
+    .route("/{id}/slots", get(experts::get_expert_slots))
+    .merge(shopper_routes)
+}
+
+fn category_routes() -> Router<AppState> {
+  Router::new()
+    .route("/", get(categories::list_categories))
+    .route("/{slug}/experts", get(categories::list_category_experts))
 }
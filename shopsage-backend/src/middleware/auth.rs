@@ -1,25 +1,22 @@
 use axum::{
-  extract::Request,
-  http::{header, StatusCode},
+  extract::{FromRequestParts, Request, State},
+  http::{header, request::Parts, StatusCode},
   middleware::Next,
   response::Response,
   Json,
 };
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
-use serde::{Deserialize, Serialize};
+use sea_orm::EntityTrait;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 use uuid::Uuid;
 
-use crate::services::user_service::UserProfile;
+use crate::entities::prelude::Users;
+use crate::services::auth_session::AuthSessionService;
+use crate::services::policy::{AccessDenied, Policy};
+use crate::services::token::Claims;
+use crate::services::user_service::{Roles, UserRole, UserService};
+use crate::AppState;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-  pub wallet_address: String,
-  pub user_id: Uuid,
-  pub exp: usize,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(untagged)]
+#[derive(Debug)]
 pub enum AuthError {
   InvalidToken,
   DatabaseError,
@@ -32,31 +29,141 @@ impl AuthError {
       error: message.to_string(),
     }
   }
+
+  pub fn message(&self) -> &str {
+    match self {
+      AuthError::InvalidToken => "Invalid or expired token",
+      AuthError::DatabaseError => "Database error",
+      AuthError::Custom { error } => error,
+    }
+  }
+}
+
+// Every variant renders as `{ "error": "..." }`, like the other handler error bodies
+impl Serialize for AuthError {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut body = serializer.serialize_struct("AuthError", 1)?;
+    body.serialize_field("error", self.message())?;
+    body.end()
+  }
+}
+
+impl From<AccessDenied> for (StatusCode, Json<AuthError>) {
+  fn from(denied: AccessDenied) -> Self {
+    (
+      StatusCode::FORBIDDEN,
+      Json(AuthError::custom(&denied.to_string())),
+    )
+  }
+}
+
+/// The authenticated caller, loaded from the database by `auth_middleware`.
+///
+/// Use it as an extractor in any handler behind the auth layer.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+  pub id: Uuid,
+  pub session_id: Uuid,
+  pub roles: Roles,
+}
+
+impl AuthUser {
+  pub fn has_role(&self, role: UserRole) -> bool {
+    self.roles.has(role)
+  }
+
+  pub fn is_admin(&self) -> bool {
+    self.roles.admin
+  }
+
+  /// Check `policy` for this caller against `resource`
+  pub fn authorize<R: ?Sized>(
+    &self,
+    policy: impl Policy<R>,
+    resource: &R,
+  ) -> Result<(), AccessDenied> {
+    policy.evaluate(self, resource)
+  }
+}
+
+impl<S> FromRequestParts<S> for AuthUser
+where
+  S: Send + Sync,
+{
+  type Rejection = (StatusCode, Json<AuthError>);
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    parts.extensions.get::<AuthUser>().cloned().ok_or((
+      StatusCode::UNAUTHORIZED,
+      Json(AuthError::custom("User not found in request")),
+    ))
+  }
 }
 
 pub async fn auth_middleware(
+  State(app_state): State<AppState>,
   mut request: Request,
   next: Next,
 ) -> Result<Response, (StatusCode, Json<AuthError>)> {
   let token = extract_token(&request)?;
 
-  // For now, just verify the token structure is valid
-  // In a full implementation, you'd verify against the database
-  let claims = verify_basic_token(&token)?;
-
-  // Create a mock user profile from the claims
-  // In a real implementation, you'd fetch the user from the database
-  let user = UserProfile {
-    id: claims.user_id.to_string(),
-    wallet_address: claims.wallet_address.clone(),
-    name: "Mock User".to_string(), // Would come from database
-    email: Some("user@example.com".to_string()), // Would come from database
-    created_at: "2024-01-01T00:00:00Z".to_string(), // Would come from database
-    updated_at: "2024-01-01T00:00:00Z".to_string(), // Would come from database
+  let claims = verify_token(&app_state, &token)?;
+
+  // Access tokens stop working as soon as their session is revoked
+  let session_active = AuthSessionService::is_active(app_state.db.connection(), claims.sid)
+    .await
+    .map_err(|err| {
+      tracing::error!(error = %err, session_id = %claims.sid, "Failed to check auth session");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError::DatabaseError),
+      )
+    })?;
+  if !session_active {
+    return Err((
+      StatusCode::UNAUTHORIZED,
+      Json(AuthError::custom("Session has been revoked")),
+    ));
+  }
+
+  // Load the real user once for both the profile and the roles; tokens of deleted users are
+  // rejected
+  let user = Users::find_by_id(claims.sub)
+    .one(app_state.db.connection())
+    .await
+    .map_err(|err| {
+      tracing::error!(error = %err, user_id = %claims.sub, "Failed to load authenticated user");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError::DatabaseError),
+      )
+    })?
+    .ok_or((
+      StatusCode::UNAUTHORIZED,
+      Json(AuthError::custom("User no longer exists")),
+    ))?;
+
+  let roles = UserService::roles_of(app_state.db.connection(), &user)
+    .await
+    .map_err(|err| {
+      tracing::error!(error = %err, user_id = %claims.sub, "Failed to load user roles");
+      (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AuthError::DatabaseError),
+      )
+    })?;
+
+  let auth_user = AuthUser {
+    id: claims.sub,
+    session_id: claims.sid,
+    roles,
   };
 
-  // Insert the user into request extensions
-  request.extensions_mut().insert(user);
+  // Insert the user and the typed caller into request extensions
+  request
+    .extensions_mut()
+    .insert(UserService::profile_from_model(&user));
+  request.extensions_mut().insert(auth_user);
 
   Ok(next.run(request).await)
 }
@@ -81,49 +188,12 @@ fn extract_token(request: &Request) -> Result<String, (StatusCode, Json<AuthErro
   Ok(auth_header.trim_start_matches("Bearer ").to_string())
 }
 
-fn verify_basic_token(token: &str) -> Result<Claims, (StatusCode, Json<AuthError>)> {
-  // Use a default secret for now - in production this would come from config
-  let secret = "your-secret-key";
-  let validation = Validation::new(Algorithm::HS256);
-  let token_data = decode::<Claims>(
-    token,
-    &DecodingKey::from_secret(secret.as_ref()),
-    &validation,
-  )
-  .map_err(|_| {
-    (
-      StatusCode::UNAUTHORIZED,
-      Json(AuthError::InvalidToken),
-    )
-  })?;
-
-  Ok(token_data.claims)
-}
-
-pub fn generate_token(
-  wallet_address: &str,
-  user_id: Uuid,
-  secret: &str,
-) -> Result<String, jsonwebtoken::errors::Error> {
-  let expiration = chrono::Utc::now()
-    .checked_add_signed(chrono::Duration::days(7))
-    .expect("valid timestamp")
-    .timestamp();
-
-  let claims = Claims {
-    wallet_address: wallet_address.to_string(),
-    user_id,
-    exp: expiration as usize,
-  };
-
-  let header = Header::new(Algorithm::HS256);
-  encode(&header, &claims, &EncodingKey::from_secret(secret.as_ref()))
-}
-
-// Helper function to extract user from request extensions
-pub fn extract_user(request: &Request) -> Result<&UserProfile, (StatusCode, Json<AuthError>)> {
-  request.extensions().get::<UserProfile>().ok_or((
-    StatusCode::UNAUTHORIZED,
-    Json(AuthError::custom("User not found in request")),
-  ))
+fn verify_token(
+  app_state: &AppState,
+  token: &str,
+) -> Result<Claims, (StatusCode, Json<AuthError>)> {
+  app_state.tokens.verify(token).map_err(|err| {
+    tracing::debug!(error = %err, "Rejected access token");
+    (StatusCode::UNAUTHORIZED, Json(AuthError::InvalidToken))
+  })
 }
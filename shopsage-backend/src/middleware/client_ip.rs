@@ -0,0 +1,115 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+  extract::{ConnectInfo, FromRequestParts},
+  http::{request::Parts, HeaderMap},
+};
+
+use crate::AppState;
+
+/// Best-effort address of the caller, for audit records.
+///
+/// Uses the peer address of the connection. Only when that peer is one of `TRUSTED_PROXIES` is
+/// the address it forwarded in `X-Forwarded-For` or `X-Real-IP` used instead; anything that
+/// doesn't parse as an IP address falls back to the peer.
+#[derive(Debug, Clone, Default)]
+pub struct ClientIp(pub Option<String>);
+
+impl FromRequestParts<AppState> for ClientIp {
+  type Rejection = Infallible;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &AppState,
+  ) -> Result<Self, Self::Rejection> {
+    let peer = parts
+      .extensions
+      .get::<ConnectInfo<SocketAddr>>()
+      .map(|ConnectInfo(addr)| addr.ip());
+
+    Ok(ClientIp(
+      Self::resolve(peer, &parts.headers, &state.config.trusted_proxies).map(|ip| ip.to_string()),
+    ))
+  }
+}
+
+impl ClientIp {
+  fn resolve(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpAddr],
+  ) -> Option<IpAddr> {
+    if !peer.is_some_and(|peer| trusted_proxies.contains(&peer)) {
+      return peer;
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    // Proxies append to X-Forwarded-For, so the client is the last hop none of ours added
+    let forwarded = match header("x-forwarded-for") {
+      Some(hops) => hops
+        .rsplit(',')
+        .map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .find(|hop| !hop.is_some_and(|ip| trusted_proxies.contains(&ip)))
+        .flatten(),
+      None => header("x-real-ip").and_then(|ip| ip.trim().parse().ok()),
+    };
+
+    forwarded.or(peer)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+      headers.insert(*name, value.parse().unwrap());
+    }
+    headers
+  }
+
+  #[test]
+  fn test_forwarded_headers_need_a_trusted_peer() {
+    let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+    let client: IpAddr = "203.0.113.7".parse().unwrap();
+    let forwarded = headers(&[("x-forwarded-for", "198.51.100.1, 203.0.113.7")]);
+
+    assert_eq!(
+      ClientIp::resolve(Some(client), &forwarded, &[proxy]),
+      Some(client)
+    );
+    assert_eq!(
+      ClientIp::resolve(Some(proxy), &forwarded, &[proxy]),
+      Some(client)
+    );
+    assert_eq!(
+      ClientIp::resolve(
+        Some(proxy),
+        &headers(&[("x-real-ip", "203.0.113.7")]),
+        &[proxy]
+      ),
+      Some(client)
+    );
+  }
+
+  #[test]
+  fn test_unparseable_forwarded_address_falls_back_to_peer() {
+    let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+    let overlong = "1".repeat(64);
+
+    for value in ["not-an-ip", overlong.as_str(), ""] {
+      assert_eq!(
+        ClientIp::resolve(
+          Some(proxy),
+          &headers(&[("x-forwarded-for", value)]),
+          &[proxy]
+        ),
+        Some(proxy)
+      );
+    }
+  }
+}
@@ -1,11 +1,17 @@
 use chrono::{FixedOffset, NaiveTime, Utc};
 use rust_decimal::{prelude::FromPrimitive, Decimal};
 use sea_orm::*;
+use serde_json::json;
 use uuid::Uuid;
 
 use super::user_seeder::UserSeeder;
 use crate::database::DatabaseResult;
-use crate::entities::{expert_availability, expert_profiles, expert_stats, prelude::*};
+use crate::entities::{
+  categories, expert_availability, expert_categories, expert_profiles, expert_stats, prelude::*,
+  sea_orm_active_enums::PricingModel,
+};
+use crate::services::pricing::SUPPORTED_DURATIONS;
+use crate::services::ratings::{RatingAggregate, RatingPrior};
 
 pub struct ExpertSeeder;
 
@@ -31,6 +37,9 @@ impl ExpertSeeder {
     // Seed expert availability
     Self::seed_expert_availability(db, &expert_ids).await?;
 
+    // File experts under the categories seeded by the migrations
+    Self::seed_expert_categories(db, &expert_ids).await?;
+
     tracing::info!("✅ Successfully seeded all expert data");
     Ok(())
   }
@@ -39,20 +48,31 @@ impl ExpertSeeder {
     db: &DatabaseConnection,
     expert_ids: &[Uuid],
   ) -> DatabaseResult<()> {
+    // Seeded ratings come without reviews, so they are ranked as unrated experts until
+    // `recompute-stats` rebuilds them
+    let unrated_score = RatingAggregate::default().score(&RatingPrior::default());
+
     let expert_profiles_data = vec![
       expert_profiles::ActiveModel {
         id: Set(Uuid::new_v4()),
         user_id: Set(expert_ids[0]),
         specialization: Set("Fashion & Style Consultant".to_string()),
         bio: Set("Dr. Sarah Wilson is a renowned fashion consultant.".to_string()),
+        bio_hidden_at: Set(None),
+        bio_held: Set(false),
         session_rate: Set(Decimal::from(85)),
         rating: Set(Decimal::from_f32(4.8).unwrap()),
+        rating_count: Set(0),
+        rating_sum: Set(0),
+        rating_score: Set(unrated_score),
         total_consultations: Set(247),
         is_verified: Set(true),
         is_online: Set(true),
         profile_image_url: Set(
           "https://images.unsplash.com/photo-1494790108755-2616b056ae6c?w=300".to_string(),
         ),
+        pricing_model: Set(PricingModel::Flat),
+        allowed_durations: Set(json!(SUPPORTED_DURATIONS)),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -61,14 +81,21 @@ impl ExpertSeeder {
         user_id: Set(expert_ids[1]),
         specialization: Set("Technology & Gadgets Specialist".to_string()),
         bio: Set("Michael Chen is a senior software engineer.".to_string()),
+        bio_hidden_at: Set(None),
+        bio_held: Set(false),
         session_rate: Set(Decimal::from(120)),
         rating: Set(Decimal::from_f32(4.9).unwrap()),
+        rating_count: Set(0),
+        rating_sum: Set(0),
+        rating_score: Set(unrated_score),
         total_consultations: Set(189),
         is_verified: Set(true),
         is_online: Set(false),
         profile_image_url: Set(
           "https://images.unsplash.com/photo-1472099645785-5658abf4ff4e?w=300".to_string(),
         ),
+        pricing_model: Set(PricingModel::Flat),
+        allowed_durations: Set(json!(SUPPORTED_DURATIONS)),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -77,14 +104,21 @@ impl ExpertSeeder {
         user_id: Set(expert_ids[2]),
         specialization: Set("Beauty & Skincare Consultant".to_string()),
         bio: Set("Jessica Rodriguez is a licensed esthetician.".to_string()),
+        bio_hidden_at: Set(None),
+        bio_held: Set(false),
         session_rate: Set(Decimal::from(75)),
         rating: Set(Decimal::from_f32(4.7).unwrap()),
+        rating_count: Set(0),
+        rating_sum: Set(0),
+        rating_score: Set(unrated_score),
         total_consultations: Set(312),
         is_verified: Set(true),
         is_online: Set(true),
         profile_image_url: Set(
           "https://images.unsplash.com/photo-1438761681033-6461ffad8d80?w=300".to_string(),
         ),
+        pricing_model: Set(PricingModel::Flat),
+        allowed_durations: Set(json!(SUPPORTED_DURATIONS)),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -93,14 +127,21 @@ impl ExpertSeeder {
         user_id: Set(expert_ids[3]),
         specialization: Set("Gaming & Electronics Advisor".to_string()),
         bio: Set("David Kumar is a professional esports coach.".to_string()),
+        bio_hidden_at: Set(None),
+        bio_held: Set(false),
         session_rate: Set(Decimal::from(95)),
         rating: Set(Decimal::from_f32(4.6).unwrap()),
+        rating_count: Set(0),
+        rating_sum: Set(0),
+        rating_score: Set(unrated_score),
         total_consultations: Set(156),
         is_verified: Set(true),
         is_online: Set(true),
         profile_image_url: Set(
           "https://images.unsplash.com/photo-1507003211169-0a1dd7228f2d?w=300".to_string(),
         ),
+        pricing_model: Set(PricingModel::Flat),
+        allowed_durations: Set(json!(SUPPORTED_DURATIONS)),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -109,14 +150,21 @@ impl ExpertSeeder {
         user_id: Set(expert_ids[4]),
         specialization: Set("Health & Wellness Coach".to_string()),
         bio: Set("Emily Foster is a certified health coach.".to_string()),
+        bio_hidden_at: Set(None),
+        bio_held: Set(false),
         session_rate: Set(Decimal::from(65)),
         rating: Set(Decimal::from_f32(4.5).unwrap()),
+        rating_count: Set(0),
+        rating_sum: Set(0),
+        rating_score: Set(unrated_score),
         total_consultations: Set(203),
         is_verified: Set(true),
         is_online: Set(true),
         profile_image_url: Set(
           "https://images.unsplash.com/photo-1559839734-2b71ea197ec2?w=300".to_string(),
         ),
+        pricing_model: Set(PricingModel::Flat),
+        allowed_durations: Set(json!(SUPPORTED_DURATIONS)),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -229,9 +277,64 @@ impl ExpertSeeder {
     Ok(())
   }
 
+  async fn seed_expert_categories(
+    db: &DatabaseConnection,
+    expert_ids: &[Uuid],
+  ) -> DatabaseResult<()> {
+    // Primary category first
+    let expert_categories_data: [&[&str]; 5] = [
+      &["fashion-style", "clothing"],
+      &["technology-gadgets", "smartphones"],
+      &["beauty-skincare", "skincare"],
+      &["gaming-electronics"],
+      &["health-wellness", "fitness"],
+    ];
+
+    let categories_by_slug: std::collections::HashMap<String, Uuid> = Categories::find()
+      .all(db)
+      .await?
+      .into_iter()
+      .map(|category: categories::Model| (category.slug, category.id))
+      .collect();
+
+    let mut links = Vec::new();
+    for (&expert_id, slugs) in expert_ids.iter().zip(expert_categories_data) {
+      let Some(profile) = ExpertProfiles::find()
+        .filter(expert_profiles::Column::UserId.eq(expert_id))
+        .one(db)
+        .await?
+      else {
+        continue;
+      };
+
+      for (index, slug) in slugs.iter().enumerate() {
+        let Some(&category_id) = categories_by_slug.get(*slug) else {
+          tracing::warn!("Category '{}' not found, skipping", slug);
+          continue;
+        };
+
+        links.push(expert_categories::ActiveModel {
+          expert_id: Set(profile.id),
+          category_id: Set(category_id),
+          is_primary: Set(index == 0),
+          created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+        });
+      }
+    }
+
+    let count = links.len();
+    if count > 0 {
+      ExpertCategories::insert_many(links).exec(db).await?;
+    }
+
+    tracing::info!("✅ Successfully seeded {} expert category links", count);
+    Ok(())
+  }
+
   pub async fn clear(db: &DatabaseConnection) -> DatabaseResult<()> {
     tracing::info!("🧹 Clearing expert data...");
 
+    ExpertCategories::delete_many().exec(db).await?;
     ExpertAvailability::delete_many().exec(db).await?;
     ExpertStats::delete_many().exec(db).await?;
     ExpertProfiles::delete_many().exec(db).await?;
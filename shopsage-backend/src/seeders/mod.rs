@@ -18,6 +18,9 @@ impl Seeder {
     shopper_seeder::ShopperSeeder::seed(db).await?;
     expert_seeder::ExpertSeeder::seed(db).await?;
 
+    // Links between shoppers and experts need both sets of profiles
+    shopper_seeder::ShopperSeeder::seed_saved_experts(db).await?;
+
     tracing::info!("✅ Database seeding completed successfully!");
     Ok(())
   }
@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use super::user_seeder::UserSeeder;
 use crate::database::DatabaseResult;
-use crate::entities::{prelude::*, shopper_profiles};
+use crate::entities::{expert_profiles, prelude::*, shopper_profiles, shopper_saved_experts};
 
 pub struct ShopperSeeder;
 
@@ -22,7 +22,6 @@ impl ShopperSeeder {
     }
 
     let shopper_ids = UserSeeder::get_shopper_ids();
-    let expert_ids = UserSeeder::get_expert_ids();
 
     let shopper_profiles_data = vec![
       // Alice Johnson - Fashion enthusiast
@@ -32,11 +31,6 @@ impl ShopperSeeder {
         categories: Set(json!(["Fashion & Style", "Beauty & Skincare", "Shopping"])),
         price_range_min: Set(Decimal::from(25)),
         price_range_max: Set(Decimal::from(150)),
-        preferred_experts: Set(json!([
-          expert_ids[0].to_string(),
-          expert_ids[2].to_string()
-        ])),
-        saved_experts: Set(json!([expert_ids[0].to_string()])),
         interests: Set(json!([
           "Sustainable Fashion",
           "Vintage Style",
@@ -59,14 +53,6 @@ impl ShopperSeeder {
         ])),
         price_range_min: Set(Decimal::from(50)),
         price_range_max: Set(Decimal::from(500)),
-        preferred_experts: Set(json!([
-          expert_ids[1].to_string(),
-          expert_ids[3].to_string()
-        ])),
-        saved_experts: Set(json!([
-          expert_ids[1].to_string(),
-          expert_ids[3].to_string()
-        ])),
         interests: Set(json!([
           "Latest Tech",
           "Gaming Gear",
@@ -86,11 +72,6 @@ impl ShopperSeeder {
         categories: Set(json!(["Health & Wellness", "Beauty & Skincare", "Fitness"])),
         price_range_min: Set(Decimal::from(20)),
         price_range_max: Set(Decimal::from(200)),
-        preferred_experts: Set(json!([
-          expert_ids[4].to_string(),
-          expert_ids[2].to_string()
-        ])),
-        saved_experts: Set(json!([expert_ids[4].to_string()])),
         interests: Set(json!([
           "Natural Products",
           "Wellness Coaching",
@@ -116,9 +97,62 @@ impl ShopperSeeder {
     Ok(())
   }
 
+  /// Saved experts reference expert profiles, so this runs after the expert seeder
+  pub async fn seed_saved_experts(db: &DatabaseConnection) -> DatabaseResult<()> {
+    if ShopperSavedExperts::find().count(db).await? > 0 {
+      tracing::info!("Saved experts already exist, skipping saved expert seeding");
+      return Ok(());
+    }
+
+    let shopper_ids = UserSeeder::get_shopper_ids();
+    let expert_ids = UserSeeder::get_expert_ids();
+
+    // (shopper, expert, preferred)
+    let saved_experts_data = [
+      (0, 0, true),
+      (0, 2, true),
+      (1, 1, true),
+      (1, 3, true),
+      (2, 4, true),
+      (2, 2, false),
+    ];
+
+    let mut entries = Vec::new();
+    for (shopper, expert, preferred) in saved_experts_data {
+      let shopper_profile = ShopperProfiles::find()
+        .filter(shopper_profiles::Column::UserId.eq(shopper_ids[shopper]))
+        .one(db)
+        .await?;
+      let expert_profile = ExpertProfiles::find()
+        .filter(expert_profiles::Column::UserId.eq(expert_ids[expert]))
+        .one(db)
+        .await?;
+
+      let (Some(shopper_profile), Some(expert_profile)) = (shopper_profile, expert_profile) else {
+        continue;
+      };
+
+      entries.push(shopper_saved_experts::ActiveModel {
+        shopper_profile_id: Set(shopper_profile.id),
+        expert_id: Set(expert_profile.id),
+        is_preferred: Set(preferred),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+      });
+    }
+
+    let count = entries.len();
+    if count > 0 {
+      ShopperSavedExperts::insert_many(entries).exec(db).await?;
+    }
+
+    tracing::info!("✅ Successfully seeded {} saved experts", count);
+    Ok(())
+  }
+
   pub async fn clear(db: &DatabaseConnection) -> DatabaseResult<()> {
     tracing::info!("🧹 Clearing shopper profiles...");
 
+    ShopperSavedExperts::delete_many().exec(db).await?;
     ShopperProfiles::delete_many().exec(db).await?;
 
     tracing::info!("✅ Shopper profiles cleared");
@@ -25,6 +25,9 @@ impl UserSeeder {
         wallet_address: Set("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string()),
         name: Set("Alice Johnson".to_string()),
         email: Set("alice.johnson@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -33,6 +36,9 @@ impl UserSeeder {
         wallet_address: Set("2WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWN".to_string()),
         name: Set("Bob Smith".to_string()),
         email: Set("bob.smith@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -41,6 +47,9 @@ impl UserSeeder {
         wallet_address: Set("3WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWO".to_string()),
         name: Set("Carol Davis".to_string()),
         email: Set("carol.davis@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -50,6 +59,9 @@ impl UserSeeder {
         wallet_address: Set("EWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWW1".to_string()),
         name: Set("Dr. Sarah Wilson".to_string()),
         email: Set("sarah.wilson@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -58,6 +70,9 @@ impl UserSeeder {
         wallet_address: Set("FWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWW2".to_string()),
         name: Set("Michael Chen".to_string()),
         email: Set("michael.chen@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -66,6 +81,9 @@ impl UserSeeder {
         wallet_address: Set("GWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWW3".to_string()),
         name: Set("Jessica Rodriguez".to_string()),
         email: Set("jessica.rodriguez@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -74,6 +92,9 @@ impl UserSeeder {
         wallet_address: Set("HWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWW4".to_string()),
         name: Set("David Kumar".to_string()),
         email: Set("david.kumar@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -82,6 +103,9 @@ impl UserSeeder {
         wallet_address: Set("IWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWW5".to_string()),
         name: Set("Emily Foster".to_string()),
         email: Set("emily.foster@example.com".to_string()),
+        is_admin: Set(false),
+        banned_at: Set(None),
+        ban_reason: Set(None),
         created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
         updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       },
@@ -0,0 +1,263 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, FixedOffset, Utc};
+use rand::RngCore;
+use sea_orm::{sea_query::Expr, *};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::entities::{auth_sessions, prelude::*};
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuthSessionError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Malformed refresh token")]
+  MalformedToken,
+  #[error("Session not found")]
+  NotFound,
+  #[error("Session has expired")]
+  Expired,
+  #[error("Session has been revoked")]
+  Revoked,
+  #[error("Refresh token reuse detected, session revoked")]
+  ReuseDetected,
+}
+
+/// A freshly issued refresh token together with the session it belongs to.
+///
+/// The plain token is only ever handed back to the client; the database keeps its SHA-256 hash.
+pub struct IssuedRefreshToken {
+  pub session: auth_sessions::Model,
+  pub refresh_token: String,
+}
+
+/// Login sessions backed by rotating refresh tokens.
+///
+/// A refresh token has the shape `<session id>.<secret>`. Every refresh swaps the stored hash for
+/// a new secret, so presenting an older secret for a live session means the token was copied;
+/// the whole session is revoked when that happens.
+pub struct AuthSessionService;
+
+impl AuthSessionService {
+  pub async fn start(
+    db: &DatabaseConnection,
+    config: &Config,
+    user_id: Uuid,
+  ) -> Result<IssuedRefreshToken, AuthSessionError> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let session_id = Uuid::new_v4();
+    let secret = Self::generate_secret();
+
+    let session = auth_sessions::ActiveModel {
+      id: Set(session_id),
+      user_id: Set(user_id),
+      refresh_token_hash: Set(Self::hash_secret(&secret)),
+      expires_at: Set(now + Duration::seconds(config.refresh_token_ttl_secs)),
+      last_used_at: Set(None),
+      revoked_at: Set(None),
+      revoked_reason: Set(None),
+      created_at: Set(now),
+      updated_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(IssuedRefreshToken {
+      refresh_token: Self::format_token(session_id, &secret),
+      session,
+    })
+  }
+
+  /// Exchange a refresh token for a new one, detecting reuse of rotated tokens
+  pub async fn rotate(
+    db: &DatabaseConnection,
+    config: &Config,
+    refresh_token: &str,
+  ) -> Result<IssuedRefreshToken, AuthSessionError> {
+    let (session_id, presented_secret) = Self::parse_token(refresh_token)?;
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+
+    let session = AuthSessions::find_by_id(session_id)
+      .one(db)
+      .await?
+      .ok_or(AuthSessionError::NotFound)?;
+
+    if session.revoked_at.is_some() {
+      return Err(AuthSessionError::Revoked);
+    }
+    if session.expires_at <= now {
+      return Err(AuthSessionError::Expired);
+    }
+
+    let presented_hash = Self::hash_secret(presented_secret);
+    let new_secret = Self::generate_secret();
+
+    // Compare-and-swap on the current hash so two concurrent refreshes can't both succeed
+    let result = AuthSessions::update_many()
+      .col_expr(
+        auth_sessions::Column::RefreshTokenHash,
+        Expr::value(Self::hash_secret(&new_secret)),
+      )
+      .col_expr(auth_sessions::Column::LastUsedAt, Expr::value(Some(now)))
+      .col_expr(
+        auth_sessions::Column::ExpiresAt,
+        Expr::value(now + Duration::seconds(config.refresh_token_ttl_secs)),
+      )
+      .col_expr(auth_sessions::Column::UpdatedAt, Expr::value(now))
+      .filter(auth_sessions::Column::Id.eq(session_id))
+      .filter(auth_sessions::Column::RefreshTokenHash.eq(presented_hash))
+      .filter(auth_sessions::Column::RevokedAt.is_null())
+      .exec(db)
+      .await?;
+
+    if result.rows_affected != 1 {
+      tracing::warn!(
+        session_id = %session_id,
+        user_id = %session.user_id,
+        "Refresh token reuse detected, revoking session"
+      );
+      Self::revoke(db, session_id, "refresh_token_reuse").await?;
+      return Err(AuthSessionError::ReuseDetected);
+    }
+
+    let session = AuthSessions::find_by_id(session_id)
+      .one(db)
+      .await?
+      .ok_or(AuthSessionError::NotFound)?;
+
+    Ok(IssuedRefreshToken {
+      refresh_token: Self::format_token(session_id, &new_secret),
+      session,
+    })
+  }
+
+  pub async fn revoke(
+    db: &DatabaseConnection,
+    session_id: Uuid,
+    reason: &str,
+  ) -> Result<(), AuthSessionError> {
+    Self::revoke_where(
+      db,
+      Condition::all().add(auth_sessions::Column::Id.eq(session_id)),
+      reason,
+    )
+    .await
+    .map(|_| ())
+  }
+
+  /// Revoke every live session of a user, returning how many were revoked
+  pub async fn revoke_all_for_user(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    reason: &str,
+  ) -> Result<u64, AuthSessionError> {
+    Self::revoke_where(
+      db,
+      Condition::all().add(auth_sessions::Column::UserId.eq(user_id)),
+      reason,
+    )
+    .await
+  }
+
+  /// Whether access tokens issued for this session should still be honoured
+  pub async fn is_active(
+    db: &DatabaseConnection,
+    session_id: Uuid,
+  ) -> Result<bool, AuthSessionError> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let session = AuthSessions::find_by_id(session_id).one(db).await?;
+
+    Ok(matches!(session, Some(s) if s.revoked_at.is_none() && s.expires_at > now))
+  }
+
+  async fn revoke_where(
+    db: &DatabaseConnection,
+    condition: Condition,
+    reason: &str,
+  ) -> Result<u64, AuthSessionError> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+
+    let result = AuthSessions::update_many()
+      .col_expr(auth_sessions::Column::RevokedAt, Expr::value(Some(now)))
+      .col_expr(
+        auth_sessions::Column::RevokedReason,
+        Expr::value(Some(reason.to_string())),
+      )
+      .col_expr(auth_sessions::Column::UpdatedAt, Expr::value(now))
+      .filter(condition)
+      .filter(auth_sessions::Column::RevokedAt.is_null())
+      .exec(db)
+      .await?;
+
+    Ok(result.rows_affected)
+  }
+
+  fn parse_token(refresh_token: &str) -> Result<(Uuid, &str), AuthSessionError> {
+    let (session_id, secret) = refresh_token
+      .split_once('.')
+      .ok_or(AuthSessionError::MalformedToken)?;
+    let session_id = Uuid::parse_str(session_id).map_err(|_| AuthSessionError::MalformedToken)?;
+
+    if secret.is_empty() {
+      return Err(AuthSessionError::MalformedToken);
+    }
+
+    Ok((session_id, secret))
+  }
+
+  fn format_token(session_id: Uuid, secret: &str) -> String {
+    format!("{}.{}", session_id, secret)
+  }
+
+  fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+  }
+
+  fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_token_round_trip() {
+    let session_id = Uuid::new_v4();
+    let secret = AuthSessionService::generate_secret();
+    let token = AuthSessionService::format_token(session_id, &secret);
+
+    let (parsed_id, parsed_secret) = AuthSessionService::parse_token(&token).unwrap();
+    assert_eq!(parsed_id, session_id);
+    assert_eq!(parsed_secret, secret);
+  }
+
+  #[test]
+  fn test_rejects_malformed_tokens() {
+    for token in [
+      "",
+      "no-dot",
+      "not-a-uuid.secret",
+      "6b6f7c02-6d7c-4bb8-a0a5-1a1f0c4ad1b5.",
+    ] {
+      assert!(matches!(
+        AuthSessionService::parse_token(token),
+        Err(AuthSessionError::MalformedToken)
+      ));
+    }
+  }
+
+  #[test]
+  fn test_hash_is_hex_sha256() {
+    let hash = AuthSessionService::hash_secret("secret");
+    assert_eq!(hash.len(), 64);
+    assert_eq!(
+      hash,
+      "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b"
+    );
+  }
+}
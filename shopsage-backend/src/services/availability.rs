@@ -0,0 +1,750 @@
+use chrono::{
+  DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+  Offset, TimeZone, Timelike, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{expert_availability, prelude::*};
+
+#[derive(thiserror::Error, Debug)]
+pub enum AvailabilityError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Unknown timezone '{0}', expected an IANA name such as Europe/London")]
+  InvalidTimezone(String),
+  #[error("Unknown day of week '{0}'")]
+  InvalidDay(String),
+  #[error("Invalid time '{0}', expected HH:MM")]
+  InvalidTime(String),
+  #[error("{day} window must end after it starts ({start}-{end})")]
+  EndsBeforeStart {
+    day: String,
+    start: String,
+    end: String,
+  },
+  #[error("{day} windows {first} and {second} overlap")]
+  Overlap {
+    day: String,
+    first: String,
+    second: String,
+  },
+}
+
+/// One weekly window as submitted by the expert
+#[derive(Debug, Clone)]
+pub struct WindowInput {
+  pub day_of_week: String,
+  pub start: String,
+  pub end: String,
+  pub available: bool,
+}
+
+/// A validated weekly window; an `end` of 00:00 is midnight at the end of the day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeeklyWindow {
+  pub weekday: Weekday,
+  pub start: NaiveTime,
+  pub end: NaiveTime,
+  pub available: bool,
+}
+
+impl WeeklyWindow {
+  fn start_minute(&self) -> u32 {
+    self.start.hour() * 60 + self.start.minute()
+  }
+
+  fn end_minute(&self) -> u32 {
+    if self.end == NaiveTime::MIN {
+      24 * 60
+    } else {
+      self.end.hour() * 60 + self.end.minute()
+    }
+  }
+
+  fn label(&self) -> String {
+    format!(
+      "{}-{}",
+      self.start.format("%H:%M"),
+      self.end.format("%H:%M")
+    )
+  }
+}
+
+/// A concrete bookable interval, `[start, end)` in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Window {
+  pub start: DateTime<Utc>,
+  pub end: DateTime<Utc>,
+}
+
+impl Window {
+  pub fn contains(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    self.start <= start && end <= self.end
+  }
+
+  pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    self.start < end && start < self.end
+  }
+}
+
+/// Bookable start times are multiples of this many minutes past the hour, in UTC
+pub const SLOT_STEP_MINUTES: i64 = 15;
+
+/// Expands weekly `expert_availability` rules into concrete UTC windows.
+///
+/// Rules are wall-clock times in the expert's IANA timezone, so a 09:00-17:00 Monday rule in
+/// `Europe/London` is 08:00-16:00 UTC in summer and 09:00-17:00 UTC in winter. An `end_time` of
+/// 00:00 means midnight at the end of the day. Rules with `available = false` are weekly
+/// blackouts carved out of the available ones.
+pub struct AvailabilityService;
+
+impl AvailabilityService {
+  /// Accepts full English names ("Monday") as seeded, as well as short forms ("mon")
+  pub fn parse_weekday(day_of_week: &str) -> Option<Weekday> {
+    day_of_week.trim().parse::<Weekday>().ok()
+  }
+
+  pub fn parse_timezone(timezone: &str) -> Option<Tz> {
+    timezone.trim().parse::<Tz>().ok()
+  }
+
+  /// Full English day name, the form stored in `expert_availability.day_of_week`
+  pub fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+      Weekday::Mon => "Monday",
+      Weekday::Tue => "Tuesday",
+      Weekday::Wed => "Wednesday",
+      Weekday::Thu => "Thursday",
+      Weekday::Fri => "Friday",
+      Weekday::Sat => "Saturday",
+      Weekday::Sun => "Sunday",
+    }
+  }
+
+  /// Validate a whole weekly schedule.
+  ///
+  /// Available windows on the same day must not overlap, and neither may blackouts; a blackout
+  /// is expected to overlap the available window it carves time out of. "24:00" is accepted as
+  /// an end time and stored as 00:00.
+  pub fn validate_schedule(
+    timezone: &str,
+    windows: &[WindowInput],
+  ) -> Result<(Tz, Vec<WeeklyWindow>), AvailabilityError> {
+    let tz = Self::parse_timezone(timezone)
+      .ok_or_else(|| AvailabilityError::InvalidTimezone(timezone.to_string()))?;
+
+    let mut parsed = windows
+      .iter()
+      .map(|window| {
+        let weekday = Self::parse_weekday(&window.day_of_week)
+          .ok_or_else(|| AvailabilityError::InvalidDay(window.day_of_week.clone()))?;
+        let start = Self::parse_time(&window.start)?;
+        let end = if window.end.trim() == "24:00" {
+          NaiveTime::MIN
+        } else {
+          Self::parse_time(&window.end)?
+        };
+
+        let parsed = WeeklyWindow {
+          weekday,
+          start,
+          end,
+          available: window.available,
+        };
+        if parsed.end_minute() <= parsed.start_minute() {
+          return Err(AvailabilityError::EndsBeforeStart {
+            day: Self::weekday_name(weekday).to_string(),
+            start: window.start.clone(),
+            end: window.end.clone(),
+          });
+        }
+
+        Ok(parsed)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    parsed.sort_by_key(|window| {
+      (
+        window.weekday.num_days_from_monday(),
+        !window.available,
+        window.start_minute(),
+      )
+    });
+
+    for pair in parsed.windows(2) {
+      let (first, second) = (&pair[0], &pair[1]);
+      if first.weekday == second.weekday
+        && first.available == second.available
+        && second.start_minute() < first.end_minute()
+      {
+        return Err(AvailabilityError::Overlap {
+          day: Self::weekday_name(first.weekday).to_string(),
+          first: first.label(),
+          second: second.label(),
+        });
+      }
+    }
+
+    Ok((tz, parsed))
+  }
+
+  fn parse_time(value: &str) -> Result<NaiveTime, AvailabilityError> {
+    let value = value.trim();
+    NaiveTime::parse_from_str(value, "%H:%M")
+      .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+      .map_err(|_| AvailabilityError::InvalidTime(value.to_string()))
+  }
+
+  pub async fn rules_for_expert<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+  ) -> Result<Vec<expert_availability::Model>, DbErr> {
+    ExpertAvailability::find()
+      .filter(expert_availability::Column::UserId.eq(user_id))
+      .order_by_asc(expert_availability::Column::StartTime)
+      .all(db)
+      .await
+  }
+
+  /// Swap the expert's whole weekly schedule for `windows` in one transaction
+  pub async fn replace_schedule<C: TransactionTrait>(
+    db: &C,
+    user_id: Uuid,
+    timezone: &str,
+    windows: &[WindowInput],
+  ) -> Result<Vec<expert_availability::Model>, AvailabilityError> {
+    let (tz, windows) = Self::validate_schedule(timezone, windows)?;
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+
+    let txn = db.begin().await?;
+
+    ExpertAvailability::delete_many()
+      .filter(expert_availability::Column::UserId.eq(user_id))
+      .exec(&txn)
+      .await?;
+
+    if !windows.is_empty() {
+      ExpertAvailability::insert_many(windows.iter().map(|window| {
+        expert_availability::ActiveModel {
+          id: Set(Uuid::new_v4()),
+          user_id: Set(user_id),
+          day_of_week: Set(Self::weekday_name(window.weekday).to_string()),
+          start_time: Set(window.start),
+          end_time: Set(window.end),
+          available: Set(window.available),
+          timezone: Set(tz.name().to_string()),
+          created_at: Set(now),
+          updated_at: Set(now),
+        }
+      }))
+      .exec(&txn)
+      .await?;
+    }
+
+    let rules = Self::rules_for_expert(&txn, user_id).await?;
+    txn.commit().await?;
+
+    Ok(rules)
+  }
+
+  /// Every available window overlapping `[from, to)`, sorted, merged and minus blackouts
+  pub fn windows_between(
+    rules: &[expert_availability::Model],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+  ) -> Vec<Window> {
+    Self::subtract(
+      &Self::expand(rules, true, from, to),
+      &Self::expand(rules, false, from, to),
+    )
+  }
+
+  /// Whether `[start, end)` fits entirely inside one available window
+  pub fn is_available(
+    rules: &[expert_availability::Model],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> bool {
+    Self::windows_between(rules, start, end)
+      .iter()
+      .any(|window| window.contains(start, end))
+  }
+
+  /// Remove every `busy` interval from `windows`
+  pub fn subtract(windows: &[Window], busy: &[Window]) -> Vec<Window> {
+    let busy = Self::merge(busy.to_vec());
+    let mut free = Vec::new();
+
+    for window in windows {
+      let mut cursor = window.start;
+      for blocked in busy
+        .iter()
+        .filter(|blocked| blocked.overlaps(window.start, window.end))
+      {
+        if blocked.start > cursor {
+          free.push(Window {
+            start: cursor,
+            end: blocked.start,
+          });
+        }
+        cursor = cursor.max(blocked.end);
+      }
+      if cursor < window.end {
+        free.push(Window {
+          start: cursor,
+          end: window.end,
+        });
+      }
+    }
+
+    free
+  }
+
+  /// Every `duration` long slot inside `free` starting on the `step` grid, no earlier than `not_before`
+  pub fn slots(
+    free: &[Window],
+    duration: Duration,
+    step: Duration,
+    not_before: DateTime<Utc>,
+  ) -> Vec<Window> {
+    let mut slots = Vec::new();
+
+    for window in free {
+      let mut start = Self::ceil_to_step(window.start.max(not_before), step);
+      while start + duration <= window.end {
+        slots.push(Window {
+          start,
+          end: start + duration,
+        });
+        start += step;
+      }
+    }
+
+    slots
+  }
+
+  fn ceil_to_step(time: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+    let step_secs = step.num_seconds().max(1);
+    let remainder = time.timestamp().rem_euclid(step_secs);
+    let truncated = time - Duration::nanoseconds(i64::from(time.timestamp_subsec_nanos()));
+
+    if remainder == 0 && truncated == time {
+      time
+    } else {
+      truncated - Duration::seconds(remainder) + Duration::seconds(step_secs)
+    }
+  }
+
+  fn expand(
+    rules: &[expert_availability::Model],
+    available: bool,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+  ) -> Vec<Window> {
+    let mut windows = Vec::new();
+
+    for rule in rules.iter().filter(|rule| rule.available == available) {
+      let (Some(weekday), Some(tz)) = (
+        Self::parse_weekday(&rule.day_of_week),
+        Self::parse_timezone(&rule.timezone),
+      ) else {
+        tracing::warn!(
+          availability_id = %rule.id,
+          day_of_week = %rule.day_of_week,
+          timezone = %rule.timezone,
+          "Skipping unparseable availability rule"
+        );
+        continue;
+      };
+
+      // Local dates are walked with a day of slack on both sides for far-off timezones
+      let mut date = from.with_timezone(&tz).date_naive() - Duration::days(1);
+      let last = to.with_timezone(&tz).date_naive() + Duration::days(1);
+
+      while date <= last {
+        if date.weekday() == weekday {
+          if let Some(window) = Self::window_on(&tz, date, rule.start_time, rule.end_time) {
+            if window.overlaps(from, to) {
+              windows.push(window);
+            }
+          }
+        }
+        date += Duration::days(1);
+      }
+    }
+
+    Self::merge(windows)
+  }
+
+  fn window_on(tz: &Tz, date: NaiveDate, start: NaiveTime, end: NaiveTime) -> Option<Window> {
+    let local_start = date.and_time(start);
+    let local_end = if end == NaiveTime::MIN {
+      (date + Duration::days(1)).and_time(end)
+    } else {
+      date.and_time(end)
+    };
+
+    if local_end <= local_start {
+      return None;
+    }
+
+    let window = Window {
+      start: Self::resolve_local(tz, local_start),
+      end: Self::resolve_local(tz, local_end),
+    };
+
+    (window.start < window.end).then_some(window)
+  }
+
+  /// Map a wall-clock time to UTC across DST changes.
+  ///
+  /// Times repeated when clocks go back resolve to their first occurrence. Times skipped when
+  /// clocks go forward are read with the offset from before the jump, so 01:30 inside a
+  /// 01:00-02:00 gap becomes 02:30 local.
+  pub(crate) fn resolve_local(tz: &Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+      LocalResult::Single(time) => time.with_timezone(&Utc),
+      LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+      LocalResult::None => {
+        let mut probe = local;
+        let offset = loop {
+          probe -= Duration::minutes(15);
+          if let Some(time) = tz.from_local_datetime(&probe).earliest() {
+            break time.offset().fix();
+          }
+        };
+        (local - Duration::seconds(offset.local_minus_utc().into())).and_utc()
+      }
+    }
+  }
+
+  pub(crate) fn merge(mut windows: Vec<Window>) -> Vec<Window> {
+    windows.sort();
+
+    let mut merged: Vec<Window> = Vec::with_capacity(windows.len());
+    for window in windows {
+      match merged.last_mut() {
+        Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+        _ => merged.push(window),
+      }
+    }
+
+    merged
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::FixedOffset;
+  use uuid::Uuid;
+
+  fn rule(
+    day: &str,
+    start: (u32, u32),
+    end: (u32, u32),
+    timezone: &str,
+  ) -> expert_availability::Model {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    expert_availability::Model {
+      id: Uuid::new_v4(),
+      user_id: Uuid::new_v4(),
+      day_of_week: day.to_string(),
+      start_time: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+      end_time: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+      available: true,
+      timezone: timezone.to_string(),
+      created_at: now,
+      updated_at: now,
+    }
+  }
+
+  fn utc(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+
+  #[test]
+  fn test_rules_follow_the_expert_timezone() {
+    let rules = vec![rule("Monday", (9, 0), (17, 0), "America/New_York")];
+
+    // 2025-08-11 is a Monday, New York is on EDT (UTC-4)
+    let windows = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-08-11T00:00:00Z"),
+      utc("2025-08-12T00:00:00Z"),
+    );
+    assert_eq!(
+      windows,
+      vec![Window {
+        start: utc("2025-08-11T13:00:00Z"),
+        end: utc("2025-08-11T21:00:00Z"),
+      }]
+    );
+
+    assert!(AvailabilityService::is_available(
+      &rules,
+      utc("2025-08-11T13:00:00Z"),
+      utc("2025-08-11T13:30:00Z"),
+    ));
+    // 09:00 UTC is 05:00 in New York
+    assert!(!AvailabilityService::is_available(
+      &rules,
+      utc("2025-08-11T09:00:00Z"),
+      utc("2025-08-11T09:30:00Z"),
+    ));
+  }
+
+  #[test]
+  fn test_dst_shifts_utc_windows() {
+    let rules = vec![rule("Sunday", (9, 0), (10, 0), "Europe/London")];
+
+    // Clocks went forward on 2025-03-30 and back on 2025-10-26
+    let windows = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-03-23T00:00:00Z"),
+      utc("2025-03-31T00:00:00Z"),
+    );
+    assert_eq!(windows[0].start, utc("2025-03-23T09:00:00Z"));
+    assert_eq!(windows[1].start, utc("2025-03-30T08:00:00Z"));
+
+    let windows = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-10-26T00:00:00Z"),
+      utc("2025-10-27T00:00:00Z"),
+    );
+    assert_eq!(windows[0].start, utc("2025-10-26T09:00:00Z"));
+  }
+
+  #[test]
+  fn test_windows_inside_dst_gaps_and_overlaps() {
+    // 01:00-02:00 does not exist in London on 2025-03-30 and happens twice on 2025-10-26
+    let rules = vec![rule("Sunday", (1, 30), (3, 0), "Europe/London")];
+
+    let spring = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-03-30T00:00:00Z"),
+      utc("2025-03-31T00:00:00Z"),
+    );
+    assert_eq!(
+      spring,
+      vec![Window {
+        start: utc("2025-03-30T01:30:00Z"),
+        end: utc("2025-03-30T02:00:00Z"),
+      }]
+    );
+
+    let autumn = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-10-26T00:00:00Z"),
+      utc("2025-10-27T00:00:00Z"),
+    );
+    assert_eq!(
+      autumn,
+      vec![Window {
+        start: utc("2025-10-26T00:30:00Z"),
+        end: utc("2025-10-26T03:00:00Z"),
+      }]
+    );
+  }
+
+  #[test]
+  fn test_slots_across_spring_forward() {
+    // New York skips 02:00-03:00 on Sunday 2025-03-09, so a 00:00-04:00 rule is only 3 hours long
+    let rules = vec![rule("Sunday", (0, 0), (4, 0), "America/New_York")];
+    let free = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-03-09T00:00:00Z"),
+      utc("2025-03-10T00:00:00Z"),
+    );
+    assert_eq!(
+      free,
+      vec![Window {
+        start: utc("2025-03-09T05:00:00Z"),
+        end: utc("2025-03-09T08:00:00Z"),
+      }]
+    );
+
+    let slots = AvailabilityService::slots(
+      &free,
+      Duration::minutes(60),
+      Duration::minutes(60),
+      utc("2025-03-01T00:00:00Z"),
+    );
+    let starts: Vec<_> = slots.iter().map(|slot| slot.start).collect();
+    assert_eq!(
+      starts,
+      vec![
+        utc("2025-03-09T05:00:00Z"),
+        utc("2025-03-09T06:00:00Z"),
+        utc("2025-03-09T07:00:00Z"),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_expert_and_shopper_in_different_timezones() {
+    // A Tokyo expert's Monday morning is a Los Angeles shopper's Sunday afternoon
+    let rules = vec![rule("Monday", (9, 0), (10, 0), "Asia/Tokyo")];
+    let from = DateTime::parse_from_rfc3339("2025-08-10T00:00:00-07:00")
+      .unwrap()
+      .with_timezone(&Utc);
+    let to = DateTime::parse_from_rfc3339("2025-08-11T00:00:00-07:00")
+      .unwrap()
+      .with_timezone(&Utc);
+
+    let free = AvailabilityService::windows_between(&rules, from, to);
+    let slots = AvailabilityService::slots(
+      &free,
+      Duration::minutes(30),
+      Duration::minutes(SLOT_STEP_MINUTES),
+      from,
+    );
+
+    assert_eq!(slots.len(), 3);
+    assert_eq!(slots[0].start, utc("2025-08-11T00:00:00Z"));
+    assert_eq!(
+      slots[0]
+        .start
+        .with_timezone(&chrono_tz::America::Los_Angeles)
+        .to_rfc3339(),
+      "2025-08-10T17:00:00-07:00"
+    );
+    assert_eq!(slots[2].end, utc("2025-08-11T01:00:00Z"));
+  }
+
+  #[test]
+  fn test_sessions_and_blackouts_are_subtracted() {
+    let mut blackout = rule("Monday", (12, 0), (13, 0), "UTC");
+    blackout.available = false;
+    let rules = vec![rule("Monday", (9, 0), (17, 0), "UTC"), blackout];
+
+    let free = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-08-11T00:00:00Z"),
+      utc("2025-08-12T00:00:00Z"),
+    );
+    let booked = [Window {
+      start: utc("2025-08-11T09:20:00Z"),
+      end: utc("2025-08-11T09:50:00Z"),
+    }];
+    let free = AvailabilityService::subtract(&free, &booked);
+
+    assert_eq!(
+      free,
+      vec![
+        Window {
+          start: utc("2025-08-11T09:00:00Z"),
+          end: utc("2025-08-11T09:20:00Z")
+        },
+        Window {
+          start: utc("2025-08-11T09:50:00Z"),
+          end: utc("2025-08-11T12:00:00Z")
+        },
+        Window {
+          start: utc("2025-08-11T13:00:00Z"),
+          end: utc("2025-08-11T17:00:00Z")
+        },
+      ]
+    );
+
+    let slots = AvailabilityService::slots(
+      &free,
+      Duration::minutes(60),
+      Duration::minutes(SLOT_STEP_MINUTES),
+      utc("2025-08-11T00:00:00Z"),
+    );
+    // Nothing fits before the booking, the next slot starts on the 15 minute grid
+    assert_eq!(slots[0].start, utc("2025-08-11T10:00:00Z"));
+    assert!(slots
+      .iter()
+      .all(|slot| !booked[0].overlaps(slot.start, slot.end)));
+    assert!(slots.iter().all(
+      |slot| slot.end <= utc("2025-08-11T12:00:00Z") || slot.start >= utc("2025-08-11T13:00:00Z")
+    ));
+  }
+
+  fn input(day: &str, start: &str, end: &str, available: bool) -> WindowInput {
+    WindowInput {
+      day_of_week: day.to_string(),
+      start: start.to_string(),
+      end: end.to_string(),
+      available,
+    }
+  }
+
+  #[test]
+  fn test_validate_schedule() {
+    let (tz, windows) = AvailabilityService::validate_schedule(
+      "Europe/Berlin",
+      &[
+        input("Monday", "13:00", "17:00", true),
+        input("mon", "09:00", "12:00", true),
+        input("Monday", "10:00", "11:00", false),
+        input("Friday", "20:00", "24:00", true),
+      ],
+    )
+    .unwrap();
+
+    assert_eq!(tz, chrono_tz::Europe::Berlin);
+    assert_eq!(windows.len(), 4);
+    assert_eq!(windows[0].start, NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+    assert_eq!(windows[3].end, NaiveTime::MIN);
+  }
+
+  #[test]
+  fn test_invalid_schedules_are_rejected() {
+    assert!(matches!(
+      AvailabilityService::validate_schedule("Mars/Olympus", &[]),
+      Err(AvailabilityError::InvalidTimezone(_))
+    ));
+    assert!(matches!(
+      AvailabilityService::validate_schedule("UTC", &[input("Funday", "09:00", "10:00", true)]),
+      Err(AvailabilityError::InvalidDay(_))
+    ));
+    assert!(matches!(
+      AvailabilityService::validate_schedule("UTC", &[input("Monday", "9am", "10:00", true)]),
+      Err(AvailabilityError::InvalidTime(_))
+    ));
+    assert!(matches!(
+      AvailabilityService::validate_schedule("UTC", &[input("Monday", "17:00", "09:00", true)]),
+      Err(AvailabilityError::EndsBeforeStart { .. })
+    ));
+    assert!(matches!(
+      AvailabilityService::validate_schedule(
+        "UTC",
+        &[
+          input("Tuesday", "09:00", "12:00", true),
+          input("Tuesday", "11:30", "14:00", true),
+        ]
+      ),
+      Err(AvailabilityError::Overlap { .. })
+    ));
+  }
+
+  #[test]
+  fn test_midnight_end_and_day_names() {
+    let rules = vec![
+      rule("fri", (22, 0), (0, 0), "UTC"),
+      rule("Funday", (9, 0), (17, 0), "UTC"),
+      rule("Friday", (9, 0), (17, 0), "Not/AZone"),
+    ];
+
+    // 2025-08-15 is a Friday
+    let windows = AvailabilityService::windows_between(
+      &rules,
+      utc("2025-08-15T00:00:00Z"),
+      utc("2025-08-16T00:00:00Z"),
+    );
+    assert_eq!(
+      windows,
+      vec![Window {
+        start: utc("2025-08-15T22:00:00Z"),
+        end: utc("2025-08-16T00:00:00Z"),
+      }]
+    );
+  }
+}
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{prelude::*, sea_orm_active_enums::SessionStatus, sessions};
+use crate::services::availability::{AvailabilityService, Window, SLOT_STEP_MINUTES};
+use crate::services::time_off::TimeOffService;
+
+/// Postgres `exclusion_violation`, raised by `excl_sessions_expert_no_overlap`
+const EXCLUSION_VIOLATION: &str = "23P01";
+
+/// Sessions in these states no longer hold their time slot
+pub const RELEASED_STATUSES: [SessionStatus; 2] =
+  [SessionStatus::Cancelled, SessionStatus::Refunded];
+
+#[derive(thiserror::Error, Debug)]
+pub enum BookingError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Sessions must be booked in the future")]
+  InPast,
+  #[error("The requested time is outside the expert's availability")]
+  OutsideAvailability,
+  #[error("The expert is on time off at the requested time")]
+  TimeOff,
+  #[error("The expert already has a session at the requested time")]
+  SlotTaken,
+}
+
+/// Checks a requested booking against the expert's calendar.
+///
+/// The overlap query here gives a friendly error in the common case; the
+/// `excl_sessions_expert_no_overlap` constraint is what actually stops two concurrent bookings
+/// from both succeeding, see [`BookingService::is_overlap_violation`].
+pub struct BookingService;
+
+impl BookingService {
+  pub async fn check_slot<C: ConnectionTrait>(
+    db: &C,
+    expert_user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<(), BookingError> {
+    if start <= Utc::now() {
+      return Err(BookingError::InPast);
+    }
+
+    let rules = AvailabilityService::rules_for_expert(db, expert_user_id).await?;
+
+    if !AvailabilityService::is_available(&rules, start, end) {
+      return Err(BookingError::OutsideAvailability);
+    }
+
+    let time_off = TimeOffService::list_for_expert(db, expert_user_id).await?;
+    if !TimeOffService::windows_between(&time_off, start, end).is_empty() {
+      return Err(BookingError::TimeOff);
+    }
+
+    if !Self::overlapping_sessions(db, expert_user_id, start, end)
+      .await?
+      .is_empty()
+    {
+      return Err(BookingError::SlotTaken);
+    }
+
+    Ok(())
+  }
+
+  /// Bookable `duration` long slots in `[from, to)`, after availability, blackouts, time off and sessions
+  pub async fn open_slots<C: ConnectionTrait>(
+    db: &C,
+    expert_user_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    duration: Duration,
+  ) -> Result<Vec<Window>, BookingError> {
+    let rules = AvailabilityService::rules_for_expert(db, expert_user_id).await?;
+
+    let mut busy: Vec<Window> = Self::overlapping_sessions(db, expert_user_id, from, to)
+      .await?
+      .into_iter()
+      .filter_map(|session| {
+        Some(Window {
+          start: session.booked_start?.with_timezone(&Utc),
+          end: session.booked_end?.with_timezone(&Utc),
+        })
+      })
+      .collect();
+
+    let time_off = TimeOffService::list_for_expert(db, expert_user_id).await?;
+    busy.extend(TimeOffService::windows_between(&time_off, from, to));
+
+    let free = AvailabilityService::subtract(
+      &AvailabilityService::windows_between(&rules, from, to),
+      &busy,
+    );
+
+    Ok(
+      AvailabilityService::slots(
+        &free,
+        duration,
+        Duration::minutes(SLOT_STEP_MINUTES),
+        from.max(Utc::now()),
+      )
+      .into_iter()
+      .filter(|slot| slot.end <= to)
+      .collect(),
+    )
+  }
+
+  /// Sessions of the expert still holding time that overlaps `[start, end)`
+  pub async fn overlapping_sessions<C: ConnectionTrait>(
+    db: &C,
+    expert_user_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+  ) -> Result<Vec<sessions::Model>, DbErr> {
+    Sessions::find()
+      .filter(sessions::Column::ExpertId.eq(expert_user_id))
+      .filter(sessions::Column::Status.is_not_in(RELEASED_STATUSES))
+      .filter(sessions::Column::BookedStart.lt(end))
+      .filter(sessions::Column::BookedEnd.gt(start))
+      .order_by_asc(sessions::Column::BookedStart)
+      .all(db)
+      .await
+  }
+
+  /// Whether an insert or update lost a race for the slot to another booking
+  pub fn is_overlap_violation(err: &DbErr) -> bool {
+    match err {
+      DbErr::Exec(RuntimeErr::SqlxError(sqlx_err))
+      | DbErr::Query(RuntimeErr::SqlxError(sqlx_err)) => sqlx_err
+        .as_database_error()
+        .and_then(|db_err| db_err.code())
+        .is_some_and(|code| code == EXCLUSION_VIOLATION),
+      _ => false,
+    }
+  }
+}
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use chrono::{FixedOffset, Utc};
+use sea_orm::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::entities::{categories, expert_categories, prelude::*};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CategoryError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Unknown category '{0}'")]
+  NotFound(String),
+}
+
+/// A category and everything filed under it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategoryNode {
+  pub id: Uuid,
+  pub slug: String,
+  pub name: String,
+  #[serde(rename = "parentId")]
+  pub parent_id: Option<Uuid>,
+  pub children: Vec<CategoryNode>,
+}
+
+/// A category an expert is listed under
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpertCategory {
+  pub id: Uuid,
+  pub slug: String,
+  pub name: String,
+  #[serde(rename = "isPrimary")]
+  pub is_primary: bool,
+}
+
+/// The specialization taxonomy experts are filed under.
+///
+/// Categories form a tree through `parent_id`; browsing a category includes experts filed under
+/// any of its descendants.
+pub struct CategoryService;
+
+impl CategoryService {
+  /// Every category in display order
+  pub async fn all<C: ConnectionTrait>(db: &C) -> Result<Vec<categories::Model>, DbErr> {
+    Categories::find()
+      .order_by_asc(categories::Column::SortOrder)
+      .order_by_asc(categories::Column::Name)
+      .all(db)
+      .await
+  }
+
+  /// Nest `categories` under their parents, keeping their order; orphans become roots
+  pub fn build_tree(categories: Vec<categories::Model>) -> Vec<CategoryNode> {
+    let ids: HashSet<Uuid> = categories.iter().map(|category| category.id).collect();
+    let mut children: HashMap<Option<Uuid>, Vec<categories::Model>> = HashMap::new();
+
+    for category in categories {
+      let parent = category.parent_id.filter(|parent| ids.contains(parent));
+      children.entry(parent).or_default().push(category);
+    }
+
+    fn attach(
+      parent: Option<Uuid>,
+      children: &mut HashMap<Option<Uuid>, Vec<categories::Model>>,
+    ) -> Vec<CategoryNode> {
+      children
+        .remove(&parent)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|category| CategoryNode {
+          id: category.id,
+          slug: category.slug,
+          name: category.name,
+          parent_id: category.parent_id,
+          children: attach(Some(category.id), children),
+        })
+        .collect()
+    }
+
+    attach(None, &mut children)
+  }
+
+  /// `root` and all of its descendants
+  pub fn subtree_ids(categories: &[categories::Model], root: Uuid) -> Vec<Uuid> {
+    let mut ids = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([root]);
+
+    while let Some(id) = queue.pop_front() {
+      if !seen.insert(id) {
+        continue;
+      }
+      ids.push(id);
+      queue.extend(
+        categories
+          .iter()
+          .filter(|category| category.parent_id == Some(id))
+          .map(|category| category.id),
+      );
+    }
+
+    ids
+  }
+
+  /// The category with `slug` and all of its descendants
+  pub async fn subtree_ids_for_slug<C: ConnectionTrait>(
+    db: &C,
+    slug: &str,
+  ) -> Result<Vec<Uuid>, CategoryError> {
+    let categories = Self::all(db).await?;
+    let root = categories
+      .iter()
+      .find(|category| category.slug == slug)
+      .ok_or_else(|| CategoryError::NotFound(slug.to_string()))?;
+
+    Ok(Self::subtree_ids(&categories, root.id))
+  }
+
+  /// Categories an expert profile is filed under, primary first
+  pub async fn for_expert<C: ConnectionTrait>(
+    db: &C,
+    expert_id: Uuid,
+  ) -> Result<Vec<ExpertCategory>, DbErr> {
+    let links = ExpertCategories::find()
+      .filter(expert_categories::Column::ExpertId.eq(expert_id))
+      .find_also_related(Categories)
+      .order_by_desc(expert_categories::Column::IsPrimary)
+      .order_by_asc(categories::Column::SortOrder)
+      .order_by_asc(categories::Column::Name)
+      .all(db)
+      .await?;
+
+    Ok(
+      links
+        .into_iter()
+        .filter_map(|(link, category)| {
+          category.map(|category| ExpertCategory {
+            id: category.id,
+            slug: category.slug,
+            name: category.name,
+            is_primary: link.is_primary,
+          })
+        })
+        .collect(),
+    )
+  }
+
+  /// Ids of the categories named by `slugs`, deduplicated and in order.
+  ///
+  /// Fails on the first unknown slug; resolve before saving anything that depends on them.
+  pub async fn resolve_slugs<C: ConnectionTrait>(
+    db: &C,
+    slugs: &[String],
+  ) -> Result<Vec<Uuid>, CategoryError> {
+    let mut unique_slugs: Vec<&str> = Vec::new();
+    for slug in slugs.iter().map(|slug| slug.trim()) {
+      if !slug.is_empty() && !unique_slugs.contains(&slug) {
+        unique_slugs.push(slug);
+      }
+    }
+
+    let found = Categories::find()
+      .filter(categories::Column::Slug.is_in(unique_slugs.clone()))
+      .all(db)
+      .await?;
+    let by_slug: HashMap<&str, Uuid> = found
+      .iter()
+      .map(|category| (category.slug.as_str(), category.id))
+      .collect();
+
+    let mut category_ids = Vec::with_capacity(unique_slugs.len());
+    for slug in &unique_slugs {
+      let id = by_slug
+        .get(slug)
+        .ok_or_else(|| CategoryError::NotFound(slug.to_string()))?;
+      category_ids.push(*id);
+    }
+
+    Ok(category_ids)
+  }
+
+  /// Replace the categories an expert profile is filed under; the first id is its primary one.
+  ///
+  /// Call inside the transaction that saves the profile, so a failure leaves the old links in
+  /// place.
+  pub async fn set_expert_categories<C: ConnectionTrait>(
+    db: &C,
+    expert_id: Uuid,
+    category_ids: &[Uuid],
+  ) -> Result<(), DbErr> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+
+    ExpertCategories::delete_many()
+      .filter(expert_categories::Column::ExpertId.eq(expert_id))
+      .exec(db)
+      .await?;
+
+    if !category_ids.is_empty() {
+      let links = category_ids.iter().enumerate().map(|(index, category_id)| {
+        expert_categories::ActiveModel {
+          expert_id: Set(expert_id),
+          category_id: Set(*category_id),
+          is_primary: Set(index == 0),
+          created_at: Set(now),
+        }
+      });
+      ExpertCategories::insert_many(links).exec(db).await?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::fixtures;
+
+  fn category(slug: &str, parent: Option<&categories::Model>) -> categories::Model {
+    fixtures::category(slug, slug, parent)
+  }
+
+  #[test]
+  fn test_build_tree() {
+    let tech = category("technology-gadgets", None);
+    let phones = category("smartphones", Some(&tech));
+    let laptops = category("computers-laptops", Some(&tech));
+    let fashion = category("fashion-style", None);
+
+    let tree = CategoryService::build_tree(vec![
+      tech.clone(),
+      phones.clone(),
+      fashion.clone(),
+      laptops.clone(),
+    ]);
+
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree[0].slug, "technology-gadgets");
+    assert_eq!(
+      tree[0]
+        .children
+        .iter()
+        .map(|child| child.slug.as_str())
+        .collect::<Vec<_>>(),
+      vec!["smartphones", "computers-laptops"]
+    );
+    assert_eq!(tree[1].slug, "fashion-style");
+    assert!(tree[1].children.is_empty());
+  }
+
+  #[test]
+  fn test_subtree_ids() {
+    let tech = category("technology-gadgets", None);
+    let gaming = category("gaming-electronics", Some(&tech));
+    let consoles = category("consoles", Some(&gaming));
+    let fashion = category("fashion-style", None);
+    let all = vec![
+      tech.clone(),
+      gaming.clone(),
+      consoles.clone(),
+      fashion.clone(),
+    ];
+
+    assert_eq!(
+      CategoryService::subtree_ids(&all, tech.id),
+      vec![tech.id, gaming.id, consoles.id]
+    );
+    assert_eq!(
+      CategoryService::subtree_ids(&all, consoles.id),
+      vec![consoles.id]
+    );
+    assert_eq!(
+      CategoryService::subtree_ids(&all, fashion.id),
+      vec![fashion.id]
+    );
+  }
+}
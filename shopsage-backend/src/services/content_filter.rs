@@ -0,0 +1,168 @@
+use std::fs;
+
+use crate::config::Config;
+
+/// Used when `CONTENT_FILTER_WORDLIST` is unset. One word or phrase per line; `?` marks entries
+/// that only hold content for a moderator instead of rejecting it.
+const DEFAULT_WORD_LIST: &str = "\
+# Abuse, rejected outright
+fuck
+fucking
+motherfucker
+cunt
+bitch
+# Spam and attempts to take payment off the platform, held for review
+?whatsapp
+?telegram
+?venmo
+?cashapp
+?paypal me
+?crypto giveaway
+?free money
+?casino
+?click here
+";
+
+/// Outcome of screening submitted text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterVerdict {
+  Allow,
+  /// Accept the text but keep it hidden until a moderator has looked at it
+  Hold(String),
+  /// Refuse the submission
+  Reject(String),
+}
+
+/// Screens user-generated text before it is published.
+///
+/// Implementations must be cheap enough to run inline on every submission; anything slower
+/// should return `Hold` and do its work from the moderation queue.
+pub trait ContentFilter: Send + Sync {
+  fn check(&self, text: &str) -> FilterVerdict;
+}
+
+/// Matches whole words and phrases from a local list, ignoring case and punctuation
+pub struct WordListFilter {
+  rejected: Vec<Vec<String>>,
+  held: Vec<Vec<String>>,
+}
+
+impl WordListFilter {
+  /// Parse a list with one entry per line; `#` starts a comment and `?` marks hold-only entries
+  pub fn parse(list: &str) -> Self {
+    let mut filter = Self {
+      rejected: Vec::new(),
+      held: Vec::new(),
+    };
+
+    for line in list.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let (entries, entry) = match line.strip_prefix('?') {
+        Some(entry) => (&mut filter.held, entry),
+        None => (&mut filter.rejected, line),
+      };
+      let words = Self::words(entry);
+      if !words.is_empty() {
+        entries.push(words);
+      }
+    }
+
+    filter
+  }
+
+  /// The list at `CONTENT_FILTER_WORDLIST`, or the built-in one
+  pub fn from_config(config: &Config) -> std::io::Result<Self> {
+    match &config.content_filter_wordlist {
+      Some(path) => Ok(Self::parse(&fs::read_to_string(path)?)),
+      None => Ok(Self::parse(DEFAULT_WORD_LIST)),
+    }
+  }
+
+  fn words(text: &str) -> Vec<String> {
+    text
+      .split(|c: char| !c.is_alphanumeric())
+      .filter(|word| !word.is_empty())
+      .map(str::to_lowercase)
+      .collect()
+  }
+
+  fn find<'a>(entries: &'a [Vec<String>], words: &[String]) -> Option<&'a [String]> {
+    entries
+      .iter()
+      .find(|entry| {
+        words
+          .windows(entry.len())
+          .any(|window| window == entry.as_slice())
+      })
+      .map(Vec::as_slice)
+  }
+}
+
+impl ContentFilter for WordListFilter {
+  fn check(&self, text: &str) -> FilterVerdict {
+    let words = Self::words(text);
+
+    if let Some(entry) = Self::find(&self.rejected, &words) {
+      return FilterVerdict::Reject(format!("contains blocked term '{}'", entry.join(" ")));
+    }
+    if let Some(entry) = Self::find(&self.held, &words) {
+      return FilterVerdict::Hold(format!("matched '{}'", entry.join(" ")));
+    }
+
+    FilterVerdict::Allow
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_word_list_matches_whole_words_and_phrases() {
+    let filter = WordListFilter::parse("# comment\nscam\n?free money\n\n?whatsapp\n");
+
+    assert_eq!(
+      filter.check("Great advice, thank you!"),
+      FilterVerdict::Allow
+    );
+    assert_eq!(filter.check("Scunthorpe scampi"), FilterVerdict::Allow);
+    assert_eq!(filter.check("money for free"), FilterVerdict::Allow);
+    assert_eq!(
+      filter.check("Total SCAM."),
+      FilterVerdict::Reject("contains blocked term 'scam'".to_string())
+    );
+    assert_eq!(
+      filter.check("Get FREE   money now"),
+      FilterVerdict::Hold("matched 'free money'".to_string())
+    );
+    assert!(matches!(
+      filter.check("message me on WhatsApp"),
+      FilterVerdict::Hold(_)
+    ));
+  }
+
+  #[test]
+  fn test_rejection_wins_over_hold() {
+    let filter = WordListFilter::parse("scam\n?casino");
+
+    assert!(matches!(
+      filter.check("casino scam"),
+      FilterVerdict::Reject(_)
+    ));
+  }
+
+  #[test]
+  fn test_default_list_parses() {
+    let filter = WordListFilter::parse(DEFAULT_WORD_LIST);
+
+    assert!(!filter.rejected.is_empty());
+    assert!(!filter.held.is_empty());
+    assert!(matches!(
+      filter.check("pay me on cashapp"),
+      FilterVerdict::Hold(_)
+    ));
+  }
+}
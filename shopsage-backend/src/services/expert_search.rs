@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rust_decimal::Decimal;
+use sea_orm::sea_query::{Alias, Expr, Query, SimpleExpr};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entities::{expert_categories, expert_profiles, prelude::*, users};
+use crate::services::categories::{CategoryError, CategoryService};
+
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// `ts_headline` options; matched terms are wrapped in `<mark>`
+const HEADLINE_OPTIONS: &str =
+  "StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=24, MinWords=8";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExpertSearchError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Invalid or expired cursor")]
+  InvalidCursor,
+  #[error("minRate must not be greater than maxRate")]
+  InvalidRateRange,
+  #[error("Sorting by relevance requires a search query")]
+  RelevanceWithoutText,
+  #[error("Unknown category '{0}'")]
+  UnknownCategory(String),
+}
+
+impl From<CategoryError> for ExpertSearchError {
+  fn from(err: CategoryError) -> Self {
+    match err {
+      CategoryError::DbError(err) => ExpertSearchError::DbError(err),
+      CategoryError::NotFound(slug) => ExpertSearchError::UnknownCategory(slug),
+    }
+  }
+}
+
+/// Column results are ordered by; ties are broken by profile id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpertSort {
+  /// Full-text rank plus trigram similarity; the default when searching by text
+  Relevance,
+  /// Smoothed rating, so a handful of reviews doesn't outrank a long record
+  Rating,
+  Price,
+  Consultations,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+  Asc,
+  Desc,
+}
+
+impl ExpertSort {
+  /// Best matching, best rated, cheapest and most experienced first
+  fn default_direction(self) -> SortDirection {
+    match self {
+      ExpertSort::Price => SortDirection::Asc,
+      ExpertSort::Relevance | ExpertSort::Rating | ExpertSort::Consultations => SortDirection::Desc,
+    }
+  }
+
+  fn parse_key(self, key: &str) -> Option<Value> {
+    match self {
+      ExpertSort::Relevance => key.parse::<f64>().ok().map(Value::from),
+      ExpertSort::Rating | ExpertSort::Price => Decimal::from_str(key).ok().map(Value::from),
+      ExpertSort::Consultations => key.parse::<i32>().ok().map(Value::from),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExpertSearchParams {
+  /// Matched against name, specialization and bio, tolerating small typos
+  pub text: Option<String>,
+  /// Category slug; experts filed under any of its subcategories match too
+  pub category: Option<String>,
+  pub min_rate: Option<Decimal>,
+  pub max_rate: Option<Decimal>,
+  pub min_rating: Option<Decimal>,
+  pub is_online: Option<bool>,
+  pub sort: Option<ExpertSort>,
+  pub direction: Option<SortDirection>,
+  pub limit: Option<u64>,
+  pub cursor: Option<String>,
+}
+
+impl ExpertSearchParams {
+  fn text(&self) -> Option<&str> {
+    self
+      .text
+      .as_deref()
+      .map(str::trim)
+      .filter(|text| !text.is_empty())
+  }
+
+  fn category(&self) -> Option<&str> {
+    self
+      .category
+      .as_deref()
+      .map(str::trim)
+      .filter(|category| !category.is_empty())
+  }
+}
+
+pub struct ExpertSearchHit {
+  pub expert: expert_profiles::Model,
+  pub user: users::Model,
+  /// Specialization and bio excerpt with matched terms in `<mark>`, when searching by text.
+  ///
+  /// Safe to render as HTML: the expert's text is escaped and `<mark>` is the only markup.
+  pub snippet: Option<String>,
+}
+
+pub struct ExpertSearchPage {
+  pub experts: Vec<ExpertSearchHit>,
+  /// Matches across all pages
+  pub total: u64,
+  pub next_cursor: Option<String>,
+}
+
+/// Opaque keyset position: the sort key and id of the last expert on the previous page.
+///
+/// It also records the ordering it was issued for, so a cursor can't be replayed against a
+/// different sort.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+  sort: ExpertSort,
+  direction: SortDirection,
+  key: String,
+  id: Uuid,
+}
+
+/// Verified-expert search, filtered, ordered and paginated in SQL.
+///
+/// Text queries go through the `expert_profiles.search_vector` GIN index, with `pg_trgm` word
+/// similarity on name and specialization as a fallback for misspellings.
+pub struct ExpertSearchService;
+
+impl ExpertSearchService {
+  pub async fn search<C: ConnectionTrait>(
+    db: &C,
+    params: &ExpertSearchParams,
+  ) -> Result<ExpertSearchPage, ExpertSearchError> {
+    let text = params.text();
+    let sort = params.sort.unwrap_or(if text.is_some() {
+      ExpertSort::Relevance
+    } else {
+      ExpertSort::Rating
+    });
+    let direction = params.direction.unwrap_or_else(|| sort.default_direction());
+    let limit = params
+      .limit
+      .unwrap_or(DEFAULT_PAGE_SIZE)
+      .clamp(1, MAX_PAGE_SIZE);
+    let key = Self::sort_key(sort, text)?;
+
+    let category_ids = match params.category() {
+      Some(slug) => Some(CategoryService::subtree_ids_for_slug(db, slug).await?),
+      None => None,
+    };
+
+    let filtered = Self::filtered(params, category_ids.as_deref())?;
+    let total = filtered.clone().count(db).await?;
+
+    let mut page = filtered;
+    if let Some(cursor) = &params.cursor {
+      page = page.filter(Self::after_cursor(cursor, sort, direction, key.clone())?);
+    }
+
+    let order = match direction {
+      SortDirection::Asc => Order::Asc,
+      SortDirection::Desc => Order::Desc,
+    };
+
+    // Ids and sort keys first, so relevance can be ordered and paged on like any column
+    let mut ranked: Vec<(Uuid, String)> = page
+      .select_only()
+      .column(expert_profiles::Column::Id)
+      .column_as(key.clone().cast_as(Alias::new("text")), "sort_key")
+      .order_by(key, order.clone())
+      .order_by(expert_profiles::Column::Id, order)
+      .limit(limit + 1)
+      .into_tuple()
+      .all(db)
+      .await?;
+
+    let has_more = ranked.len() as u64 > limit;
+    ranked.truncate(limit as usize);
+
+    let next_cursor = match ranked.last() {
+      Some((id, key)) if has_more => Some(Self::encode_cursor(sort, direction, key, *id)),
+      _ => None,
+    };
+
+    let ids: Vec<Uuid> = ranked.iter().map(|(id, _)| *id).collect();
+
+    let mut rows: HashMap<Uuid, (expert_profiles::Model, users::Model)> = ExpertProfiles::find()
+      .filter(expert_profiles::Column::Id.is_in(ids.clone()))
+      .find_also_related(Users)
+      .all(db)
+      .await?
+      .into_iter()
+      .filter_map(|(expert, user)| user.map(|user| (expert.id, (expert, user))))
+      .collect();
+
+    let mut snippets: HashMap<Uuid, String> = match text {
+      Some(text) if !ids.is_empty() => ExpertProfiles::find()
+        .select_only()
+        .column(expert_profiles::Column::Id)
+        .column_as(Self::headline(text), "snippet")
+        .filter(expert_profiles::Column::Id.is_in(ids.clone()))
+        .into_tuple::<(Uuid, String)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect(),
+      _ => HashMap::new(),
+    };
+
+    let experts = ids
+      .into_iter()
+      .filter_map(|id| {
+        rows.remove(&id).map(|(expert, user)| ExpertSearchHit {
+          expert,
+          user,
+          snippet: snippets.remove(&id),
+        })
+      })
+      .collect();
+
+    Ok(ExpertSearchPage {
+      experts,
+      total,
+      next_cursor,
+    })
+  }
+
+  /// Verified experts of users in good standing, narrowed by every filter in `params`.
+  ///
+  /// `category_ids` is the requested category's subtree, resolved from `params.category`.
+  pub fn filtered(
+    params: &ExpertSearchParams,
+    category_ids: Option<&[Uuid]>,
+  ) -> Result<Select<ExpertProfiles>, ExpertSearchError> {
+    if let (Some(min), Some(max)) = (params.min_rate, params.max_rate) {
+      if min > max {
+        return Err(ExpertSearchError::InvalidRateRange);
+      }
+    }
+
+    let mut query = ExpertProfiles::find()
+      .join(JoinType::InnerJoin, expert_profiles::Relation::Users.def())
+      .filter(expert_profiles::Column::IsVerified.eq(true))
+      .filter(users::Column::BannedAt.is_null());
+
+    if let Some(text) = params.text() {
+      query = query.filter(Expr::cust_with_values(
+        "(expert_profiles.search_vector @@ websearch_to_tsquery('english', $1) \
+         OR $1 <% users.name \
+         OR $1 <% expert_profiles.specialization)",
+        [text],
+      ));
+    }
+
+    if let Some(category_ids) = category_ids {
+      query = query.filter(
+        Expr::col((expert_profiles::Entity, expert_profiles::Column::Id)).in_subquery(
+          Query::select()
+            .column(expert_categories::Column::ExpertId)
+            .from(expert_categories::Entity)
+            .and_where(expert_categories::Column::CategoryId.is_in(category_ids.to_vec()))
+            .to_owned(),
+        ),
+      );
+    }
+
+    if let Some(min_rate) = params.min_rate {
+      query = query.filter(expert_profiles::Column::SessionRate.gte(min_rate));
+    }
+    if let Some(max_rate) = params.max_rate {
+      query = query.filter(expert_profiles::Column::SessionRate.lte(max_rate));
+    }
+    if let Some(min_rating) = params.min_rating {
+      query = query.filter(expert_profiles::Column::Rating.gte(min_rating));
+    }
+    if let Some(is_online) = params.is_online {
+      query = query.filter(expert_profiles::Column::IsOnline.eq(is_online));
+    }
+
+    Ok(query)
+  }
+
+  /// Expression results are ordered by
+  fn sort_key(sort: ExpertSort, text: Option<&str>) -> Result<SimpleExpr, ExpertSearchError> {
+    let column =
+      |column: expert_profiles::Column| Expr::col((expert_profiles::Entity, column)).into();
+
+    Ok(match sort {
+      ExpertSort::Relevance => {
+        let text = text.ok_or(ExpertSearchError::RelevanceWithoutText)?;
+        Expr::cust_with_values(
+          "(ts_rank(expert_profiles.search_vector, websearch_to_tsquery('english', $1)) \
+           + 0.5 * greatest(word_similarity($1, users.name), \
+                            word_similarity($1, expert_profiles.specialization)))",
+          [text],
+        )
+      }
+      ExpertSort::Rating => column(expert_profiles::Column::RatingScore),
+      ExpertSort::Price => column(expert_profiles::Column::SessionRate),
+      ExpertSort::Consultations => column(expert_profiles::Column::TotalConsultations),
+    })
+  }
+
+  /// Highlighted excerpt; the source text is escaped first so an expert can't inject markup
+  fn headline(text: &str) -> SimpleExpr {
+    Expr::cust_with_values(
+      format!(
+        "ts_headline('english', {}, websearch_to_tsquery('english', $1), $2)",
+        Self::escape_html(
+          "expert_profiles.specialization || ' - ' || \
+           CASE WHEN expert_profiles.bio_hidden_at IS NULL THEN expert_profiles.bio ELSE '' END"
+        )
+      ),
+      [text, HEADLINE_OPTIONS],
+    )
+  }
+
+  /// Wrap the SQL text expression `text` so it evaluates HTML-escaped; `&` has to go first
+  fn escape_html(text: &str) -> String {
+    [
+      ("&", "&amp;"),
+      ("<", "&lt;"),
+      (">", "&gt;"),
+      ("\"", "&quot;"),
+      ("''", "&#39;"),
+    ]
+    .into_iter()
+    .fold(text.to_string(), |sql, (from, to)| {
+      format!("replace({}, '{}', '{}')", sql, from, to)
+    })
+  }
+
+  /// Rows strictly after the cursor in `(key, id)` order
+  fn after_cursor(
+    cursor: &str,
+    sort: ExpertSort,
+    direction: SortDirection,
+    key: SimpleExpr,
+  ) -> Result<Condition, ExpertSearchError> {
+    let cursor = Self::decode_cursor(cursor)?;
+    if cursor.sort != sort || cursor.direction != direction {
+      return Err(ExpertSearchError::InvalidCursor);
+    }
+
+    let value = sort
+      .parse_key(&cursor.key)
+      .ok_or(ExpertSearchError::InvalidCursor)?;
+    let id = Expr::col((expert_profiles::Entity, expert_profiles::Column::Id));
+
+    let condition = match direction {
+      SortDirection::Asc => Condition::any()
+        .add(Expr::expr(key.clone()).gt(value.clone()))
+        .add(
+          Condition::all()
+            .add(Expr::expr(key).eq(value))
+            .add(id.gt(cursor.id)),
+        ),
+      SortDirection::Desc => Condition::any()
+        .add(Expr::expr(key.clone()).lt(value.clone()))
+        .add(
+          Condition::all()
+            .add(Expr::expr(key).eq(value))
+            .add(id.lt(cursor.id)),
+        ),
+    };
+
+    Ok(condition)
+  }
+
+  fn encode_cursor(sort: ExpertSort, direction: SortDirection, key: &str, id: Uuid) -> String {
+    let cursor = Cursor {
+      sort,
+      direction,
+      key: key.to_string(),
+      id,
+    };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+  }
+
+  fn decode_cursor(cursor: &str) -> Result<Cursor, ExpertSearchError> {
+    let bytes = URL_SAFE_NO_PAD
+      .decode(cursor)
+      .map_err(|_| ExpertSearchError::InvalidCursor)?;
+    serde_json::from_slice(&bytes).map_err(|_| ExpertSearchError::InvalidCursor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use sea_orm::sea_query::PostgresQueryBuilder;
+
+  fn sql(query: Select<ExpertProfiles>) -> String {
+    query.into_query().to_string(PostgresQueryBuilder)
+  }
+
+  #[test]
+  fn test_filters_are_applied_in_sql() {
+    let category_id = Uuid::new_v4();
+    let params = ExpertSearchParams {
+      text: Some("laptop repair".to_string()),
+      category: Some("computers-laptops".to_string()),
+      min_rate: Some(Decimal::from(10)),
+      max_rate: Some(Decimal::from(90)),
+      min_rating: Some(Decimal::from(4)),
+      is_online: Some(true),
+      ..Default::default()
+    };
+
+    let sql = sql(ExpertSearchService::filtered(&params, Some(&[category_id])).unwrap());
+    assert!(sql.contains(r#"INNER JOIN "users""#));
+    assert!(sql.contains(r#""users"."banned_at" IS NULL"#), "{sql}");
+    assert!(
+      sql.contains(
+        "expert_profiles.search_vector @@ websearch_to_tsquery('english', 'laptop repair')"
+      ),
+      "{sql}"
+    );
+    assert!(sql.contains("'laptop repair' <% users.name"), "{sql}");
+    assert!(
+      sql.contains(&format!(
+        r#""expert_profiles"."id" IN (SELECT "expert_id" FROM "expert_categories" WHERE "expert_categories"."category_id" IN ('{category_id}'))"#
+      )),
+      "{sql}"
+    );
+    assert!(
+      sql.contains(r#""expert_profiles"."session_rate" >= 10"#),
+      "{sql}"
+    );
+    assert!(
+      sql.contains(r#""expert_profiles"."session_rate" <= 90"#),
+      "{sql}"
+    );
+    assert!(sql.contains(r#""expert_profiles"."rating" >= 4"#), "{sql}");
+    assert!(
+      sql.contains(r#""expert_profiles"."is_online" = TRUE"#),
+      "{sql}"
+    );
+  }
+
+  #[test]
+  fn test_rate_range_must_be_ordered() {
+    let params = ExpertSearchParams {
+      min_rate: Some(Decimal::from(50)),
+      max_rate: Some(Decimal::from(10)),
+      ..Default::default()
+    };
+
+    assert!(matches!(
+      ExpertSearchService::filtered(&params, None),
+      Err(ExpertSearchError::InvalidRateRange)
+    ));
+  }
+
+  #[test]
+  fn test_relevance_ranks_by_text() {
+    let key = ExpertSearchService::sort_key(ExpertSort::Relevance, Some("iphone")).unwrap();
+    let sql = sql(ExpertProfiles::find().order_by_desc(key));
+    assert!(
+      sql.contains(
+        "ts_rank(expert_profiles.search_vector, websearch_to_tsquery('english', 'iphone'))"
+      ),
+      "{sql}"
+    );
+
+    assert!(matches!(
+      ExpertSearchService::sort_key(ExpertSort::Relevance, None),
+      Err(ExpertSearchError::RelevanceWithoutText)
+    ));
+  }
+
+  #[test]
+  fn test_headline_escapes_expert_text() {
+    assert_eq!(
+      ExpertSearchService::escape_html("bio"),
+      "replace(replace(replace(replace(replace(bio, '&', '&amp;'), '<', '&lt;'), \
+       '>', '&gt;'), '\"', '&quot;'), '''', '&#39;')"
+    );
+
+    let sql = sql(
+      ExpertProfiles::find()
+        .select_only()
+        .column_as(ExpertSearchService::headline("iphone"), "snippet"),
+    );
+    assert!(sql.contains("ts_headline('english', replace("), "{sql}");
+  }
+
+  #[test]
+  fn test_cursor_round_trip() {
+    let id = Uuid::new_v4();
+    let cursor =
+      ExpertSearchService::encode_cursor(ExpertSort::Rating, SortDirection::Desc, "4.80", id);
+    let key = ExpertSearchService::sort_key(ExpertSort::Rating, None).unwrap();
+
+    let condition = ExpertSearchService::after_cursor(
+      &cursor,
+      ExpertSort::Rating,
+      SortDirection::Desc,
+      key.clone(),
+    )
+    .unwrap();
+    let sql = sql(ExpertProfiles::find().filter(condition));
+    assert!(
+      sql.contains(r#""expert_profiles"."rating_score" < 4.80"#),
+      "{sql}"
+    );
+    assert!(
+      sql.contains(&format!(r#""expert_profiles"."id" < '{id}'"#)),
+      "{sql}"
+    );
+
+    // A cursor only continues the ordering it was issued for
+    assert!(matches!(
+      ExpertSearchService::after_cursor(
+        &cursor,
+        ExpertSort::Price,
+        SortDirection::Asc,
+        key.clone()
+      ),
+      Err(ExpertSearchError::InvalidCursor)
+    ));
+    assert!(matches!(
+      ExpertSearchService::after_cursor(
+        "not-a-cursor",
+        ExpertSort::Rating,
+        SortDirection::Desc,
+        key
+      ),
+      Err(ExpertSearchError::InvalidCursor)
+    ));
+  }
+}
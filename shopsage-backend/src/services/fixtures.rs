@@ -0,0 +1,97 @@
+//! Rows shared by the unit tests.
+//!
+//! Each factory returns a neutral row with fresh ids; tests override the columns they care about
+//! with struct update syntax, e.g. `sessions::Model { status, ..fixtures::session() }`, so a new
+//! column only has to be added here.
+
+use chrono::{DateTime, FixedOffset, Utc};
+use rust_decimal::Decimal;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::entities::{
+  categories, expert_profiles, sea_orm_active_enums::PricingModel,
+  sea_orm_active_enums::SessionStatus, sessions, users,
+};
+use crate::services::pricing::SUPPORTED_DURATIONS;
+
+pub fn now() -> DateTime<FixedOffset> {
+  Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())
+}
+
+/// A pending session that hasn't been booked or started
+pub fn session() -> sessions::Model {
+  let now = now();
+  sessions::Model {
+    id: Uuid::new_v4(),
+    expert_id: Uuid::new_v4(),
+    shopper_id: Uuid::new_v4(),
+    status: SessionStatus::Pending,
+    amount: Decimal::from(10),
+    start_time: None,
+    end_time: None,
+    booked_start: None,
+    booked_end: None,
+    notes: None,
+    duration_minutes: None,
+    quoted_price: None,
+    payment_reference: None,
+    created_at: now,
+    updated_at: now,
+  }
+}
+
+/// A verified, online expert without reviews who offers every duration at a flat rate
+pub fn expert_profile() -> expert_profiles::Model {
+  let now = now();
+  expert_profiles::Model {
+    id: Uuid::new_v4(),
+    user_id: Uuid::new_v4(),
+    specialization: "Electronics".to_string(),
+    bio: String::new(),
+    bio_hidden_at: None,
+    bio_held: false,
+    session_rate: Decimal::from(50),
+    rating: Decimal::ZERO,
+    rating_count: 0,
+    rating_sum: 0,
+    rating_score: Decimal::ZERO,
+    total_consultations: 0,
+    is_verified: true,
+    is_online: true,
+    profile_image_url: String::new(),
+    pricing_model: PricingModel::Flat,
+    allowed_durations: json!(SUPPORTED_DURATIONS),
+    created_at: now,
+    updated_at: now,
+  }
+}
+
+pub fn user() -> users::Model {
+  let now = now();
+  users::Model {
+    id: Uuid::new_v4(),
+    wallet_address: String::new(),
+    name: "User".to_string(),
+    email: String::new(),
+    is_admin: false,
+    banned_at: None,
+    ban_reason: None,
+    created_at: now,
+    updated_at: now,
+  }
+}
+
+/// A category filed under `parent`, or a root one
+pub fn category(slug: &str, name: &str, parent: Option<&categories::Model>) -> categories::Model {
+  let now = now();
+  categories::Model {
+    id: Uuid::new_v4(),
+    parent_id: parent.map(|parent| parent.id),
+    slug: slug.to_string(),
+    name: name.to_string(),
+    sort_order: 0,
+    created_at: now,
+    updated_at: now,
+  }
+}
@@ -1,2 +1,25 @@
+pub mod auth_session;
+pub mod availability;
+pub mod booking;
+pub mod categories;
+pub mod content_filter;
+pub mod expert_search;
+#[cfg(test)]
+pub mod fixtures;
+pub mod moderation;
+pub mod notifications;
+pub mod payments;
+pub mod policy;
+pub mod pricing;
+pub mod ratings;
+pub mod recommendations;
+pub mod reviews;
+pub mod saved_experts;
+pub mod session_events;
+pub mod session_lifecycle;
+pub mod siws;
 pub mod solana;
+pub mod stats;
+pub mod time_off;
+pub mod token;
 pub mod user_service;
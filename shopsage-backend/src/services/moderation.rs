@@ -0,0 +1,480 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{
+  content_reports, expert_profiles,
+  prelude::*,
+  reviews,
+  sea_orm_active_enums::{ContentType, ReportStatus},
+  users,
+};
+use crate::services::ratings::{RatingPrior, RatingService};
+use crate::services::reviews::MAX_TEXT_LENGTH;
+use crate::services::stats::StatsService;
+
+const DEFAULT_QUEUE_SIZE: u64 = 50;
+const MAX_QUEUE_SIZE: u64 = 200;
+/// Reason on the report queued when content a moderator hid is rewritten
+const EDITED_WHILE_HIDDEN: &str = "edited while hidden";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ModerationError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Content not found")]
+  ContentNotFound,
+  #[error("User not found")]
+  UserNotFound,
+  #[error("You cannot report your own content")]
+  OwnContent,
+  #[error("You have already reported this content")]
+  AlreadyReported,
+  #[error("Reason must be at most {MAX_TEXT_LENGTH} characters")]
+  ReasonTooLong,
+  #[error("Admins cannot be banned")]
+  CannotBanAdmin,
+}
+
+/// The reported text and who wrote it, as it currently stands
+#[derive(Debug, Clone)]
+pub struct ReportedContent {
+  pub author_id: Uuid,
+  pub text: String,
+  pub hidden_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A report in the moderation queue with the content it points at, if that still exists
+#[derive(Debug, Clone)]
+pub struct QueuedReport {
+  pub report: content_reports::Model,
+  pub content: Option<ReportedContent>,
+}
+
+/// Reports, hiding and bans for user-generated content.
+///
+/// Hidden content stays in the database so it can be restored; readers filter on `hidden_at`
+/// (reviews) and `bio_hidden_at` (expert bios). Hiding or restoring a review recomputes the
+/// expert's rating and the shopper's stats. Only a moderator can put back a bio they hid;
+/// `bio_held` marks the ones the content filter hid, which the expert clears by rewriting them.
+pub struct ModerationService;
+
+impl ModerationService {
+  /// File a user report against a review or expert bio
+  pub async fn report<C: ConnectionTrait>(
+    db: &C,
+    reporter_id: Uuid,
+    content_type: ContentType,
+    content_id: Uuid,
+    reason: Option<&str>,
+  ) -> Result<content_reports::Model, ModerationError> {
+    let reason = reason.map(str::trim).filter(|reason| !reason.is_empty());
+    if reason.is_some_and(|reason| reason.chars().count() > MAX_TEXT_LENGTH) {
+      return Err(ModerationError::ReasonTooLong);
+    }
+
+    let content = Self::content(db, content_type, content_id)
+      .await?
+      .ok_or(ModerationError::ContentNotFound)?;
+    if content.author_id == reporter_id {
+      return Err(ModerationError::OwnContent);
+    }
+
+    Self::new_report(content_type, content_id, Some(reporter_id), reason)
+      .insert(db)
+      .await
+      .map_err(|err| match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => ModerationError::AlreadyReported,
+        _ => err.into(),
+      })
+  }
+
+  /// Hide content the content filter flagged and queue it for a moderator
+  pub async fn hold<C: ConnectionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    content_type: ContentType,
+    content_id: Uuid,
+    reason: &str,
+  ) -> Result<(), DbErr> {
+    Self::set_hidden(db, prior, content_type, content_id, Some(Self::now())).await?;
+    if content_type == ContentType::ExpertBio {
+      ExpertProfiles::update_many()
+        .col_expr(expert_profiles::Column::BioHeld, Expr::value(true))
+        .filter(expert_profiles::Column::Id.eq(content_id))
+        .exec(db)
+        .await?;
+    }
+    Self::flag(db, content_type, content_id, reason).await
+  }
+
+  /// Screen a rewritten bio in the transaction that saves it.
+  ///
+  /// `held` is why the content filter wants the new text held, if it does. A bio the filter was
+  /// holding is shown again once the new text passes; one a moderator hid stays hidden and the
+  /// rewrite is queued for them instead.
+  pub async fn rescreen_bio<C: ConnectionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    previous: &expert_profiles::Model,
+    held: Option<&str>,
+  ) -> Result<(), DbErr> {
+    if previous.bio_hidden_at.is_some() && !previous.bio_held {
+      return Self::flag(
+        db,
+        ContentType::ExpertBio,
+        previous.id,
+        held.unwrap_or(EDITED_WHILE_HIDDEN),
+      )
+      .await;
+    }
+
+    match held {
+      Some(reason) => Self::hold(db, prior, ContentType::ExpertBio, previous.id, reason).await,
+      None if previous.bio_held => {
+        Self::set_hidden(db, prior, ContentType::ExpertBio, previous.id, None).await?;
+
+        // The filter's reports were about the old text
+        ContentReports::update_many()
+          .col_expr(
+            content_reports::Column::Status,
+            Expr::value(ReportStatus::Dismissed),
+          )
+          .col_expr(
+            content_reports::Column::ResolvedAt,
+            Expr::value(Self::now()),
+          )
+          .filter(content_reports::Column::ContentType.eq(ContentType::ExpertBio))
+          .filter(content_reports::Column::ContentId.eq(previous.id))
+          .filter(content_reports::Column::ReporterId.is_null())
+          .filter(content_reports::Column::Status.eq(ReportStatus::Open))
+          .exec(db)
+          .await?;
+
+        Ok(())
+      }
+      None => Ok(()),
+    }
+  }
+
+  /// Queue content that was stored hidden for a moderator, on behalf of the content filter
+  pub async fn flag<C: ConnectionTrait>(
+    db: &C,
+    content_type: ContentType,
+    content_id: Uuid,
+    reason: &str,
+  ) -> Result<(), DbErr> {
+    Self::new_report(content_type, content_id, None, Some(reason))
+      .insert(db)
+      .await?;
+
+    Ok(())
+  }
+
+  /// Reports with the given status, oldest first
+  pub async fn queue<C: ConnectionTrait>(
+    db: &C,
+    status: ReportStatus,
+    content_type: Option<ContentType>,
+    limit: Option<u64>,
+  ) -> Result<Vec<QueuedReport>, DbErr> {
+    let mut query = ContentReports::find().filter(content_reports::Column::Status.eq(status));
+    if let Some(content_type) = content_type {
+      query = query.filter(content_reports::Column::ContentType.eq(content_type));
+    }
+
+    let reports = query
+      .order_by_asc(content_reports::Column::CreatedAt)
+      .order_by_asc(content_reports::Column::Id)
+      .limit(limit.unwrap_or(DEFAULT_QUEUE_SIZE).clamp(1, MAX_QUEUE_SIZE))
+      .all(db)
+      .await?;
+
+    let mut queued = Vec::with_capacity(reports.len());
+    let mut contents: HashMap<(ContentType, Uuid), Option<ReportedContent>> = HashMap::new();
+    for report in reports {
+      // Several reports often point at the same content
+      let content = match contents.entry((report.content_type, report.content_id)) {
+        Entry::Occupied(entry) => entry.get().clone(),
+        Entry::Vacant(entry) => entry
+          .insert(Self::content(db, report.content_type, report.content_id).await?)
+          .clone(),
+      };
+
+      queued.push(QueuedReport { content, report });
+    }
+
+    Ok(queued)
+  }
+
+  /// Take content down and resolve its open reports
+  pub async fn hide<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    moderator_id: Uuid,
+    content_type: ContentType,
+    content_id: Uuid,
+  ) -> Result<(), ModerationError> {
+    let txn = db.begin().await?;
+
+    if !Self::set_hidden(&txn, prior, content_type, content_id, Some(Self::now())).await? {
+      return Err(ModerationError::ContentNotFound);
+    }
+    Self::close_reports(
+      &txn,
+      moderator_id,
+      content_type,
+      content_id,
+      ReportStatus::Resolved,
+    )
+    .await?;
+
+    txn.commit().await?;
+    Ok(())
+  }
+
+  /// Put hidden content back up and dismiss its open reports
+  pub async fn restore<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    moderator_id: Uuid,
+    content_type: ContentType,
+    content_id: Uuid,
+  ) -> Result<(), ModerationError> {
+    let txn = db.begin().await?;
+
+    if !Self::set_hidden(&txn, prior, content_type, content_id, None).await? {
+      return Err(ModerationError::ContentNotFound);
+    }
+    Self::close_reports(
+      &txn,
+      moderator_id,
+      content_type,
+      content_id,
+      ReportStatus::Dismissed,
+    )
+    .await?;
+
+    txn.commit().await?;
+    Ok(())
+  }
+
+  /// Ban a user: their reviews and bio are hidden and reports against them resolved.
+  ///
+  /// The caller revokes the user's auth sessions once this has committed.
+  pub async fn ban<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    moderator_id: Uuid,
+    user_id: Uuid,
+    reason: Option<&str>,
+  ) -> Result<users::Model, ModerationError> {
+    let user = Users::find_by_id(user_id)
+      .one(db)
+      .await?
+      .ok_or(ModerationError::UserNotFound)?;
+    if user.is_admin {
+      return Err(ModerationError::CannotBanAdmin);
+    }
+
+    let now = Self::now();
+    let txn = db.begin().await?;
+
+    let mut banned: users::ActiveModel = user.into();
+    banned.banned_at = Set(Some(now));
+    banned.ban_reason = Set(
+      reason
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(str::to_string),
+    );
+    banned.updated_at = Set(now);
+    let banned = banned.update(&txn).await?;
+
+    let review_ids: Vec<Uuid> = Reviews::find()
+      .select_only()
+      .column(reviews::Column::Id)
+      .filter(reviews::Column::ShopperId.eq(user_id))
+      .into_tuple()
+      .all(&txn)
+      .await?;
+    for review_id in review_ids {
+      Self::set_hidden(&txn, prior, ContentType::Review, review_id, Some(now)).await?;
+      Self::close_reports(
+        &txn,
+        moderator_id,
+        ContentType::Review,
+        review_id,
+        ReportStatus::Resolved,
+      )
+      .await?;
+    }
+
+    let expert_ids: Vec<Uuid> = ExpertProfiles::find()
+      .select_only()
+      .column(expert_profiles::Column::Id)
+      .filter(expert_profiles::Column::UserId.eq(user_id))
+      .into_tuple()
+      .all(&txn)
+      .await?;
+    for expert_id in expert_ids {
+      Self::set_hidden(&txn, prior, ContentType::ExpertBio, expert_id, Some(now)).await?;
+      Self::close_reports(
+        &txn,
+        moderator_id,
+        ContentType::ExpertBio,
+        expert_id,
+        ReportStatus::Resolved,
+      )
+      .await?;
+    }
+
+    txn.commit().await?;
+    Ok(banned)
+  }
+
+  /// Lift a ban. Content hidden by it stays hidden until restored one by one.
+  pub async fn unban<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+  ) -> Result<users::Model, ModerationError> {
+    let user = Users::find_by_id(user_id)
+      .one(db)
+      .await?
+      .ok_or(ModerationError::UserNotFound)?;
+
+    let mut user: users::ActiveModel = user.into();
+    user.banned_at = Set(None);
+    user.ban_reason = Set(None);
+    user.updated_at = Set(Self::now());
+
+    Ok(user.update(db).await?)
+  }
+
+  pub async fn is_banned<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<bool, DbErr> {
+    Ok(
+      Users::find_by_id(user_id)
+        .filter(users::Column::BannedAt.is_not_null())
+        .count(db)
+        .await?
+        > 0,
+    )
+  }
+
+  /// Returns whether the content exists
+  async fn set_hidden<C: ConnectionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    content_type: ContentType,
+    content_id: Uuid,
+    hidden_at: Option<DateTime<FixedOffset>>,
+  ) -> Result<bool, DbErr> {
+    match content_type {
+      ContentType::Review => {
+        let Some(review) = Reviews::find_by_id(content_id).one(db).await? else {
+          return Ok(false);
+        };
+
+        Reviews::update_many()
+          .col_expr(reviews::Column::HiddenAt, Expr::value(hidden_at))
+          .filter(reviews::Column::Id.eq(content_id))
+          .exec(db)
+          .await?;
+        StatsService::refresh_shopper(db, review.shopper_id).await?;
+        RatingService::refresh_expert(db, prior, review.expert_id).await?;
+      }
+      ContentType::ExpertBio => {
+        let result = ExpertProfiles::update_many()
+          .col_expr(expert_profiles::Column::BioHiddenAt, Expr::value(hidden_at))
+          .col_expr(expert_profiles::Column::BioHeld, Expr::value(false))
+          .filter(expert_profiles::Column::Id.eq(content_id))
+          .exec(db)
+          .await?;
+        if result.rows_affected == 0 {
+          return Ok(false);
+        }
+      }
+    }
+
+    Ok(true)
+  }
+
+  async fn close_reports<C: ConnectionTrait>(
+    db: &C,
+    moderator_id: Uuid,
+    content_type: ContentType,
+    content_id: Uuid,
+    status: ReportStatus,
+  ) -> Result<(), DbErr> {
+    ContentReports::update_many()
+      .col_expr(content_reports::Column::Status, Expr::value(status))
+      .col_expr(
+        content_reports::Column::ResolvedBy,
+        Expr::value(moderator_id),
+      )
+      .col_expr(
+        content_reports::Column::ResolvedAt,
+        Expr::value(Self::now()),
+      )
+      .filter(content_reports::Column::ContentType.eq(content_type))
+      .filter(content_reports::Column::ContentId.eq(content_id))
+      .filter(content_reports::Column::Status.eq(ReportStatus::Open))
+      .exec(db)
+      .await?;
+
+    Ok(())
+  }
+
+  async fn content<C: ConnectionTrait>(
+    db: &C,
+    content_type: ContentType,
+    content_id: Uuid,
+  ) -> Result<Option<ReportedContent>, DbErr> {
+    Ok(match content_type {
+      ContentType::Review => Reviews::find_by_id(content_id)
+        .one(db)
+        .await?
+        .map(|review| ReportedContent {
+          author_id: review.shopper_id,
+          text: review.comment,
+          hidden_at: review.hidden_at,
+        }),
+      ContentType::ExpertBio => {
+        ExpertProfiles::find_by_id(content_id)
+          .one(db)
+          .await?
+          .map(|expert| ReportedContent {
+            author_id: expert.user_id,
+            text: expert.bio,
+            hidden_at: expert.bio_hidden_at,
+          })
+      }
+    })
+  }
+
+  fn new_report(
+    content_type: ContentType,
+    content_id: Uuid,
+    reporter_id: Option<Uuid>,
+    reason: Option<&str>,
+  ) -> content_reports::ActiveModel {
+    content_reports::ActiveModel {
+      id: Set(Uuid::new_v4()),
+      content_type: Set(content_type),
+      content_id: Set(content_id),
+      reporter_id: Set(reporter_id),
+      reason: Set(reason.map(str::to_string)),
+      status: Set(ReportStatus::Open),
+      resolved_by: Set(None),
+      resolved_at: Set(None),
+      created_at: Set(Self::now()),
+    }
+  }
+
+  fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())
+  }
+}
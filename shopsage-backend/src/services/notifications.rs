@@ -0,0 +1,77 @@
+use chrono::{FixedOffset, Utc};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{notifications, prelude::*, sea_orm_active_enums::NotificationKind};
+
+/// Most notifications returned by a single listing
+pub const MAX_NOTIFICATIONS: u64 = 100;
+
+/// In-app notifications stored per user and read back by the app
+pub struct NotificationService;
+
+impl NotificationService {
+  pub async fn notify<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    kind: NotificationKind,
+    message: String,
+    session_id: Option<Uuid>,
+  ) -> Result<notifications::Model, DbErr> {
+    notifications::ActiveModel {
+      id: Set(Uuid::new_v4()),
+      user_id: Set(user_id),
+      kind: Set(kind),
+      message: Set(message),
+      session_id: Set(session_id),
+      read_at: Set(None),
+      created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    }
+    .insert(db)
+    .await
+  }
+
+  /// Newest first
+  pub async fn list_for_user<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    unread_only: bool,
+  ) -> Result<Vec<notifications::Model>, DbErr> {
+    let mut query = Notifications::find().filter(notifications::Column::UserId.eq(user_id));
+    if unread_only {
+      query = query.filter(notifications::Column::ReadAt.is_null());
+    }
+
+    query
+      .order_by_desc(notifications::Column::CreatedAt)
+      .limit(MAX_NOTIFICATIONS)
+      .all(db)
+      .await
+  }
+
+  /// Mark one of the user's notifications as read; `None` if it isn't theirs
+  pub async fn mark_read<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    notification_id: Uuid,
+  ) -> Result<Option<notifications::Model>, DbErr> {
+    let Some(notification) = Notifications::find_by_id(notification_id)
+      .filter(notifications::Column::UserId.eq(user_id))
+      .one(db)
+      .await?
+    else {
+      return Ok(None);
+    };
+
+    if notification.read_at.is_some() {
+      return Ok(Some(notification));
+    }
+
+    let mut active_model: notifications::ActiveModel = notification.into();
+    active_model.read_at = Set(Some(
+      Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    ));
+
+    Ok(Some(active_model.update(db).await?))
+  }
+}
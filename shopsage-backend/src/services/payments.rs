@@ -0,0 +1,563 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use rand::RngCore;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{
+  expert_profiles, payments,
+  prelude::*,
+  sea_orm_active_enums::{PaymentStatus, SessionStatus},
+  sessions, users,
+};
+use crate::services::solana::{ExpectedPayment, SolanaError, SolanaService, COMMITMENT_CONFIRMED};
+use crate::services::stats::StatsService;
+
+const DEFAULT_HISTORY_SIZE: u64 = 50;
+const MAX_HISTORY_SIZE: u64 = 200;
+const LAMPORTS_PER_SOL: i64 = 1_000_000_000;
+pub const CURRENCY_SOL: &str = "SOL";
+
+#[derive(thiserror::Error, Debug)]
+pub enum PaymentError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Payment not found")]
+  NotFound,
+  #[error("Session not found")]
+  SessionNotFound,
+  /// The session's shopper or expert row is gone
+  #[error("Session participant not found")]
+  UserNotFound(Uuid),
+  #[error("Only the session's shopper can pay for it")]
+  NotSessionShopper,
+  #[error("A '{}' session can't be paid for", .0.to_value())]
+  SessionNotPayable(SessionStatus),
+  #[error("This session has already been paid for")]
+  AlreadyPaid,
+  #[error("This transaction has already been used for a payment")]
+  DuplicateSignature,
+  #[error("Request a payment reference for this session before paying")]
+  MissingReference,
+  #[error("Transaction is not confirmed yet, submit it again shortly")]
+  NotYetConfirmed,
+  #[error("Payment is already {}", .0.to_value())]
+  AlreadySettled(PaymentStatus),
+  #[error("Invalid transaction signature")]
+  InvalidSignature,
+  #[error("Invalid session amount")]
+  InvalidAmount,
+  #[error("Transaction does not pay for this session: {0}")]
+  VerificationFailed(String),
+  #[error("Could not verify the transaction, try again later")]
+  Unavailable(#[source] SolanaError),
+}
+
+/// A payment with what the history screen shows about its session
+#[derive(Debug, Clone)]
+pub struct PaymentDetails {
+  pub payment: payments::Model,
+  pub expert_name: Option<String>,
+  pub expert_specialization: Option<String>,
+  pub session_start_time: Option<DateTime<FixedOffset>>,
+}
+
+/// On-chain payments for sessions.
+///
+/// The shopper first asks for the session's payment intent, which carries a Solana Pay reference
+/// key the transaction has to include; this binds the transaction to that one session. They then
+/// submit the signature of the transaction that paid for a session; it is recorded as confirmed
+/// once the chain shows it paying the expert and the treasury their shares of the session amount,
+/// or as pending while it isn't finalized yet. Pending payments are settled by resubmitting the
+/// signature or through the payment webhook.
+pub struct PaymentService;
+
+impl PaymentService {
+  /// Sessions can be paid for from booking until they are finished
+  pub fn is_payable(status: SessionStatus) -> bool {
+    matches!(
+      status,
+      SessionStatus::Scheduled
+        | SessionStatus::Pending
+        | SessionStatus::Active
+        | SessionStatus::Completed
+    )
+  }
+
+  /// Session amounts are stored in SOL
+  pub fn to_lamports(sol: Decimal) -> Option<i64> {
+    (sol * Decimal::from(LAMPORTS_PER_SOL))
+      .to_i64()
+      .filter(|lamports| *lamports > 0)
+  }
+
+  pub fn to_sol(lamports: i64) -> Decimal {
+    Decimal::from(lamports) / Decimal::from(LAMPORTS_PER_SOL)
+  }
+
+  /// Whether `signature` is a base58-encoded ed25519 signature
+  pub fn is_signature(signature: &str) -> bool {
+    bs58::decode(signature)
+      .into_vec()
+      .map(|bytes| bytes.len() == 64)
+      .unwrap_or(false)
+  }
+
+  /// What the shopper's transaction has to do to pay for a session, assigning the session a
+  /// payment reference if it has none yet
+  pub async fn intent<C: ConnectionTrait>(
+    db: &C,
+    treasury: &str,
+    shopper_id: Uuid,
+    session_id: Uuid,
+  ) -> Result<(sessions::Model, ExpectedPayment), PaymentError> {
+    let mut session = Self::unpaid_session(db, shopper_id, session_id).await?;
+
+    if session.payment_reference.is_none() {
+      let mut key = [0u8; 32];
+      rand::thread_rng().fill_bytes(&mut key);
+      Sessions::update_many()
+        .col_expr(
+          sessions::Column::PaymentReference,
+          Expr::value(bs58::encode(key).into_string()),
+        )
+        .filter(sessions::Column::Id.eq(session.id))
+        .filter(sessions::Column::PaymentReference.is_null())
+        .exec(db)
+        .await?;
+
+      // A concurrent request may have assigned one first
+      session = Sessions::find_by_id(session.id)
+        .one(db)
+        .await?
+        .ok_or(PaymentError::SessionNotFound)?;
+    }
+
+    let expected = Self::expected(db, treasury, &session).await?;
+    Ok((session, expected))
+  }
+
+  /// Record the shopper's transaction for a session and verify it on chain.
+  ///
+  /// Returns the payment along with the session it pays for. Nothing is recorded until the chain
+  /// shows the transaction paying for this session, so a signature can't be claimed by another
+  /// session before it lands. Resubmitting the signature of a pending payment verifies it again;
+  /// any other signature that is already recorded is refused.
+  /// A confirmed payment refreshes the stats of both parties along with it.
+  pub async fn submit<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    solana: &SolanaService,
+    treasury: &str,
+    shopper_id: Uuid,
+    session_id: Uuid,
+    tx_signature: &str,
+  ) -> Result<(payments::Model, sessions::Model), PaymentError> {
+    if !Self::is_signature(tx_signature) {
+      return Err(PaymentError::InvalidSignature);
+    }
+
+    let existing = Payments::find()
+      .filter(payments::Column::TxSignature.eq(tx_signature))
+      .one(db)
+      .await?;
+    if let Some(existing) = &existing {
+      if existing.session_id != session_id || existing.status != PaymentStatus::Pending {
+        return Err(PaymentError::DuplicateSignature);
+      }
+    }
+
+    let session = Self::unpaid_session(db, shopper_id, session_id).await?;
+    let expected = Self::expected(db, treasury, &session).await?;
+    let status = match solana.verify_payment(tx_signature, &expected).await {
+      // Not finalized yet; only record it as pending if the confirmed transaction checks out
+      Err(SolanaError::TransactionNotFound(_)) => Self::outcome(
+        solana
+          .verify_payment_at(tx_signature, &expected, COMMITMENT_CONFIRMED)
+          .await,
+      )
+      .map(|_| PaymentStatus::Pending)?,
+      verified => Self::outcome(verified)?,
+    };
+
+    let now = Self::now();
+    let txn = db.begin().await?;
+    let payment = match existing {
+      Some(_) => Self::resolve_pending(&txn, tx_signature, status, None).await?,
+      None => payments::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        session_id: Set(session.id),
+        payer_id: Set(session.shopper_id),
+        payee_id: Set(session.expert_id),
+        payer_wallet: Set(expected.payer),
+        payee_wallet: Set(expected.expert),
+        lamports: Set(expected.lamports as i64),
+        currency: Set(CURRENCY_SOL.to_string()),
+        tx_signature: Set(tx_signature.to_string()),
+        status: Set(status),
+        failure_reason: Set(None),
+        confirmed_at: Set((status == PaymentStatus::Confirmed).then_some(now)),
+        created_at: Set(now),
+        updated_at: Set(now),
+      }
+      .insert(&txn)
+      .await
+      .map_err(Self::conflict)?,
+    };
+    if payment.status == PaymentStatus::Confirmed {
+      StatsService::refresh_for_payment(&txn, &payment).await?;
+    }
+    txn.commit().await?;
+
+    Ok((payment, session))
+  }
+
+  /// Settle a pending payment as reported by the payment webhook.
+  ///
+  /// A reported confirmation is only recorded once the transaction verifies on chain against the
+  /// session; a transaction that doesn't pay for it marks the payment failed instead. Once
+  /// confirmed, the stats of both parties are refreshed along with it.
+  pub async fn settle<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    solana: &SolanaService,
+    treasury: &str,
+    tx_signature: &str,
+    status: PaymentStatus,
+    reason: Option<&str>,
+  ) -> Result<payments::Model, PaymentError> {
+    let payment = Payments::find()
+      .filter(payments::Column::TxSignature.eq(tx_signature))
+      .one(db)
+      .await?
+      .ok_or(PaymentError::NotFound)?;
+
+    // Webhooks are delivered at least once
+    if payment.status == status {
+      return Ok(payment);
+    }
+    if payment.status != PaymentStatus::Pending {
+      return Err(PaymentError::AlreadySettled(payment.status));
+    }
+
+    let (status, reason) = match status {
+      PaymentStatus::Confirmed => {
+        let session = Sessions::find_by_id(payment.session_id)
+          .one(db)
+          .await?
+          .ok_or(PaymentError::SessionNotFound)?;
+        let expected = Self::expected(db, treasury, &session).await?;
+        Self::settled(solana.verify_payment(tx_signature, &expected).await)?
+      }
+      _ => (status, reason.map(str::to_string)),
+    };
+
+    let txn = db.begin().await?;
+    let payment = Self::resolve_pending(&txn, tx_signature, status, reason).await?;
+    if payment.status == PaymentStatus::Confirmed {
+      StatsService::refresh_for_payment(&txn, &payment).await?;
+    }
+    txn.commit().await?;
+
+    Ok(payment)
+  }
+
+  /// Move a pending payment to `status`.
+  ///
+  /// Only a row that is still pending is written, so a payment settled concurrently by the
+  /// webhook or a resubmission is never overwritten; reporting the status it already has is fine.
+  async fn resolve_pending(
+    txn: &DatabaseTransaction,
+    tx_signature: &str,
+    status: PaymentStatus,
+    reason: Option<String>,
+  ) -> Result<payments::Model, PaymentError> {
+    let now = Self::now();
+    let mut update = Payments::update_many()
+      .col_expr(payments::Column::Status, Expr::value(status))
+      .col_expr(payments::Column::UpdatedAt, Expr::value(now));
+    update = match status {
+      PaymentStatus::Confirmed => {
+        update.col_expr(payments::Column::ConfirmedAt, Expr::value(Some(now)))
+      }
+      PaymentStatus::Failed => {
+        update.col_expr(payments::Column::FailureReason, Expr::value(reason))
+      }
+      PaymentStatus::Pending => update,
+    };
+    let result = update
+      .filter(payments::Column::TxSignature.eq(tx_signature))
+      .filter(payments::Column::Status.eq(PaymentStatus::Pending))
+      .exec(txn)
+      .await
+      .map_err(Self::conflict)?;
+
+    let payment = Payments::find()
+      .filter(payments::Column::TxSignature.eq(tx_signature))
+      .one(txn)
+      .await?
+      .ok_or(PaymentError::NotFound)?;
+    if result.rows_affected == 0 && payment.status != status {
+      return Err(PaymentError::AlreadySettled(payment.status));
+    }
+
+    Ok(payment)
+  }
+
+  /// Payments the user made or received, newest first
+  pub async fn history<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    limit: Option<u64>,
+  ) -> Result<Vec<PaymentDetails>, DbErr> {
+    let payments = Payments::find()
+      .filter(
+        Condition::any()
+          .add(payments::Column::PayerId.eq(user_id))
+          .add(payments::Column::PayeeId.eq(user_id)),
+      )
+      .order_by_desc(payments::Column::CreatedAt)
+      .limit(
+        limit
+          .unwrap_or(DEFAULT_HISTORY_SIZE)
+          .clamp(1, MAX_HISTORY_SIZE),
+      )
+      .all(db)
+      .await?;
+
+    let start_times: HashMap<Uuid, Option<DateTime<FixedOffset>>> = Sessions::find()
+      .select_only()
+      .column(sessions::Column::Id)
+      .column(sessions::Column::BookedStart)
+      .filter(sessions::Column::Id.is_in(payments.iter().map(|payment| payment.session_id)))
+      .into_tuple()
+      .all(db)
+      .await?
+      .into_iter()
+      .collect();
+
+    let expert_ids = || payments.iter().map(|payment| payment.payee_id);
+    let names: HashMap<Uuid, String> = Users::find()
+      .select_only()
+      .column(users::Column::Id)
+      .column(users::Column::Name)
+      .filter(users::Column::Id.is_in(expert_ids()))
+      .into_tuple()
+      .all(db)
+      .await?
+      .into_iter()
+      .collect();
+    let specializations: HashMap<Uuid, String> = ExpertProfiles::find()
+      .select_only()
+      .column(expert_profiles::Column::UserId)
+      .column(expert_profiles::Column::Specialization)
+      .filter(expert_profiles::Column::UserId.is_in(expert_ids()))
+      .into_tuple()
+      .all(db)
+      .await?
+      .into_iter()
+      .collect();
+
+    Ok(
+      payments
+        .into_iter()
+        .map(|payment| PaymentDetails {
+          expert_name: names.get(&payment.payee_id).cloned(),
+          expert_specialization: specializations.get(&payment.payee_id).cloned(),
+          session_start_time: start_times.get(&payment.session_id).copied().flatten(),
+          payment,
+        })
+        .collect(),
+    )
+  }
+
+  /// Map a verification attempt to the status to record; a transaction the RPC node doesn't
+  /// know yet is not recorded at all
+  fn outcome(verified: Result<(), SolanaError>) -> Result<PaymentStatus, PaymentError> {
+    match verified {
+      Ok(()) => Ok(PaymentStatus::Confirmed),
+      Err(SolanaError::TransactionNotFound(_)) => Err(PaymentError::NotYetConfirmed),
+      Err(SolanaError::InvalidSignature(_)) => Err(PaymentError::InvalidSignature),
+      Err(SolanaError::TransactionFailed(reason) | SolanaError::PaymentMismatch(reason)) => {
+        Err(PaymentError::VerificationFailed(reason))
+      }
+      Err(SolanaError::InvalidAmount) => Err(PaymentError::VerificationFailed(
+        "no transfer found".to_string(),
+      )),
+      Err(SolanaError::InvalidWalletAddress(address)) => Err(PaymentError::VerificationFailed(
+        format!("invalid wallet address {}", address),
+      )),
+      Err(err @ (SolanaError::RequestError(_) | SolanaError::JsonError(_))) => {
+        Err(PaymentError::Unavailable(err))
+      }
+    }
+  }
+
+  /// Map the check behind a reported confirmation to the status to record; until our RPC node
+  /// sees the transaction the payment stays pending and the webhook has to be retried
+  fn settled(
+    verified: Result<(), SolanaError>,
+  ) -> Result<(PaymentStatus, Option<String>), PaymentError> {
+    match verified {
+      Ok(()) => Ok((PaymentStatus::Confirmed, None)),
+      Err(
+        err @ (SolanaError::TransactionNotFound(_)
+        | SolanaError::RequestError(_)
+        | SolanaError::JsonError(_)),
+      ) => Err(PaymentError::Unavailable(err)),
+      Err(err) => Ok((PaymentStatus::Failed, Some(err.to_string()))),
+    }
+  }
+
+  /// A concurrent request can record the same transaction or pay for the session first; the
+  /// unique indexes on `payments` catch both
+  fn conflict(err: DbErr) -> PaymentError {
+    match err.sql_err() {
+      Some(SqlErr::UniqueConstraintViolation(message))
+        if message.contains("uq_payments_session_id_confirmed") =>
+      {
+        PaymentError::AlreadyPaid
+      }
+      Some(SqlErr::UniqueConstraintViolation(_)) => PaymentError::DuplicateSignature,
+      _ => err.into(),
+    }
+  }
+
+  /// The shopper's session, as long as it can still be paid for
+  async fn unpaid_session<C: ConnectionTrait>(
+    db: &C,
+    shopper_id: Uuid,
+    session_id: Uuid,
+  ) -> Result<sessions::Model, PaymentError> {
+    let session = Sessions::find_by_id(session_id)
+      .one(db)
+      .await?
+      .ok_or(PaymentError::SessionNotFound)?;
+    if session.shopper_id != shopper_id {
+      return Err(PaymentError::NotSessionShopper);
+    }
+    if !Self::is_payable(session.status) {
+      return Err(PaymentError::SessionNotPayable(session.status));
+    }
+
+    let paid = Payments::find()
+      .filter(payments::Column::SessionId.eq(session.id))
+      .filter(payments::Column::Status.eq(PaymentStatus::Confirmed))
+      .count(db)
+      .await?;
+    if paid > 0 {
+      return Err(PaymentError::AlreadyPaid);
+    }
+
+    Ok(session)
+  }
+
+  async fn expected<C: ConnectionTrait>(
+    db: &C,
+    treasury: &str,
+    session: &sessions::Model,
+  ) -> Result<ExpectedPayment, PaymentError> {
+    let reference = session
+      .payment_reference
+      .clone()
+      .ok_or(PaymentError::MissingReference)?;
+    let lamports = Self::to_lamports(session.amount).ok_or(PaymentError::InvalidAmount)?;
+
+    Ok(ExpectedPayment {
+      payer: Self::wallet(db, session.shopper_id).await?,
+      expert: Self::wallet(db, session.expert_id).await?,
+      treasury: treasury.to_string(),
+      lamports: lamports as u64,
+      reference,
+    })
+  }
+
+  async fn wallet<C: ConnectionTrait>(db: &C, user_id: Uuid) -> Result<String, PaymentError> {
+    Users::find_by_id(user_id)
+      .select_only()
+      .column(users::Column::WalletAddress)
+      .into_tuple()
+      .one(db)
+      .await?
+      .ok_or(PaymentError::UserNotFound(user_id))
+  }
+
+  fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_lamports_conversion() {
+    assert_eq!(
+      PaymentService::to_lamports(Decimal::new(125, 2)),
+      Some(1_250_000_000)
+    );
+    assert_eq!(PaymentService::to_lamports(Decimal::ZERO), None);
+    assert_eq!(PaymentService::to_lamports(Decimal::from(-1)), None);
+    assert_eq!(PaymentService::to_sol(1_250_000_000), Decimal::new(125, 2));
+  }
+
+  #[test]
+  fn test_finished_sessions_are_not_payable() {
+    use SessionStatus::*;
+
+    assert!(PaymentService::is_payable(Scheduled));
+    assert!(PaymentService::is_payable(Completed));
+    for status in [NoShow, Disputed, Cancelled, Refunded] {
+      assert!(!PaymentService::is_payable(status));
+    }
+  }
+
+  #[test]
+  fn test_verification_outcome() {
+    assert_eq!(
+      PaymentService::outcome(Ok(())).unwrap(),
+      PaymentStatus::Confirmed
+    );
+    assert!(matches!(
+      PaymentService::outcome(Err(SolanaError::TransactionNotFound("sig".to_string()))),
+      Err(PaymentError::NotYetConfirmed)
+    ));
+    assert!(matches!(
+      PaymentService::outcome(Err(SolanaError::PaymentMismatch(
+        "expert received 0 lamports, expected 800".to_string()
+      ))),
+      Err(PaymentError::VerificationFailed(_))
+    ));
+    assert!(matches!(
+      PaymentService::outcome(Err(SolanaError::InvalidSignature("sig".to_string()))),
+      Err(PaymentError::InvalidSignature)
+    ));
+    assert!(PaymentService::is_signature(
+      &bs58::encode([7u8; 64]).into_string()
+    ));
+    assert!(!PaymentService::is_signature(
+      &bs58::encode([7u8; 32]).into_string()
+    ));
+  }
+
+  #[test]
+  fn test_reported_confirmation_must_verify() {
+    assert_eq!(
+      PaymentService::settled(Ok(())).unwrap(),
+      (PaymentStatus::Confirmed, None)
+    );
+    assert!(matches!(
+      PaymentService::settled(Err(SolanaError::TransactionNotFound("sig".to_string()))),
+      Err(PaymentError::Unavailable(_))
+    ));
+    let (status, reason) = PaymentService::settled(Err(SolanaError::PaymentMismatch(
+      "transaction doesn't include the session's payment reference".to_string(),
+    )))
+    .unwrap();
+    assert_eq!(status, PaymentStatus::Failed);
+    assert!(reason.unwrap().contains("payment reference"));
+  }
+}
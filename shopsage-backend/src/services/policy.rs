@@ -0,0 +1,228 @@
+use std::fmt;
+
+use sea_orm::ActiveEnum;
+use uuid::Uuid;
+
+use crate::entities::{sea_orm_active_enums::SessionStatus, sessions};
+use crate::middleware::auth::AuthUser;
+use crate::services::user_service::UserRole;
+
+/// Returned when a policy refuses an action; handlers turn it into a 403 `{ "error": ... }` body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessDenied {
+  pub reason: String,
+}
+
+impl AccessDenied {
+  pub fn new(reason: impl Into<String>) -> Self {
+    Self {
+      reason: reason.into(),
+    }
+  }
+}
+
+impl fmt::Display for AccessDenied {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Access denied: {}", self.reason)
+  }
+}
+
+/// An authorization rule deciding whether `actor` may act on a resource of type `R`
+pub trait Policy<R: ?Sized> {
+  fn evaluate(&self, actor: &AuthUser, resource: &R) -> Result<(), AccessDenied>;
+}
+
+/// The caller must hold the given role
+pub struct RequireRole(pub UserRole);
+
+impl Policy<()> for RequireRole {
+  fn evaluate(&self, actor: &AuthUser, _resource: &()) -> Result<(), AccessDenied> {
+    if actor.has_role(self.0) {
+      Ok(())
+    } else {
+      Err(AccessDenied::new(format!(
+        "requires the {} role",
+        self.0.as_str()
+      )))
+    }
+  }
+}
+
+/// The caller must be the user owning the resource, or an admin
+pub struct OwnerOrAdmin;
+
+impl Policy<Uuid> for OwnerOrAdmin {
+  fn evaluate(&self, actor: &AuthUser, owner_id: &Uuid) -> Result<(), AccessDenied> {
+    if actor.is_admin() || actor.id == *owner_id {
+      Ok(())
+    } else {
+      Err(AccessDenied::new("resource belongs to another user"))
+    }
+  }
+}
+
+/// Which side of a session the caller is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionParty {
+  Shopper,
+  Expert,
+  Admin,
+}
+
+impl SessionParty {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SessionParty::Shopper => "shopper",
+      SessionParty::Expert => "expert",
+      SessionParty::Admin => "admin",
+    }
+  }
+
+  /// Resolve the caller's party; participants take precedence over the admin role
+  pub fn of(actor: &AuthUser, session: &sessions::Model) -> Option<Self> {
+    if actor.id == session.shopper_id {
+      Some(Self::Shopper)
+    } else if actor.id == session.expert_id {
+      Some(Self::Expert)
+    } else if actor.is_admin() {
+      Some(Self::Admin)
+    } else {
+      None
+    }
+  }
+}
+
+/// Access to a single consultation session, restricted to its shopper, its expert or an admin
+pub enum SessionPolicy {
+  View,
+  ChangeStatus(SessionStatus),
+}
+
+impl Policy<sessions::Model> for SessionPolicy {
+  fn evaluate(&self, actor: &AuthUser, session: &sessions::Model) -> Result<(), AccessDenied> {
+    let party = SessionParty::of(actor, session)
+      .ok_or_else(|| AccessDenied::new("not a participant of this session"))?;
+
+    match self {
+      SessionPolicy::View => Ok(()),
+      SessionPolicy::ChangeStatus(status) => {
+        use SessionStatus::*;
+
+        // Refunds and dispute resolution are left to admins
+        let allowed = match party {
+          SessionParty::Admin => true,
+          SessionParty::Shopper => matches!(status, Cancelled | Disputed),
+          SessionParty::Expert => {
+            matches!(status, Pending | Active | Completed | Cancelled | NoShow)
+          }
+        };
+
+        if allowed {
+          Ok(())
+        } else {
+          Err(AccessDenied::new(format!(
+            "the {} may not set status '{}'",
+            party.as_str(),
+            status.to_value()
+          )))
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::fixtures;
+  use crate::services::user_service::Roles;
+
+  fn actor(id: Uuid, roles: Roles) -> AuthUser {
+    AuthUser {
+      id,
+      session_id: Uuid::new_v4(),
+      roles,
+    }
+  }
+
+  fn session(shopper_id: Uuid, expert_id: Uuid) -> sessions::Model {
+    sessions::Model {
+      shopper_id,
+      expert_id,
+      ..fixtures::session()
+    }
+  }
+
+  #[test]
+  fn test_session_access_limited_to_participants() {
+    let (shopper_id, expert_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let session = session(shopper_id, expert_id);
+
+    let shopper = actor(shopper_id, Roles::default());
+    let expert = actor(expert_id, Roles::default());
+    let stranger = actor(Uuid::new_v4(), Roles::default());
+    let admin = actor(
+      Uuid::new_v4(),
+      Roles {
+        admin: true,
+        ..Roles::default()
+      },
+    );
+
+    assert!(shopper.authorize(SessionPolicy::View, &session).is_ok());
+    assert!(expert.authorize(SessionPolicy::View, &session).is_ok());
+    assert!(admin.authorize(SessionPolicy::View, &session).is_ok());
+    assert!(stranger.authorize(SessionPolicy::View, &session).is_err());
+  }
+
+  #[test]
+  fn test_status_changes_limited_by_party() {
+    let (shopper_id, expert_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let session = session(shopper_id, expert_id);
+    let shopper = actor(shopper_id, Roles::default());
+    let expert = actor(expert_id, Roles::default());
+
+    assert!(shopper
+      .authorize(
+        SessionPolicy::ChangeStatus(SessionStatus::Cancelled),
+        &session
+      )
+      .is_ok());
+    assert!(shopper
+      .authorize(
+        SessionPolicy::ChangeStatus(SessionStatus::Completed),
+        &session
+      )
+      .is_err());
+    assert!(expert
+      .authorize(
+        SessionPolicy::ChangeStatus(SessionStatus::Completed),
+        &session
+      )
+      .is_ok());
+    assert!(expert
+      .authorize(
+        SessionPolicy::ChangeStatus(SessionStatus::Refunded),
+        &session
+      )
+      .is_err());
+  }
+
+  #[test]
+  fn test_owner_or_admin() {
+    let owner_id = Uuid::new_v4();
+    let owner = actor(owner_id, Roles::default());
+    let other = actor(Uuid::new_v4(), Roles::default());
+    let admin = actor(
+      Uuid::new_v4(),
+      Roles {
+        admin: true,
+        ..Roles::default()
+      },
+    );
+
+    assert!(owner.authorize(OwnerOrAdmin, &owner_id).is_ok());
+    assert!(admin.authorize(OwnerOrAdmin, &owner_id).is_ok());
+    assert!(other.authorize(OwnerOrAdmin, &owner_id).is_err());
+  }
+}
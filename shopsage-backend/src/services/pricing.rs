@@ -0,0 +1,209 @@
+use rust_decimal::Decimal;
+
+use crate::entities::{expert_profiles, sea_orm_active_enums::PricingModel};
+
+/// Session lengths, in minutes, an expert can offer
+pub const SUPPORTED_DURATIONS: [i32; 3] = [15, 30, 60];
+
+#[derive(thiserror::Error, Debug)]
+pub enum PricingError {
+  #[error("Unsupported session duration {0}, expected one of 15, 30 or 60 minutes")]
+  UnsupportedDuration(i32),
+  #[error("The expert does not offer {0} minute sessions")]
+  DurationNotOffered(i32),
+  #[error("At least one session duration must be offered")]
+  NoDurations,
+  #[error("Amount {supplied} does not match the quoted price {quoted}")]
+  AmountMismatch { quoted: Decimal, supplied: Decimal },
+}
+
+/// Server-side price of a booking
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quote {
+  pub duration_minutes: i32,
+  pub pricing_model: PricingModel,
+  pub price: Decimal,
+}
+
+/// Pricing settings saved along with an expert profile; build it with `PricingService::update`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PricingUpdate {
+  pub pricing_model: Option<PricingModel>,
+  pub allowed_durations: Option<Vec<i32>>,
+}
+
+pub struct PricingService;
+
+impl PricingService {
+  /// Durations the expert accepts, smallest first; falls back to every supported duration
+  pub fn allowed_durations(expert: &expert_profiles::Model) -> Vec<i32> {
+    let mut durations: Vec<i32> =
+      serde_json::from_value::<Vec<i32>>(expert.allowed_durations.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|duration| SUPPORTED_DURATIONS.contains(duration))
+        .collect();
+    durations.sort_unstable();
+    durations.dedup();
+
+    if durations.is_empty() {
+      SUPPORTED_DURATIONS.to_vec()
+    } else {
+      durations
+    }
+  }
+
+  /// Price a session of `duration_minutes`, or of the expert's shortest duration when omitted
+  pub fn quote(
+    expert: &expert_profiles::Model,
+    duration_minutes: Option<i32>,
+  ) -> Result<Quote, PricingError> {
+    let allowed = Self::allowed_durations(expert);
+    let duration_minutes = duration_minutes.unwrap_or(allowed[0]);
+
+    if !SUPPORTED_DURATIONS.contains(&duration_minutes) {
+      return Err(PricingError::UnsupportedDuration(duration_minutes));
+    }
+    if !allowed.contains(&duration_minutes) {
+      return Err(PricingError::DurationNotOffered(duration_minutes));
+    }
+
+    let price = match expert.pricing_model {
+      PricingModel::Flat => expert.session_rate,
+      PricingModel::PerMinute => expert.session_rate * Decimal::from(duration_minutes),
+    };
+
+    Ok(Quote {
+      duration_minutes,
+      pricing_model: expert.pricing_model,
+      price: price.round_dp(2),
+    })
+  }
+
+  /// Reject a client-supplied amount unless it equals the quote
+  pub fn check_amount(quote: &Quote, supplied: Decimal) -> Result<(), PricingError> {
+    if supplied == quote.price {
+      Ok(())
+    } else {
+      Err(PricingError::AmountMismatch {
+        quoted: quote.price,
+        supplied,
+      })
+    }
+  }
+
+  /// Validate and normalise a set of offered durations
+  pub fn validate_durations(durations: &[i32]) -> Result<Vec<i32>, PricingError> {
+    if let Some(duration) = durations
+      .iter()
+      .find(|duration| !SUPPORTED_DURATIONS.contains(duration))
+    {
+      return Err(PricingError::UnsupportedDuration(*duration));
+    }
+
+    let mut durations = durations.to_vec();
+    durations.sort_unstable();
+    durations.dedup();
+
+    if durations.is_empty() {
+      return Err(PricingError::NoDurations);
+    }
+
+    Ok(durations)
+  }
+
+  /// Validate the pricing settings an expert asked for
+  pub fn update(
+    pricing_model: Option<PricingModel>,
+    allowed_durations: Option<Vec<i32>>,
+  ) -> Result<PricingUpdate, PricingError> {
+    Ok(PricingUpdate {
+      pricing_model,
+      allowed_durations: allowed_durations
+        .map(|durations| Self::validate_durations(&durations))
+        .transpose()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::fixtures;
+  use serde_json::json;
+
+  fn expert(
+    pricing_model: PricingModel,
+    rate: i64,
+    durations: serde_json::Value,
+  ) -> expert_profiles::Model {
+    expert_profiles::Model {
+      pricing_model,
+      session_rate: Decimal::from(rate),
+      allowed_durations: durations,
+      ..fixtures::expert_profile()
+    }
+  }
+
+  #[test]
+  fn test_flat_and_per_minute_quotes() {
+    let flat = expert(PricingModel::Flat, 85, json!([15, 30, 60]));
+    assert_eq!(
+      PricingService::quote(&flat, Some(60)).unwrap().price,
+      Decimal::from(85)
+    );
+
+    let per_minute = expert(PricingModel::PerMinute, 2, json!([15, 30, 60]));
+    let quote = PricingService::quote(&per_minute, Some(30)).unwrap();
+    assert_eq!(quote.price, Decimal::from(60));
+    assert_eq!(quote.duration_minutes, 30);
+  }
+
+  #[test]
+  fn test_duration_must_be_offered() {
+    let expert = expert(PricingModel::Flat, 50, json!([30, 60]));
+
+    assert_eq!(
+      PricingService::quote(&expert, None)
+        .unwrap()
+        .duration_minutes,
+      30
+    );
+    assert!(matches!(
+      PricingService::quote(&expert, Some(15)),
+      Err(PricingError::DurationNotOffered(15))
+    ));
+    assert!(matches!(
+      PricingService::quote(&expert, Some(45)),
+      Err(PricingError::UnsupportedDuration(45))
+    ));
+  }
+
+  #[test]
+  fn test_amount_must_match_quote() {
+    let expert = expert(PricingModel::Flat, 50, json!([30]));
+    let quote = PricingService::quote(&expert, None).unwrap();
+
+    assert!(PricingService::check_amount(&quote, "50.00".parse().unwrap()).is_ok());
+    assert!(matches!(
+      PricingService::check_amount(&quote, Decimal::from(1)),
+      Err(PricingError::AmountMismatch { .. })
+    ));
+  }
+
+  #[test]
+  fn test_validate_durations() {
+    assert_eq!(
+      PricingService::validate_durations(&[60, 15, 60]).unwrap(),
+      vec![15, 60]
+    );
+    assert!(matches!(
+      PricingService::validate_durations(&[]),
+      Err(PricingError::NoDurations)
+    ));
+    assert!(matches!(
+      PricingService::validate_durations(&[20]),
+      Err(PricingError::UnsupportedDuration(20))
+    ));
+  }
+}
@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+use chrono::{FixedOffset, Utc};
+use rust_decimal::Decimal;
+use sea_orm::sea_query::{Alias, Expr, Func, SimpleExpr};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{expert_profiles, prelude::*, reviews};
+
+/// What an expert's rating is assumed to be before their reviews say otherwise.
+///
+/// `weight` is how many reviews the prior counts as: the higher it is, the more reviews an expert
+/// needs before their own average dominates their score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatingPrior {
+  pub mean: Decimal,
+  pub weight: Decimal,
+}
+
+impl Default for RatingPrior {
+  fn default() -> Self {
+    // Keep in line with the backfill in the rating aggregates migration
+    Self {
+      mean: Decimal::new(35, 1),
+      weight: Decimal::from(5),
+    }
+  }
+}
+
+impl RatingPrior {
+  /// Parse `mean=..,weight=..`; either may be left out to keep its default.
+  ///
+  /// Returns the offending entry on error.
+  pub fn from_spec(spec: &str) -> Result<Self, String> {
+    let mut prior = Self::default();
+
+    for entry in spec
+      .split(',')
+      .map(str::trim)
+      .filter(|entry| !entry.is_empty())
+    {
+      let (name, value) = entry.split_once('=').ok_or_else(|| entry.to_string())?;
+      let value = Decimal::from_str(value.trim()).map_err(|_| entry.to_string())?;
+
+      match name.trim() {
+        "mean" if (Decimal::ONE..=Decimal::from(5)).contains(&value) => prior.mean = value,
+        "weight" if value >= Decimal::ZERO => prior.weight = value,
+        _ => return Err(entry.to_string()),
+      }
+    }
+
+    Ok(prior)
+  }
+}
+
+/// Visible reviews of an expert, as stored on their profile
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RatingAggregate {
+  pub count: i32,
+  pub sum: i32,
+}
+
+impl RatingAggregate {
+  /// Plain average, rounded to 2 places; zero without reviews
+  pub fn average(&self) -> Decimal {
+    if self.count == 0 {
+      return Decimal::ZERO;
+    }
+    (Decimal::from(self.sum) / Decimal::from(self.count)).round_dp(2)
+  }
+
+  /// Bayesian average: the prior counts as `weight` reviews of `mean` stars
+  pub fn score(&self, prior: &RatingPrior) -> Decimal {
+    let weight = Decimal::from(self.count) + prior.weight;
+    if weight.is_zero() {
+      return prior.mean;
+    }
+    ((Decimal::from(self.sum) + prior.mean * prior.weight) / weight).round_dp(2)
+  }
+}
+
+/// Count of visible reviews per star, one star first
+pub type RatingHistogram = [u64; 5];
+
+/// Aggregates an expert's reviews into `rating`, `rating_count`, `rating_sum` and
+/// `rating_score`.
+///
+/// `rating` is what the expert's page shows; `rating_score` smooths it with a prior so a couple
+/// of five-star reviews don't outrank a long record, and is what experts are ranked by.
+pub struct RatingService;
+
+impl RatingService {
+  /// Rebuild the expert's aggregate from their visible reviews.
+  ///
+  /// Call inside the transaction that changed the reviews: the expert's row is locked before
+  /// counting, so concurrent refreshes run one after the other and the last one sees every
+  /// committed review.
+  pub async fn refresh_expert<C: ConnectionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    expert_id: Uuid,
+  ) -> Result<RatingAggregate, DbErr> {
+    ExpertProfiles::find_by_id(expert_id)
+      .select_only()
+      .column(expert_profiles::Column::Id)
+      .lock_exclusive()
+      .into_tuple::<Uuid>()
+      .one(db)
+      .await?;
+
+    let (count, sum): (i64, Option<i64>) = Reviews::find()
+      .select_only()
+      .column_as(reviews::Column::Id.count(), "count")
+      .column_as(
+        SimpleExpr::from(Func::sum(Expr::col(reviews::Column::Rating)))
+          .cast_as(Alias::new("bigint")),
+        "sum",
+      )
+      .filter(reviews::Column::ExpertId.eq(expert_id))
+      .filter(reviews::Column::HiddenAt.is_null())
+      .into_tuple()
+      .one(db)
+      .await?
+      .unwrap_or_default();
+
+    let aggregate = RatingAggregate {
+      count: count as i32,
+      sum: sum.unwrap_or_default() as i32,
+    };
+    Self::save(db, prior, expert_id, aggregate).await?;
+
+    Ok(aggregate)
+  }
+
+  /// Rescore every expert, e.g. after the prior has changed. Returns how many were updated.
+  pub async fn recompute_all<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+  ) -> Result<usize, DbErr> {
+    let txn = db.begin().await?;
+
+    let expert_ids: Vec<Uuid> = ExpertProfiles::find()
+      .select_only()
+      .column(expert_profiles::Column::Id)
+      .into_tuple()
+      .all(&txn)
+      .await?;
+    for expert_id in &expert_ids {
+      Self::refresh_expert(&txn, prior, *expert_id).await?;
+    }
+
+    txn.commit().await?;
+    Ok(expert_ids.len())
+  }
+
+  pub async fn histogram<C: ConnectionTrait>(
+    db: &C,
+    expert_id: Uuid,
+  ) -> Result<RatingHistogram, DbErr> {
+    let counts: Vec<(i16, i64)> = Reviews::find()
+      .select_only()
+      .column(reviews::Column::Rating)
+      .column_as(reviews::Column::Id.count(), "count")
+      .filter(reviews::Column::ExpertId.eq(expert_id))
+      .filter(reviews::Column::HiddenAt.is_null())
+      .group_by(reviews::Column::Rating)
+      .into_tuple()
+      .all(db)
+      .await?;
+
+    Ok(Self::bucket(counts))
+  }
+
+  fn bucket(counts: impl IntoIterator<Item = (i16, i64)>) -> RatingHistogram {
+    let mut histogram = RatingHistogram::default();
+    for (stars, count) in counts {
+      if let Some(bucket) = usize::try_from(stars - 1)
+        .ok()
+        .and_then(|index| histogram.get_mut(index))
+      {
+        *bucket += count as u64;
+      }
+    }
+    histogram
+  }
+
+  async fn save<C: ConnectionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    expert_id: Uuid,
+    aggregate: RatingAggregate,
+  ) -> Result<(), DbErr> {
+    ExpertProfiles::update_many()
+      .col_expr(
+        expert_profiles::Column::Rating,
+        Expr::value(aggregate.average()),
+      )
+      .col_expr(
+        expert_profiles::Column::RatingCount,
+        Expr::value(aggregate.count),
+      )
+      .col_expr(
+        expert_profiles::Column::RatingSum,
+        Expr::value(aggregate.sum),
+      )
+      .col_expr(
+        expert_profiles::Column::RatingScore,
+        Expr::value(aggregate.score(prior)),
+      )
+      .col_expr(
+        expert_profiles::Column::UpdatedAt,
+        Expr::value(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+      )
+      .filter(expert_profiles::Column::Id.eq(expert_id))
+      .exec(db)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_score_shrinks_small_samples_toward_the_prior() {
+    let prior = RatingPrior::default();
+    let newcomer = RatingAggregate { count: 2, sum: 10 };
+    let veteran = RatingAggregate {
+      count: 200,
+      sum: 950,
+    };
+
+    assert_eq!(newcomer.average(), Decimal::from(5));
+    assert_eq!(veteran.average(), Decimal::new(475, 2));
+    assert_eq!(newcomer.score(&prior), Decimal::new(393, 2));
+    assert_eq!(veteran.score(&prior), Decimal::new(472, 2));
+    assert!(veteran.score(&prior) > newcomer.score(&prior));
+
+    assert_eq!(RatingAggregate::default().average(), Decimal::ZERO);
+    assert_eq!(RatingAggregate::default().score(&prior), prior.mean);
+    let unweighted = RatingPrior {
+      weight: Decimal::ZERO,
+      ..prior
+    };
+    assert_eq!(RatingAggregate::default().score(&unweighted), prior.mean);
+    assert_eq!(newcomer.score(&unweighted), Decimal::from(5));
+  }
+
+  #[test]
+  fn test_prior_from_spec() {
+    assert_eq!(RatingPrior::from_spec("").unwrap(), RatingPrior::default());
+    assert_eq!(
+      RatingPrior::from_spec("mean=4, weight=10").unwrap(),
+      RatingPrior {
+        mean: Decimal::from(4),
+        weight: Decimal::from(10),
+      }
+    );
+    assert_eq!(RatingPrior::from_spec("mean=6"), Err("mean=6".to_string()));
+    assert_eq!(
+      RatingPrior::from_spec("weight=-1"),
+      Err("weight=-1".to_string())
+    );
+    assert_eq!(RatingPrior::from_spec("bias=1"), Err("bias=1".to_string()));
+  }
+
+  #[test]
+  fn test_histogram_buckets() {
+    assert_eq!(
+      RatingService::bucket([(5, 3), (1, 1), (4, 2), (0, 7)]),
+      [1, 0, 0, 2, 3]
+    );
+  }
+}
@@ -0,0 +1,708 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::entities::{
+  categories, expert_categories, expert_profiles, prelude::*, sea_orm_active_enums::SessionStatus,
+  sessions, shopper_profiles, users,
+};
+use crate::services::categories::CategoryService;
+
+pub const DEFAULT_RECOMMENDATIONS: u64 = 10;
+pub const MAX_RECOMMENDATIONS: u64 = 50;
+
+/// Completed sessions after which the history factor stops growing
+const HISTORY_CAP: u64 = 3;
+
+/// Most experts scored for one request, best rated first
+const MAX_CANDIDATES: u64 = 200;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RecommendationError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Shopper profile not found")]
+  ShopperProfileNotFound,
+}
+
+/// Signals an expert is scored on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendationFactor {
+  Category,
+  Price,
+  Rating,
+  Online,
+  History,
+}
+
+impl RecommendationFactor {
+  const ALL: [RecommendationFactor; 5] = [
+    RecommendationFactor::Category,
+    RecommendationFactor::Price,
+    RecommendationFactor::Rating,
+    RecommendationFactor::Online,
+    RecommendationFactor::History,
+  ];
+
+  fn name(self) -> &'static str {
+    match self {
+      RecommendationFactor::Category => "category",
+      RecommendationFactor::Price => "price",
+      RecommendationFactor::Rating => "rating",
+      RecommendationFactor::Online => "online",
+      RecommendationFactor::History => "history",
+    }
+  }
+}
+
+/// Relative importance of each factor; only their ratios matter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecommendationWeights {
+  pub category: f64,
+  pub price: f64,
+  pub rating: f64,
+  pub online: f64,
+  pub history: f64,
+}
+
+impl Default for RecommendationWeights {
+  fn default() -> Self {
+    Self {
+      category: 0.35,
+      price: 0.2,
+      rating: 0.2,
+      online: 0.1,
+      history: 0.15,
+    }
+  }
+}
+
+impl RecommendationWeights {
+  /// Parse `factor=weight` pairs separated by commas, e.g. `category=0.5,online=0`.
+  ///
+  /// Factors left out keep their default weight. Returns the offending entry on error.
+  pub fn from_spec(spec: &str) -> Result<Self, String> {
+    let mut weights = Self::default();
+
+    for entry in spec
+      .split(',')
+      .map(str::trim)
+      .filter(|entry| !entry.is_empty())
+    {
+      let (factor, weight) = entry.split_once('=').ok_or_else(|| entry.to_string())?;
+      let weight: f64 = weight
+        .trim()
+        .parse()
+        .ok()
+        .filter(|weight: &f64| weight.is_finite() && *weight >= 0.0)
+        .ok_or_else(|| entry.to_string())?;
+      let factor = RecommendationFactor::ALL
+        .into_iter()
+        .find(|candidate| candidate.name() == factor.trim())
+        .ok_or_else(|| entry.to_string())?;
+
+      *weights.weight_mut(factor) = weight;
+    }
+
+    Ok(weights)
+  }
+
+  pub fn weight(&self, factor: RecommendationFactor) -> f64 {
+    match factor {
+      RecommendationFactor::Category => self.category,
+      RecommendationFactor::Price => self.price,
+      RecommendationFactor::Rating => self.rating,
+      RecommendationFactor::Online => self.online,
+      RecommendationFactor::History => self.history,
+    }
+  }
+
+  fn weight_mut(&mut self, factor: RecommendationFactor) -> &mut f64 {
+    match factor {
+      RecommendationFactor::Category => &mut self.category,
+      RecommendationFactor::Price => &mut self.price,
+      RecommendationFactor::Rating => &mut self.rating,
+      RecommendationFactor::Online => &mut self.online,
+      RecommendationFactor::History => &mut self.history,
+    }
+  }
+
+  fn total(&self) -> f64 {
+    RecommendationFactor::ALL
+      .into_iter()
+      .map(|factor| self.weight(factor))
+      .sum()
+  }
+}
+
+/// A shopper category resolved against the taxonomy
+#[derive(Debug, Clone)]
+pub struct PreferredCategory {
+  pub name: String,
+  /// The category and everything under it
+  pub subtree: HashSet<Uuid>,
+  /// Broader categories the shopper's pick is filed under
+  pub ancestors: HashSet<Uuid>,
+}
+
+/// What the shopper told us they are looking for
+#[derive(Debug, Clone, Default)]
+pub struct ShopperTaste {
+  pub categories: Vec<PreferredCategory>,
+  /// Preferred categories that aren't in the taxonomy, matched against specializations instead
+  pub keywords: Vec<String>,
+  pub price_min: Decimal,
+  pub price_max: Decimal,
+}
+
+/// An expert being considered, with what we know about them relative to the shopper
+#[derive(Debug, Clone)]
+pub struct Candidate {
+  pub expert: expert_profiles::Model,
+  pub user: users::Model,
+  pub category_ids: HashSet<Uuid>,
+  /// Completed sessions between the shopper and this expert
+  pub past_sessions: u64,
+}
+
+/// How one factor contributed to an expert's score
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreReason {
+  pub factor: RecommendationFactor,
+  pub weight: f64,
+  /// How well the expert does on this factor, from 0 to 1
+  pub score: f64,
+  /// Share of the overall score this factor accounts for
+  pub contribution: f64,
+  pub detail: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Recommendation {
+  pub expert: expert_profiles::Model,
+  pub user: users::Model,
+  /// Weighted score from 0 to 1
+  pub score: f64,
+  pub reasons: Vec<ScoreReason>,
+}
+
+/// Ranks verified experts for a shopper using the preferences on their shopper profile.
+///
+/// Each factor scores an expert between 0 and 1, and the overall score is their weighted mean,
+/// so every recommendation can be explained factor by factor.
+pub struct RecommendationService;
+
+impl RecommendationService {
+  pub async fn recommend<C: ConnectionTrait>(
+    db: &C,
+    shopper_id: Uuid,
+    weights: &RecommendationWeights,
+    limit: Option<u64>,
+  ) -> Result<Vec<Recommendation>, RecommendationError> {
+    let limit = limit
+      .unwrap_or(DEFAULT_RECOMMENDATIONS)
+      .clamp(1, MAX_RECOMMENDATIONS);
+
+    let profile = ShopperProfiles::find()
+      .filter(shopper_profiles::Column::UserId.eq(shopper_id))
+      .one(db)
+      .await?
+      .ok_or(RecommendationError::ShopperProfileNotFound)?;
+
+    let preferred: Vec<String> = serde_json::from_value(profile.categories).unwrap_or_default();
+    let taste = Self::resolve_taste(
+      &CategoryService::all(db).await?,
+      &preferred,
+      profile.price_range_min,
+      profile.price_range_max,
+    );
+
+    let past_sessions: HashMap<Uuid, i64> = Sessions::find()
+      .select_only()
+      .column(sessions::Column::ExpertId)
+      .column_as(Expr::col(sessions::Column::Id).count(), "sessions")
+      .filter(sessions::Column::ShopperId.eq(shopper_id))
+      .filter(sessions::Column::Status.eq(SessionStatus::Completed))
+      .group_by(sessions::Column::ExpertId)
+      .into_tuple::<(Uuid, i64)>()
+      .all(db)
+      .await?
+      .into_iter()
+      .collect();
+
+    let experts = Self::candidates(shopper_id, &taste, past_sessions.keys().copied().collect())
+      .find_also_related(Users)
+      .all(db)
+      .await?;
+
+    let expert_ids: Vec<Uuid> = experts.iter().map(|(expert, _)| expert.id).collect();
+    let mut category_ids: HashMap<Uuid, HashSet<Uuid>> = HashMap::new();
+    for link in ExpertCategories::find()
+      .filter(expert_categories::Column::ExpertId.is_in(expert_ids))
+      .all(db)
+      .await?
+    {
+      category_ids
+        .entry(link.expert_id)
+        .or_default()
+        .insert(link.category_id);
+    }
+
+    let mut recommendations: Vec<Recommendation> = experts
+      .into_iter()
+      .filter_map(|(expert, user)| {
+        let user = user?;
+        let candidate = Candidate {
+          category_ids: category_ids.remove(&expert.id).unwrap_or_default(),
+          past_sessions: past_sessions
+            .get(&expert.user_id)
+            .map_or(0, |count| u64::try_from(*count).unwrap_or(0)),
+          expert,
+          user,
+        };
+        Some(Self::score(weights, &taste, candidate))
+      })
+      .collect();
+
+    recommendations.sort_by(|a, b| {
+      b.score
+        .partial_cmp(&a.score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| b.expert.rating.cmp(&a.expert.rating))
+        .then_with(|| a.expert.id.cmp(&b.expert.id))
+    });
+    recommendations.truncate(limit as usize);
+
+    Ok(recommendations)
+  }
+
+  /// Experts worth scoring for the shopper: those filed under or above one of their categories,
+  /// matching one of their keywords, charging less than twice their maximum (beyond which the
+  /// price factor is zero) or with past sessions with them. The best rated `MAX_CANDIDATES` are
+  /// kept.
+  fn candidates(
+    shopper_id: Uuid,
+    taste: &ShopperTaste,
+    past_experts: Vec<Uuid>,
+  ) -> Select<expert_profiles::Entity> {
+    let category_ids: Vec<Uuid> = taste
+      .categories
+      .iter()
+      .flat_map(|category| category.subtree.iter().chain(&category.ancestors))
+      .copied()
+      .collect();
+
+    let mut relevant = Condition::any()
+      .add(expert_profiles::Column::SessionRate.lte(taste.price_max * Decimal::from(2)));
+    if !category_ids.is_empty() {
+      relevant = relevant.add(
+        Expr::col((expert_profiles::Entity, expert_profiles::Column::Id)).in_subquery(
+          Query::select()
+            .column(expert_categories::Column::ExpertId)
+            .from(expert_categories::Entity)
+            .and_where(expert_categories::Column::CategoryId.is_in(category_ids))
+            .to_owned(),
+        ),
+      );
+    }
+    for keyword in &taste.keywords {
+      relevant = relevant.add(Expr::cust_with_values(
+        "strpos(lower(expert_profiles.specialization), lower($1)) > 0",
+        [keyword.clone()],
+      ));
+    }
+    if !past_experts.is_empty() {
+      relevant = relevant.add(expert_profiles::Column::UserId.is_in(past_experts));
+    }
+
+    ExpertProfiles::find()
+      .filter(expert_profiles::Column::IsVerified.eq(true))
+      .filter(expert_profiles::Column::UserId.ne(shopper_id))
+      .filter(users::Column::BannedAt.is_null())
+      .filter(relevant)
+      .order_by_desc(expert_profiles::Column::RatingScore)
+      .limit(MAX_CANDIDATES)
+  }
+
+  /// Match the shopper's category strings to the taxonomy by slug or name
+  pub fn resolve_taste(
+    all: &[categories::Model],
+    preferred: &[String],
+    price_min: Decimal,
+    price_max: Decimal,
+  ) -> ShopperTaste {
+    let mut taste = ShopperTaste {
+      price_min,
+      price_max,
+      ..Default::default()
+    };
+
+    for name in preferred
+      .iter()
+      .map(|name| name.trim())
+      .filter(|name| !name.is_empty())
+    {
+      let found = all.iter().find(|category| {
+        category.slug.eq_ignore_ascii_case(name) || category.name.eq_ignore_ascii_case(name)
+      });
+
+      match found {
+        Some(category) => {
+          let mut ancestors = HashSet::new();
+          let mut parent = category.parent_id;
+          while let Some(id) = parent.filter(|id| ancestors.insert(*id)) {
+            parent = all.iter().find(|c| c.id == id).and_then(|c| c.parent_id);
+          }
+
+          taste.categories.push(PreferredCategory {
+            name: category.name.clone(),
+            subtree: CategoryService::subtree_ids(all, category.id)
+              .into_iter()
+              .collect(),
+            ancestors,
+          });
+        }
+        None => taste.keywords.push(name.to_string()),
+      }
+    }
+
+    taste
+  }
+
+  pub fn score(
+    weights: &RecommendationWeights,
+    taste: &ShopperTaste,
+    candidate: Candidate,
+  ) -> Recommendation {
+    let total_weight = weights.total();
+
+    let reasons: Vec<ScoreReason> = RecommendationFactor::ALL
+      .into_iter()
+      .map(|factor| {
+        let (score, detail) = match factor {
+          RecommendationFactor::Category => Self::category_fit(taste, &candidate),
+          RecommendationFactor::Price => Self::price_fit(taste, candidate.expert.session_rate),
+          RecommendationFactor::Rating => {
+            let rating = candidate.expert.rating.to_f64().unwrap_or(0.0);
+            (
+              (rating / 5.0).clamp(0.0, 1.0),
+              format!("Rated {:.1} out of 5", rating),
+            )
+          }
+          RecommendationFactor::Online if candidate.expert.is_online => {
+            (1.0, "Online now".to_string())
+          }
+          RecommendationFactor::Online => (0.0, "Currently offline".to_string()),
+          RecommendationFactor::History => match candidate.past_sessions {
+            0 => (0.0, "No past sessions with this expert".to_string()),
+            count => (
+              count.min(HISTORY_CAP) as f64 / HISTORY_CAP as f64,
+              format!(
+                "You've completed {} session{} with this expert",
+                count,
+                if count == 1 { "" } else { "s" }
+              ),
+            ),
+          },
+        };
+
+        let weight = weights.weight(factor);
+        let contribution = if total_weight > 0.0 {
+          weight * score / total_weight
+        } else {
+          0.0
+        };
+
+        ScoreReason {
+          factor,
+          weight,
+          score,
+          contribution,
+          detail,
+        }
+      })
+      .collect();
+
+    Recommendation {
+      score: reasons.iter().map(|reason| reason.contribution).sum(),
+      expert: candidate.expert,
+      user: candidate.user,
+      reasons,
+    }
+  }
+
+  /// Share of the shopper's categories the expert covers; a broader expert category counts half
+  fn category_fit(taste: &ShopperTaste, candidate: &Candidate) -> (f64, String) {
+    let wanted = taste.categories.len() + taste.keywords.len();
+    if wanted == 0 {
+      return (0.0, "No preferred categories set".to_string());
+    }
+
+    let specialization = candidate.expert.specialization.to_lowercase();
+    let mut matched = Vec::new();
+    let mut total = 0.0;
+
+    for category in &taste.categories {
+      if !category.subtree.is_disjoint(&candidate.category_ids) {
+        total += 1.0;
+        matched.push(category.name.clone());
+      } else if !category.ancestors.is_disjoint(&candidate.category_ids) {
+        total += 0.5;
+        matched.push(format!("{} (broadly)", category.name));
+      }
+    }
+    for keyword in &taste.keywords {
+      if specialization.contains(&keyword.to_lowercase()) {
+        total += 1.0;
+        matched.push(keyword.clone());
+      }
+    }
+
+    if matched.is_empty() {
+      return (0.0, "Outside your preferred categories".to_string());
+    }
+
+    (
+      total / wanted as f64,
+      format!("Matches your interest in {}", matched.join(", ")),
+    )
+  }
+
+  /// 1 inside the shopper's range, falling off with distance from the nearest bound
+  fn price_fit(taste: &ShopperTaste, rate: Decimal) -> (f64, String) {
+    let (min, max) = (taste.price_min, taste.price_max);
+
+    if rate >= min && rate <= max {
+      return (
+        1.0,
+        format!("Rate {} is within your {}-{} range", rate, min, max),
+      );
+    }
+
+    let (bound, distance, detail) = if rate > max {
+      (
+        max,
+        rate - max,
+        format!("Rate {} is {} above your maximum", rate, rate - max),
+      )
+    } else {
+      (
+        min,
+        min - rate,
+        format!("Rate {} is {} below your minimum", rate, min - rate),
+      )
+    };
+
+    let score = if bound > Decimal::ZERO {
+      (1.0 - (distance / bound).to_f64().unwrap_or(1.0)).clamp(0.0, 1.0)
+    } else {
+      0.0
+    };
+
+    (score, detail)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::fixtures::{self, category};
+  use sea_orm::sea_query::PostgresQueryBuilder;
+
+  fn candidate(rate: i64, rating: &str, is_online: bool, category_ids: &[Uuid]) -> Candidate {
+    Candidate {
+      expert: expert_profiles::Model {
+        specialization: "Technology & Gadgets Specialist".to_string(),
+        session_rate: Decimal::from(rate),
+        rating: rating.parse().unwrap(),
+        is_online,
+        allowed_durations: serde_json::json!([30]),
+        ..fixtures::expert_profile()
+      },
+      user: users::Model {
+        name: "Expert".to_string(),
+        ..fixtures::user()
+      },
+      category_ids: category_ids.iter().copied().collect(),
+      past_sessions: 0,
+    }
+  }
+
+  #[test]
+  fn test_candidates_are_prefiltered_in_sql() {
+    let (category_id, past_expert) = (Uuid::new_v4(), Uuid::new_v4());
+    let taste = ShopperTaste {
+      categories: vec![PreferredCategory {
+        name: "Smartphones".to_string(),
+        subtree: HashSet::from([category_id]),
+        ancestors: HashSet::new(),
+      }],
+      keywords: vec!["Gadgets".to_string()],
+      price_min: Decimal::from(20),
+      price_max: Decimal::from(100),
+    };
+
+    let sql = RecommendationService::candidates(Uuid::new_v4(), &taste, vec![past_expert])
+      .into_query()
+      .to_string(PostgresQueryBuilder);
+    assert!(
+      sql.contains(&format!(
+        r#""expert_profiles"."id" IN (SELECT "expert_id" FROM "expert_categories" WHERE "expert_categories"."category_id" IN ('{category_id}'))"#
+      )),
+      "{sql}"
+    );
+    assert!(
+      sql.contains(r#""expert_profiles"."session_rate" <= 200"#),
+      "{sql}"
+    );
+    assert!(
+      sql.contains("strpos(lower(expert_profiles.specialization), lower('Gadgets')) > 0"),
+      "{sql}"
+    );
+    assert!(
+      sql.contains(&format!(
+        r#""expert_profiles"."user_id" IN ('{past_expert}')"#
+      )),
+      "{sql}"
+    );
+    assert!(sql.ends_with("LIMIT 200"), "{sql}");
+  }
+
+  #[test]
+  fn test_weights_from_spec() {
+    let weights = RecommendationWeights::from_spec("category=0.5, online=0").unwrap();
+    assert_eq!(weights.category, 0.5);
+    assert_eq!(weights.online, 0.0);
+    assert_eq!(weights.price, RecommendationWeights::default().price);
+
+    assert_eq!(
+      RecommendationWeights::from_spec("").unwrap(),
+      RecommendationWeights::default()
+    );
+    assert_eq!(
+      RecommendationWeights::from_spec("category=0.5,vibes=1"),
+      Err("vibes=1".to_string())
+    );
+    assert_eq!(
+      RecommendationWeights::from_spec("rating=-1"),
+      Err("rating=-1".to_string())
+    );
+  }
+
+  #[test]
+  fn test_category_overlap() {
+    let tech = category("technology-gadgets", "Technology & Gadgets", None);
+    let phones = category("smartphones", "Smartphones", Some(&tech));
+    let fashion = category("fashion-style", "Fashion & Style", None);
+    let all = vec![tech.clone(), phones.clone(), fashion.clone()];
+
+    let taste = RecommendationService::resolve_taste(
+      &all,
+      &[
+        "Technology & Gadgets".to_string(),
+        "fashion-style".to_string(),
+      ],
+      Decimal::from(10),
+      Decimal::from(500),
+    );
+    assert_eq!(taste.categories.len(), 2);
+    assert!(taste.keywords.is_empty());
+
+    // Half of the shopper's categories are covered
+    let (score, detail) =
+      RecommendationService::category_fit(&taste, &candidate(50, "4.5", true, &[phones.id]));
+    assert_eq!(score, 0.5);
+    assert!(detail.contains("Technology & Gadgets"), "{detail}");
+
+    // A shopper after smartphones gets half credit for a general tech expert
+    let taste = RecommendationService::resolve_taste(
+      &all,
+      &["smartphones".to_string()],
+      Decimal::from(10),
+      Decimal::from(500),
+    );
+    let (score, _) =
+      RecommendationService::category_fit(&taste, &candidate(50, "4.5", true, &[tech.id]));
+    assert_eq!(score, 0.5);
+
+    // Strings outside the taxonomy fall back to the specialization
+    let taste = RecommendationService::resolve_taste(
+      &all,
+      &["Gadgets".to_string()],
+      Decimal::from(10),
+      Decimal::from(500),
+    );
+    assert_eq!(taste.keywords, vec!["Gadgets".to_string()]);
+    let (score, _) = RecommendationService::category_fit(&taste, &candidate(50, "4.5", true, &[]));
+    assert_eq!(score, 1.0);
+  }
+
+  #[test]
+  fn test_price_fit() {
+    let taste = ShopperTaste {
+      price_min: Decimal::from(20),
+      price_max: Decimal::from(100),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      RecommendationService::price_fit(&taste, Decimal::from(60)).0,
+      1.0
+    );
+    assert_eq!(
+      RecommendationService::price_fit(&taste, Decimal::from(150)).0,
+      0.5
+    );
+    assert_eq!(
+      RecommendationService::price_fit(&taste, Decimal::from(10)).0,
+      0.5
+    );
+    assert_eq!(
+      RecommendationService::price_fit(&taste, Decimal::from(300)).0,
+      0.0
+    );
+  }
+
+  #[test]
+  fn test_score_is_weighted_mean_of_factors() {
+    let taste = ShopperTaste {
+      price_min: Decimal::from(20),
+      price_max: Decimal::from(100),
+      ..Default::default()
+    };
+    let weights = RecommendationWeights {
+      category: 0.0,
+      price: 1.0,
+      rating: 1.0,
+      online: 2.0,
+      history: 0.0,
+    };
+
+    let mut regular = candidate(60, "4.0", false, &[]);
+    regular.past_sessions = 5;
+    let recommendation = RecommendationService::score(&weights, &taste, regular);
+
+    // (1.0 * 1 + 0.8 * 1 + 0 * 2) / 4
+    assert!(
+      (recommendation.score - 0.45).abs() < 1e-9,
+      "{}",
+      recommendation.score
+    );
+    assert_eq!(recommendation.reasons.len(), 5);
+
+    let history = recommendation
+      .reasons
+      .iter()
+      .find(|reason| reason.factor == RecommendationFactor::History)
+      .unwrap();
+    assert_eq!(history.score, 1.0);
+    assert_eq!(history.contribution, 0.0);
+  }
+}
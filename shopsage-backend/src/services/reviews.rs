@@ -0,0 +1,348 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entities::{
+  expert_profiles,
+  prelude::*,
+  reviews,
+  sea_orm_active_enums::{ContentType, SessionStatus},
+  users,
+};
+use crate::services::content_filter::{ContentFilter, FilterVerdict};
+use crate::services::moderation::ModerationService;
+use crate::services::ratings::{RatingPrior, RatingService};
+use crate::services::stats::StatsService;
+
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 50;
+pub const MAX_TEXT_LENGTH: usize = 2000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReviewError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Review not found")]
+  NotFound,
+  #[error("Session not found")]
+  SessionNotFound,
+  #[error("Only the shopper who took the session can review it")]
+  NotSessionShopper,
+  #[error("Only completed sessions can be reviewed")]
+  SessionNotCompleted,
+  #[error("This session has already been reviewed")]
+  AlreadyReviewed,
+  #[error("Rating must be between 1 and 5 stars")]
+  InvalidRating,
+  #[error("Text must be at most {MAX_TEXT_LENGTH} characters")]
+  TextTooLong,
+  #[error("Reply must not be empty")]
+  EmptyReply,
+  #[error("Only the reviewed expert can reply")]
+  NotReviewedExpert,
+  #[error("Review rejected: {0}")]
+  Rejected(String),
+  #[error("Invalid cursor")]
+  InvalidCursor,
+}
+
+/// A review with the name of the shopper who wrote it
+#[derive(Debug, Clone)]
+pub struct ReviewWithAuthor {
+  pub review: reviews::Model,
+  pub shopper_name: String,
+}
+
+pub struct ReviewPage {
+  pub reviews: Vec<ReviewWithAuthor>,
+  /// Reviews of the expert across all pages
+  pub total: u64,
+  pub next_cursor: Option<String>,
+}
+
+/// Keyset position of the last review on the previous page, newest first
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+  created_at: DateTime<FixedOffset>,
+  id: Uuid,
+}
+
+/// Shopper reviews of completed sessions.
+///
+/// Each session can be reviewed once, by its shopper, and the expert's rating aggregate is
+/// recomputed from their visible reviews whenever one is added, removed, hidden or restored.
+pub struct ReviewService;
+
+impl ReviewService {
+  pub fn validate(rating: i16, comment: &str) -> Result<(), ReviewError> {
+    if !(1..=5).contains(&rating) {
+      return Err(ReviewError::InvalidRating);
+    }
+    if comment.chars().count() > MAX_TEXT_LENGTH {
+      return Err(ReviewError::TextTooLong);
+    }
+
+    Ok(())
+  }
+
+  /// Comments the filter holds are stored hidden and queued for a moderator
+  pub async fn create<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    filter: &dyn ContentFilter,
+    prior: &RatingPrior,
+    shopper_id: Uuid,
+    session_id: Uuid,
+    rating: i16,
+    comment: &str,
+  ) -> Result<reviews::Model, ReviewError> {
+    let comment = comment.trim();
+    Self::validate(rating, comment)?;
+
+    let held = match filter.check(comment) {
+      FilterVerdict::Allow => None,
+      FilterVerdict::Hold(reason) => Some(reason),
+      FilterVerdict::Reject(reason) => return Err(ReviewError::Rejected(reason)),
+    };
+
+    let session = Sessions::find_by_id(session_id)
+      .one(db)
+      .await?
+      .ok_or(ReviewError::SessionNotFound)?;
+
+    if session.shopper_id != shopper_id {
+      return Err(ReviewError::NotSessionShopper);
+    }
+    if session.status != SessionStatus::Completed {
+      return Err(ReviewError::SessionNotCompleted);
+    }
+
+    // Sessions point at the expert's user; reviews belong to their profile
+    let expert = ExpertProfiles::find()
+      .filter(expert_profiles::Column::UserId.eq(session.expert_id))
+      .one(db)
+      .await?
+      .ok_or(ReviewError::SessionNotFound)?;
+
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let txn = db.begin().await?;
+
+    // The unique session_id index settles concurrent submissions for the same session
+    let review = reviews::ActiveModel {
+      id: Set(Uuid::new_v4()),
+      session_id: Set(session.id),
+      expert_id: Set(expert.id),
+      shopper_id: Set(shopper_id),
+      rating: Set(rating),
+      comment: Set(comment.to_string()),
+      expert_reply: Set(None),
+      replied_at: Set(None),
+      hidden_at: Set(held.as_ref().map(|_| now)),
+      created_at: Set(now),
+      updated_at: Set(now),
+    }
+    .insert(&txn)
+    .await
+    .map_err(|err| match err.sql_err() {
+      Some(SqlErr::UniqueConstraintViolation(_)) => ReviewError::AlreadyReviewed,
+      _ => err.into(),
+    })?;
+
+    if let Some(reason) = held {
+      ModerationService::flag(&txn, ContentType::Review, review.id, &reason).await?;
+    }
+
+    // Shopper before expert, the order every stats refresh locks the profile rows in
+    StatsService::refresh_shopper(&txn, shopper_id).await?;
+    RatingService::refresh_expert(&txn, prior, expert.id).await?;
+    txn.commit().await?;
+
+    Ok(review)
+  }
+
+  pub async fn get<C: ConnectionTrait>(
+    db: &C,
+    review_id: Uuid,
+  ) -> Result<reviews::Model, ReviewError> {
+    Reviews::find_by_id(review_id)
+      .one(db)
+      .await?
+      .ok_or(ReviewError::NotFound)
+  }
+
+  /// An expert's visible reviews, newest first
+  pub async fn list_for_expert<C: ConnectionTrait>(
+    db: &C,
+    expert_id: Uuid,
+    limit: Option<u64>,
+    cursor: Option<&str>,
+  ) -> Result<ReviewPage, ReviewError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let base = Reviews::find()
+      .filter(reviews::Column::ExpertId.eq(expert_id))
+      .filter(reviews::Column::HiddenAt.is_null());
+
+    let total = base.clone().count(db).await?;
+
+    let mut page = base
+      .find_also_related(Users)
+      .order_by_desc(reviews::Column::CreatedAt)
+      .order_by_desc(reviews::Column::Id)
+      .limit(limit + 1);
+
+    if let Some(cursor) = cursor {
+      let cursor = Self::decode_cursor(cursor)?;
+      page = page.filter(
+        Condition::any()
+          .add(reviews::Column::CreatedAt.lt(cursor.created_at))
+          .add(
+            Condition::all()
+              .add(reviews::Column::CreatedAt.eq(cursor.created_at))
+              .add(reviews::Column::Id.lt(cursor.id)),
+          ),
+      );
+    }
+
+    let mut rows = page.all(db).await?;
+    let has_more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+
+    let next_cursor = match rows.last() {
+      Some((review, _)) if has_more => Some(Self::encode_cursor(review)),
+      _ => None,
+    };
+
+    Ok(ReviewPage {
+      reviews: rows
+        .into_iter()
+        .map(|(review, shopper)| ReviewWithAuthor {
+          review,
+          shopper_name: shopper
+            .map(|shopper: users::Model| shopper.name)
+            .unwrap_or_default(),
+        })
+        .collect(),
+      total,
+      next_cursor,
+    })
+  }
+
+  /// Set or replace the expert's public reply; `expert_user_id` must own the reviewed profile
+  pub async fn reply<C: ConnectionTrait>(
+    db: &C,
+    expert_user_id: Uuid,
+    review_id: Uuid,
+    reply: &str,
+  ) -> Result<reviews::Model, ReviewError> {
+    let reply = reply.trim();
+    if reply.is_empty() {
+      return Err(ReviewError::EmptyReply);
+    }
+    if reply.chars().count() > MAX_TEXT_LENGTH {
+      return Err(ReviewError::TextTooLong);
+    }
+
+    let review = Self::get(db, review_id).await?;
+    let owns_profile = ExpertProfiles::find_by_id(review.expert_id)
+      .filter(expert_profiles::Column::UserId.eq(expert_user_id))
+      .count(db)
+      .await?
+      > 0;
+    if !owns_profile {
+      return Err(ReviewError::NotReviewedExpert);
+    }
+
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let mut review: reviews::ActiveModel = review.into();
+    review.expert_reply = Set(Some(reply.to_string()));
+    review.replied_at = Set(Some(now));
+    review.updated_at = Set(now);
+
+    Ok(review.update(db).await?)
+  }
+
+  /// Delete a review and recompute the expert's rating and the shopper's stats without it
+  pub async fn remove<C: TransactionTrait>(
+    db: &C,
+    prior: &RatingPrior,
+    review: &reviews::Model,
+  ) -> Result<(), ReviewError> {
+    let txn = db.begin().await?;
+
+    let result = Reviews::delete_by_id(review.id).exec(&txn).await?;
+    if result.rows_affected == 0 {
+      return Err(ReviewError::NotFound);
+    }
+
+    StatsService::refresh_shopper(&txn, review.shopper_id).await?;
+    RatingService::refresh_expert(&txn, prior, review.expert_id).await?;
+    txn.commit().await?;
+
+    Ok(())
+  }
+
+  fn encode_cursor(review: &reviews::Model) -> String {
+    let cursor = Cursor {
+      created_at: review.created_at,
+      id: review.id,
+    };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+  }
+
+  fn decode_cursor(cursor: &str) -> Result<Cursor, ReviewError> {
+    let bytes = URL_SAFE_NO_PAD
+      .decode(cursor)
+      .map_err(|_| ReviewError::InvalidCursor)?;
+    serde_json::from_slice(&bytes).map_err(|_| ReviewError::InvalidCursor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate() {
+    assert!(ReviewService::validate(1, "").is_ok());
+    assert!(ReviewService::validate(5, "Great advice").is_ok());
+    assert!(matches!(
+      ReviewService::validate(0, "Great advice"),
+      Err(ReviewError::InvalidRating)
+    ));
+    assert!(matches!(
+      ReviewService::validate(6, "Great advice"),
+      Err(ReviewError::InvalidRating)
+    ));
+    assert!(matches!(
+      ReviewService::validate(4, &"a".repeat(MAX_TEXT_LENGTH + 1)),
+      Err(ReviewError::TextTooLong)
+    ));
+  }
+
+  #[test]
+  fn test_cursor_round_trip() {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let review = reviews::Model {
+      id: Uuid::new_v4(),
+      session_id: Uuid::new_v4(),
+      expert_id: Uuid::new_v4(),
+      shopper_id: Uuid::new_v4(),
+      rating: 4,
+      comment: String::new(),
+      expert_reply: None,
+      replied_at: None,
+      hidden_at: None,
+      created_at: now,
+      updated_at: now,
+    };
+
+    let cursor = ReviewService::decode_cursor(&ReviewService::encode_cursor(&review)).unwrap();
+    assert_eq!(cursor.id, review.id);
+    assert_eq!(cursor.created_at, review.created_at);
+    assert!(matches!(
+      ReviewService::decode_cursor("not-a-cursor"),
+      Err(ReviewError::InvalidCursor)
+    ));
+  }
+}
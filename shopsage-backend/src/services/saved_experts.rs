@@ -0,0 +1,187 @@
+use chrono::{FixedOffset, Utc};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{
+  expert_profiles, prelude::*, shopper_profiles, shopper_saved_experts, users,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SavedExpertError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Shopper profile not found")]
+  ShopperProfileNotFound,
+  #[error("Expert not found or not verified")]
+  ExpertNotFound,
+  #[error("Expert is not in your saved experts")]
+  NotSaved,
+}
+
+/// A saved expert with their current profile
+#[derive(Debug, Clone)]
+pub struct SavedExpert {
+  pub expert: expert_profiles::Model,
+  pub user: users::Model,
+  pub is_preferred: bool,
+  pub saved_at: sea_orm::prelude::DateTimeWithTimeZone,
+}
+
+/// Experts a shopper has saved for later, with favourites flagged as preferred
+pub struct SavedExpertService;
+
+impl SavedExpertService {
+  /// Favourites first, then most recently saved
+  pub async fn list<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+  ) -> Result<Vec<SavedExpert>, SavedExpertError> {
+    let shopper_profile_id = Self::shopper_profile_id(db, user_id).await?;
+
+    let saved = ShopperSavedExperts::find()
+      .filter(shopper_saved_experts::Column::ShopperProfileId.eq(shopper_profile_id))
+      .order_by_desc(shopper_saved_experts::Column::IsPreferred)
+      .order_by_desc(shopper_saved_experts::Column::CreatedAt)
+      .all(db)
+      .await?;
+
+    let expert_ids: Vec<Uuid> = saved.iter().map(|entry| entry.expert_id).collect();
+    let mut experts: std::collections::HashMap<Uuid, (expert_profiles::Model, users::Model)> =
+      ExpertProfiles::find()
+        .filter(expert_profiles::Column::Id.is_in(expert_ids))
+        .find_also_related(Users)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(expert, user)| user.map(|user| (expert.id, (expert, user))))
+        .collect();
+
+    Ok(
+      saved
+        .into_iter()
+        .filter_map(|entry| {
+          experts
+            .remove(&entry.expert_id)
+            .map(|(expert, user)| SavedExpert {
+              expert,
+              user,
+              is_preferred: entry.is_preferred,
+              saved_at: entry.created_at,
+            })
+        })
+        .collect(),
+    )
+  }
+
+  /// Save `expert_id` for the shopper; saving again only changes `preferred` when it is given
+  pub async fn save<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    expert_id: Uuid,
+    preferred: Option<bool>,
+  ) -> Result<SavedExpert, SavedExpertError> {
+    let shopper_profile_id = Self::shopper_profile_id(db, user_id).await?;
+
+    let (expert, user) = ExpertProfiles::find_by_id(expert_id)
+      .find_also_related(Users)
+      .one(db)
+      .await?
+      .and_then(|(expert, user)| user.map(|user| (expert, user)))
+      .filter(|(expert, _)| expert.is_verified)
+      .ok_or(SavedExpertError::ExpertNotFound)?;
+
+    let on_conflict = match preferred {
+      Some(_) => OnConflict::columns([
+        shopper_saved_experts::Column::ShopperProfileId,
+        shopper_saved_experts::Column::ExpertId,
+      ])
+      .update_column(shopper_saved_experts::Column::IsPreferred)
+      .to_owned(),
+      None => OnConflict::columns([
+        shopper_saved_experts::Column::ShopperProfileId,
+        shopper_saved_experts::Column::ExpertId,
+      ])
+      .do_nothing()
+      .to_owned(),
+    };
+
+    ShopperSavedExperts::insert(shopper_saved_experts::ActiveModel {
+      shopper_profile_id: Set(shopper_profile_id),
+      expert_id: Set(expert_id),
+      is_preferred: Set(preferred.unwrap_or(false)),
+      created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    })
+    .on_conflict(on_conflict)
+    .exec_without_returning(db)
+    .await?;
+
+    let entry = ShopperSavedExperts::find_by_id((shopper_profile_id, expert_id))
+      .one(db)
+      .await?
+      .ok_or(SavedExpertError::NotSaved)?;
+
+    Ok(SavedExpert {
+      expert,
+      user,
+      is_preferred: entry.is_preferred,
+      saved_at: entry.created_at,
+    })
+  }
+
+  pub async fn unsave<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    expert_id: Uuid,
+  ) -> Result<(), SavedExpertError> {
+    let shopper_profile_id = Self::shopper_profile_id(db, user_id).await?;
+
+    let result = ShopperSavedExperts::delete_by_id((shopper_profile_id, expert_id))
+      .exec(db)
+      .await?;
+
+    if result.rows_affected == 0 {
+      return Err(SavedExpertError::NotSaved);
+    }
+
+    Ok(())
+  }
+
+  /// Saved and preferred expert profile ids for a shopper profile, favourites first
+  pub async fn ids_for_profile<C: ConnectionTrait>(
+    db: &C,
+    shopper_profile_id: Uuid,
+  ) -> Result<(Vec<Uuid>, Vec<Uuid>), DbErr> {
+    let saved = ShopperSavedExperts::find()
+      .filter(shopper_saved_experts::Column::ShopperProfileId.eq(shopper_profile_id))
+      .order_by_desc(shopper_saved_experts::Column::IsPreferred)
+      .order_by_desc(shopper_saved_experts::Column::CreatedAt)
+      .all(db)
+      .await?;
+
+    let preferred = saved
+      .iter()
+      .filter(|entry| entry.is_preferred)
+      .map(|entry| entry.expert_id)
+      .collect();
+
+    Ok((
+      saved.into_iter().map(|entry| entry.expert_id).collect(),
+      preferred,
+    ))
+  }
+
+  async fn shopper_profile_id<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+  ) -> Result<Uuid, SavedExpertError> {
+    ShopperProfiles::find()
+      .select_only()
+      .column(shopper_profiles::Column::Id)
+      .filter(shopper_profiles::Column::UserId.eq(user_id))
+      .into_tuple()
+      .one(db)
+      .await?
+      .ok_or(SavedExpertError::ShopperProfileNotFound)
+  }
+}
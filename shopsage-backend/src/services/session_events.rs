@@ -0,0 +1,57 @@
+use chrono::{FixedOffset, Utc};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{prelude::*, sea_orm_active_enums::SessionStatus, session_events};
+
+/// Who changed a session and from where; recorded with every status change
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+  pub actor_id: Option<Uuid>,
+  pub reason: Option<String>,
+  pub client_ip: Option<String>,
+  /// On-chain transaction backing the change, if any
+  pub tx_signature: Option<String>,
+}
+
+/// Append-only history of session status changes.
+///
+/// Callers pass the same transaction they used for the session row so the row and its audit
+/// record are committed together.
+pub struct SessionEventService;
+
+impl SessionEventService {
+  pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    session_id: Uuid,
+    old_status: Option<SessionStatus>,
+    new_status: SessionStatus,
+    audit: &AuditContext,
+  ) -> Result<session_events::Model, DbErr> {
+    session_events::ActiveModel {
+      id: Set(Uuid::new_v4()),
+      session_id: Set(session_id),
+      actor_id: Set(audit.actor_id),
+      old_status: Set(old_status),
+      new_status: Set(new_status),
+      reason: Set(audit.reason.clone()),
+      client_ip: Set(audit.client_ip.clone()),
+      tx_signature: Set(audit.tx_signature.clone()),
+      created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    }
+    .insert(db)
+    .await
+  }
+
+  /// Events of a session, oldest first
+  pub async fn list_for_session<C: ConnectionTrait>(
+    db: &C,
+    session_id: Uuid,
+  ) -> Result<Vec<session_events::Model>, DbErr> {
+    SessionEvents::find()
+      .filter(session_events::Column::SessionId.eq(session_id))
+      .order_by_asc(session_events::Column::CreatedAt)
+      .all(db)
+      .await
+  }
+}
@@ -0,0 +1,183 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::*;
+
+use crate::entities::{prelude::*, sea_orm_active_enums::SessionStatus, sessions};
+use crate::services::session_events::{AuditContext, SessionEventService};
+use crate::services::stats::StatsService;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionLifecycleError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Cannot move a session from '{}' to '{}'", .from.to_value(), .to.to_value())]
+  IllegalTransition {
+    from: SessionStatus,
+    to: SessionStatus,
+  },
+  #[error("Session status changed concurrently, reload and retry")]
+  Conflict,
+}
+
+/// Consultation session state machine.
+///
+/// Mirrors `shopsage_session` on chain (pending -> active -> completed, pending -> cancelled)
+/// and adds the off-chain states for booking, no-shows, disputes and refunds.
+pub struct SessionLifecycle;
+
+impl SessionLifecycle {
+  /// States reachable from `from` in a single step
+  pub fn next_states(from: SessionStatus) -> &'static [SessionStatus] {
+    use SessionStatus::*;
+
+    match from {
+      Scheduled => &[Pending, Cancelled],
+      Pending => &[Active, Cancelled, NoShow],
+      Active => &[Completed, Disputed],
+      Completed => &[Disputed, Refunded],
+      NoShow => &[Disputed, Refunded],
+      Disputed => &[Completed, Refunded],
+      Cancelled => &[Refunded],
+      Refunded => &[],
+    }
+  }
+
+  pub fn can_transition(from: SessionStatus, to: SessionStatus) -> bool {
+    Self::next_states(from).contains(&to)
+  }
+
+  /// Validate `from -> to` and build the changes, stamping the actual start and end times.
+  ///
+  /// The booked window is left alone so a late finish never collides with the next booking.
+  pub fn plan(
+    session: &sessions::Model,
+    to: SessionStatus,
+    now: DateTime<FixedOffset>,
+  ) -> Result<sessions::ActiveModel, SessionLifecycleError> {
+    let from = session.status;
+    if !Self::can_transition(from, to) {
+      return Err(SessionLifecycleError::IllegalTransition { from, to });
+    }
+
+    let mut changes = sessions::ActiveModel {
+      id: Unchanged(session.id),
+      status: Set(to),
+      updated_at: Set(now),
+      ..Default::default()
+    };
+
+    if to == SessionStatus::Active {
+      changes.start_time = Set(Some(now));
+    }
+    if from == SessionStatus::Active {
+      changes.end_time = Set(Some(now));
+    }
+
+    Ok(changes)
+  }
+
+  /// Move a session to `to` and record the event in one transaction.
+  ///
+  /// Shopper and expert stats are refreshed in the same transaction when the change affects them.
+  /// Given a caller's transaction this runs as a savepoint inside it, so the change commits or
+  /// rolls back with the caller's other writes. Fails with `Conflict` if another request changed
+  /// the status in the meantime.
+  pub async fn transition<C: TransactionTrait>(
+    db: &C,
+    session: &sessions::Model,
+    to: SessionStatus,
+    audit: &AuditContext,
+  ) -> Result<sessions::Model, SessionLifecycleError> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let changes = Self::plan(session, to, now)?;
+
+    let txn = db.begin().await?;
+
+    let result = Sessions::update_many()
+      .set(changes)
+      .filter(sessions::Column::Id.eq(session.id))
+      .filter(sessions::Column::Status.eq(session.status))
+      .exec(&txn)
+      .await?;
+
+    if result.rows_affected != 1 {
+      return Err(SessionLifecycleError::Conflict);
+    }
+
+    SessionEventService::record(&txn, session.id, Some(session.status), to, audit).await?;
+
+    let updated = Sessions::find_by_id(session.id)
+      .one(&txn)
+      .await?
+      .ok_or(SessionLifecycleError::Conflict)?;
+
+    if StatsService::affects_stats(session.status, to, audit) {
+      StatsService::refresh_for_session(&txn, &updated).await?;
+    }
+
+    txn.commit().await?;
+
+    Ok(updated)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::fixtures;
+
+  fn session(status: SessionStatus) -> sessions::Model {
+    sessions::Model {
+      status,
+      ..fixtures::session()
+    }
+  }
+
+  #[test]
+  fn test_on_chain_flow_is_allowed() {
+    use SessionStatus::*;
+
+    assert!(SessionLifecycle::can_transition(Pending, Active));
+    assert!(SessionLifecycle::can_transition(Active, Completed));
+    assert!(SessionLifecycle::can_transition(Pending, Cancelled));
+  }
+
+  #[test]
+  fn test_illegal_moves_are_rejected() {
+    use SessionStatus::*;
+
+    assert!(!SessionLifecycle::can_transition(Completed, Active));
+    assert!(!SessionLifecycle::can_transition(Active, Cancelled));
+    assert!(!SessionLifecycle::can_transition(Pending, Pending));
+    assert!(SessionLifecycle::next_states(Refunded).is_empty());
+
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    assert!(matches!(
+      SessionLifecycle::plan(&session(Cancelled), Active, now),
+      Err(SessionLifecycleError::IllegalTransition {
+        from: Cancelled,
+        to: Active
+      })
+    ));
+  }
+
+  #[test]
+  fn test_start_and_end_are_stamped() {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+
+    let started =
+      SessionLifecycle::plan(&session(SessionStatus::Pending), SessionStatus::Active, now).unwrap();
+    assert_eq!(started.start_time, Set(Some(now)));
+    assert!(started.end_time.is_not_set());
+    assert!(started.booked_start.is_not_set());
+
+    let ended = SessionLifecycle::plan(
+      &session(SessionStatus::Active),
+      SessionStatus::Completed,
+      now,
+    )
+    .unwrap();
+    assert_eq!(ended.end_time, Set(Some(now)));
+    assert!(ended.start_time.is_not_set());
+    assert!(ended.booked_end.is_not_set());
+  }
+}
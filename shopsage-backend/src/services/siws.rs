@@ -0,0 +1,231 @@
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat, Utc};
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::entities::{auth_nonces, prelude::*};
+
+const SIWS_STATEMENT: &str = "Sign in to ShopSage with your Solana wallet.";
+const NONCE_LENGTH: usize = 32;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SiwsError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Invalid wallet address: {0}")]
+  InvalidWalletAddress(String),
+  #[error("Invalid signature encoding")]
+  InvalidSignatureEncoding,
+  #[error("Signature verification failed")]
+  VerificationFailed,
+  #[error("Nonce is invalid, expired or already used")]
+  InvalidNonce,
+}
+
+/// Sign-In With Solana challenge, rendered as the plain-text message the wallet signs
+#[derive(Debug, Clone)]
+pub struct SiwsMessage {
+  pub domain: String,
+  pub address: String,
+  pub nonce: String,
+  pub issued_at: DateTime<Utc>,
+  pub expiration_time: DateTime<Utc>,
+}
+
+impl SiwsMessage {
+  pub fn to_message_string(&self) -> String {
+    format!(
+      "{domain} wants you to sign in with your Solana account:\n\
+       {address}\n\
+       \n\
+       {statement}\n\
+       \n\
+       URI: https://{domain}\n\
+       Version: 1\n\
+       Nonce: {nonce}\n\
+       Issued At: {issued_at}\n\
+       Expiration Time: {expiration_time}",
+      domain = self.domain,
+      address = self.address,
+      statement = SIWS_STATEMENT,
+      nonce = self.nonce,
+      issued_at = self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+      expiration_time = self
+        .expiration_time
+        .to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+  }
+}
+
+pub struct SiwsService;
+
+impl SiwsService {
+  /// Issue a new single-use challenge for `wallet_address` and persist it until it expires
+  pub async fn issue_challenge(
+    db: &DatabaseConnection,
+    config: &Config,
+    wallet_address: &str,
+  ) -> Result<auth_nonces::Model, SiwsError> {
+    Self::parse_public_key(wallet_address)?;
+
+    let now = Utc::now();
+
+    // Opportunistically drop challenges nobody answered
+    AuthNonces::delete_many()
+      .filter(
+        auth_nonces::Column::ExpiresAt.lt(now.with_timezone(&FixedOffset::east_opt(0).unwrap())),
+      )
+      .exec(db)
+      .await?;
+
+    let message = SiwsMessage {
+      domain: config.siws_domain.clone(),
+      address: wallet_address.to_string(),
+      nonce: Self::generate_nonce(),
+      issued_at: now,
+      expiration_time: now + Duration::seconds(config.siws_nonce_ttl_secs),
+    };
+
+    let challenge = auth_nonces::ActiveModel {
+      id: Set(Uuid::new_v4()),
+      wallet_address: Set(message.address.clone()),
+      nonce: Set(message.nonce.clone()),
+      message: Set(message.to_message_string()),
+      expires_at: Set(
+        message
+          .expiration_time
+          .with_timezone(&FixedOffset::east_opt(0).unwrap()),
+      ),
+      created_at: Set(now.with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    };
+
+    Ok(challenge.insert(db).await?)
+  }
+
+  /// Check the wallet's signature over a previously issued challenge and consume the nonce.
+  ///
+  /// The nonce is only deleted once the signature checks out, so a bad signature from a third
+  /// party cannot burn a legitimate user's challenge.
+  pub async fn verify_challenge(
+    db: &DatabaseConnection,
+    wallet_address: &str,
+    nonce: &str,
+    signature: &str,
+  ) -> Result<(), SiwsError> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+
+    let challenge = AuthNonces::find()
+      .filter(auth_nonces::Column::Nonce.eq(nonce))
+      .filter(auth_nonces::Column::WalletAddress.eq(wallet_address))
+      .filter(auth_nonces::Column::ExpiresAt.gt(now))
+      .one(db)
+      .await?
+      .ok_or(SiwsError::InvalidNonce)?;
+
+    Self::verify_signature(wallet_address, challenge.message.as_bytes(), signature)?;
+
+    // Deleting by id makes consumption atomic: a concurrent replay sees zero affected rows
+    let result = AuthNonces::delete_by_id(challenge.id).exec(db).await?;
+    if result.rows_affected != 1 {
+      return Err(SiwsError::InvalidNonce);
+    }
+
+    Ok(())
+  }
+
+  /// Verify a base58 encoded ed25519 signature made by `wallet_address` over `message`
+  pub fn verify_signature(
+    wallet_address: &str,
+    message: &[u8],
+    signature: &str,
+  ) -> Result<(), SiwsError> {
+    let public_key = Self::parse_public_key(wallet_address)?;
+
+    let signature_bytes = bs58::decode(signature)
+      .into_vec()
+      .map_err(|_| SiwsError::InvalidSignatureEncoding)?;
+    let signature =
+      Signature::from_slice(&signature_bytes).map_err(|_| SiwsError::InvalidSignatureEncoding)?;
+
+    public_key
+      .verify_strict(message, &signature)
+      .map_err(|_| SiwsError::VerificationFailed)
+  }
+
+  fn parse_public_key(wallet_address: &str) -> Result<VerifyingKey, SiwsError> {
+    let invalid = || SiwsError::InvalidWalletAddress(wallet_address.to_string());
+
+    let bytes: [u8; 32] = bs58::decode(wallet_address)
+      .into_vec()
+      .map_err(|_| invalid())?
+      .try_into()
+      .map_err(|_| invalid())?;
+
+    VerifyingKey::from_bytes(&bytes).map_err(|_| invalid())
+  }
+
+  fn generate_nonce() -> String {
+    rand::thread_rng()
+      .sample_iter(&Alphanumeric)
+      .take(NONCE_LENGTH)
+      .map(char::from)
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ed25519_dalek::{Signer, SigningKey};
+
+  fn test_keypair() -> (SigningKey, String) {
+    let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+    let address = bs58::encode(signing_key.verifying_key().to_bytes()).into_string();
+    (signing_key, address)
+  }
+
+  #[test]
+  fn test_message_format() {
+    let issued_at = DateTime::parse_from_rfc3339("2025-08-10T12:00:00Z")
+      .unwrap()
+      .with_timezone(&Utc);
+    let message = SiwsMessage {
+      domain: "shopsage.app".to_string(),
+      address: "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU".to_string(),
+      nonce: "abc123".to_string(),
+      issued_at,
+      expiration_time: issued_at + Duration::minutes(5),
+    };
+
+    let text = message.to_message_string();
+    assert!(text.starts_with(
+      "shopsage.app wants you to sign in with your Solana account:\n7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU\n"
+    ));
+    assert!(text.contains("\nNonce: abc123\n"));
+    assert!(text.contains("\nIssued At: 2025-08-10T12:00:00Z\n"));
+    assert!(text.ends_with("\nExpiration Time: 2025-08-10T12:05:00Z"));
+  }
+
+  #[test]
+  fn test_signature_verification() {
+    let (signing_key, address) = test_keypair();
+    let message = b"shopsage.app wants you to sign in";
+    let signature = bs58::encode(signing_key.sign(message).to_bytes()).into_string();
+
+    assert!(SiwsService::verify_signature(&address, message, &signature).is_ok());
+    assert!(matches!(
+      SiwsService::verify_signature(&address, b"tampered message", &signature),
+      Err(SiwsError::VerificationFailed)
+    ));
+    assert!(matches!(
+      SiwsService::verify_signature(&address, message, "not-base58!"),
+      Err(SiwsError::InvalidSignatureEncoding)
+    ));
+    assert!(matches!(
+      SiwsService::verify_signature("invalid", message, &signature),
+      Err(SiwsError::InvalidWalletAddress(_))
+    ));
+  }
+}
@@ -3,8 +3,22 @@ use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+/// `shopsage_payment` as deployed from `shopsage-programs`
+pub const SHOPSAGE_PAYMENT_PROGRAM_ID: &str = "GN61kESLP3vmVREX6nhTfqEf94vyuLX8YK4trEv6u6cZ";
+/// Share of a consultation fee that goes to the expert; the platform treasury gets the rest
+pub const EXPERT_SHARE_PERCENT: u64 = 80;
+
+/// `SystemInstruction::Transfer`
+const SYSTEM_TRANSFER: u32 = 2;
+
+/// RPC commitment levels a transaction can be looked up at
+pub const COMMITMENT_CONFIRMED: &str = "confirmed";
+pub const COMMITMENT_FINALIZED: &str = "finalized";
+
 #[derive(thiserror::Error, Debug)]
 pub enum SolanaError {
   #[error("HTTP request error: {0}")]
@@ -21,6 +35,8 @@ pub enum SolanaError {
   InvalidWalletAddress(String),
   #[error("JSON parsing error: {0}")]
   JsonError(#[from] serde_json::Error),
+  #[error("Transaction does not match the payment: {0}")]
+  PaymentMismatch(String),
 }
 
 #[derive(Debug, Serialize)]
@@ -52,11 +68,15 @@ struct TransactionResponse {
 #[derive(Debug, Deserialize)]
 struct TransactionMeta {
   err: Option<Value>,
-  #[serde(rename = "preBalances")]
-  pre_balances: Option<Vec<u64>>,
-  #[serde(rename = "postBalances")]
-  post_balances: Option<Vec<u64>>,
-  fee: Option<u64>,
+  /// Accounts a versioned transaction loaded from lookup tables
+  #[serde(rename = "loadedAddresses")]
+  loaded_addresses: Option<LoadedAddresses>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LoadedAddresses {
+  writable: Vec<String>,
+  readonly: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,7 +88,38 @@ struct TransactionData {
 struct TransactionMessage {
   #[serde(rename = "accountKeys")]
   account_keys: Vec<String>,
-  instructions: Vec<Value>,
+  instructions: Vec<CompiledInstruction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompiledInstruction {
+  #[serde(rename = "programIdIndex")]
+  program_id_index: usize,
+  accounts: Vec<usize>,
+  /// Base58-encoded instruction data
+  data: String,
+}
+
+/// Lamports moved from one account to another by a transaction instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+  pub from: String,
+  pub to: String,
+  pub lamports: u64,
+}
+
+/// What a session payment must look like on chain
+#[derive(Debug, Clone)]
+pub struct ExpectedPayment {
+  /// The shopper's wallet
+  pub payer: String,
+  /// The expert's wallet
+  pub expert: String,
+  pub treasury: String,
+  /// The full session price; see `SolanaService::split`
+  pub lamports: u64,
+  /// Solana Pay reference key of the session, which the transaction must include as an account
+  pub reference: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -83,6 +134,7 @@ struct AccountInfo {
 pub struct SolanaService {
   client: Client,
   rpc_url: String,
+  payment_program_id: String,
 }
 
 impl SolanaService {
@@ -90,62 +142,107 @@ impl SolanaService {
     Self {
       client: Client::new(),
       rpc_url: rpc_url.to_string(),
+      payment_program_id: SHOPSAGE_PAYMENT_PROGRAM_ID.to_string(),
     }
   }
 
-  /// Verify a Solana transaction exists and matches expected criteria
-  pub async fn verify_transaction(
+  /// Use another deployment of `shopsage_payment`, e.g. on devnet
+  pub fn with_payment_program(mut self, program_id: &str) -> Self {
+    self.payment_program_id = program_id.to_string();
+    self
+  }
+
+  /// Verify a finalized transaction pays for a session.
+  ///
+  /// The payment is either a pair of System Program transfers or a call to
+  /// `shopsage_payment::process_consultation_payment`, which makes the same transfers. Either way
+  /// the shopper must send the expert and the treasury their exact shares of the price.
+  pub async fn verify_payment(
     &self,
     transaction_hash: &str,
-    expected_amount_sol: Decimal,
-    expected_recipient: Option<&str>,
-  ) -> Result<bool, SolanaError> {
-    // Validate transaction signature format (base58, 64-88 chars)
+    expected: &ExpectedPayment,
+  ) -> Result<(), SolanaError> {
+    self
+      .verify_payment_at(transaction_hash, expected, COMMITMENT_FINALIZED)
+      .await
+  }
+
+  /// Like [`SolanaService::verify_payment`], for a transaction at the given commitment level
+  pub async fn verify_payment_at(
+    &self,
+    transaction_hash: &str,
+    expected: &ExpectedPayment,
+    commitment: &str,
+  ) -> Result<(), SolanaError> {
     if !self.is_valid_signature(transaction_hash) {
       return Err(SolanaError::InvalidSignature(transaction_hash.to_string()));
     }
 
-    // Get transaction details from Solana RPC
-    let transaction = self.get_transaction(transaction_hash).await?;
+    let transaction = self.get_transaction(transaction_hash, commitment).await?;
 
-    // Check if transaction was successful
-    if let Some(meta) = &transaction.meta {
-      if meta.err.is_some() {
+    match &transaction.meta {
+      Some(meta) if meta.err.is_some() => {
         return Err(SolanaError::TransactionFailed(
           "Transaction failed on-chain".to_string(),
-        ));
+        ))
+      }
+      Some(_) => {}
+      None => {
+        return Err(SolanaError::PaymentMismatch(
+          "transaction has no status".to_string(),
+        ))
       }
-    } else {
-      return Err(SolanaError::InvalidAmount);
     }
 
-    // Verify the transfer amount
-    let actual_amount = self.extract_transfer_amount(&transaction)?;
-    let actual_sol = self.lamports_to_sol(actual_amount);
-
-    // Allow small tolerance for fees and rounding (0.001 SOL = 1,000,000 lamports)
-    let tolerance = Decimal::new(1, 3); // 0.001 SOL
-    let difference = (expected_amount_sol - actual_sol).abs();
-
-    if difference > tolerance {
-      tracing::warn!(
-        "Amount mismatch: expected {}, actual {}, difference {}",
-        expected_amount_sol,
-        actual_sol,
-        difference
-      );
-      return Ok(false);
+    if !Self::account_keys(&transaction).contains(&expected.reference.as_str()) {
+      return Err(SolanaError::PaymentMismatch(
+        "transaction doesn't include the session's payment reference".to_string(),
+      ));
     }
 
-    // Optionally verify recipient address
-    if let Some(recipient) = expected_recipient {
-      if !self.verify_recipient(&transaction, recipient)? {
-        tracing::warn!("Recipient verification failed for {}", recipient);
-        return Ok(false);
+    let transfers = self.transfers(&transaction)?;
+    Self::check_payment(&transfers, expected)
+  }
+
+  /// Split a price into the expert's and the treasury's share, rounding like the program does
+  pub fn split(lamports: u64) -> (u64, u64) {
+    let expert = (u128::from(lamports) * u128::from(EXPERT_SHARE_PERCENT) / 100) as u64;
+    (expert, lamports - expert)
+  }
+
+  /// Check the shopper's transfers add up to exactly the expected shares
+  pub fn check_payment(
+    transfers: &[Transfer],
+    expected: &ExpectedPayment,
+  ) -> Result<(), SolanaError> {
+    if !transfers
+      .iter()
+      .any(|transfer| transfer.from == expected.payer)
+    {
+      return Err(SolanaError::PaymentMismatch(
+        "not paid from the shopper's wallet".to_string(),
+      ));
+    }
+
+    let (expert_share, treasury_share) = Self::split(expected.lamports);
+    for (recipient, to, share) in [
+      ("expert", &expected.expert, expert_share),
+      ("treasury", &expected.treasury, treasury_share),
+    ] {
+      let received: u64 = transfers
+        .iter()
+        .filter(|transfer| transfer.from == expected.payer && &transfer.to == to)
+        .map(|transfer| transfer.lamports)
+        .sum();
+      if received != share {
+        return Err(SolanaError::PaymentMismatch(format!(
+          "{} received {} lamports, expected {}",
+          recipient, received, share
+        )));
       }
     }
 
-    Ok(true)
+    Ok(())
   }
 
   /// Get account balance in SOL
@@ -216,7 +313,11 @@ impl SolanaService {
 
   // Private helper methods
 
-  async fn get_transaction(&self, signature: &str) -> Result<TransactionResponse, SolanaError> {
+  async fn get_transaction(
+    &self,
+    signature: &str,
+    commitment: &str,
+  ) -> Result<TransactionResponse, SolanaError> {
     let request = RpcRequest {
       jsonrpc: "2.0".to_string(),
       id: 1,
@@ -225,6 +326,7 @@ impl SolanaService {
           signature,
           {
               "encoding": "json",
+              "commitment": commitment,
               "maxSupportedTransactionVersion": 0
           }
       ]),
@@ -244,52 +346,97 @@ impl SolanaService {
     }
   }
 
-  fn extract_transfer_amount(&self, transaction: &TransactionResponse) -> Result<u64, SolanaError> {
-    let meta = transaction
+  /// Every account the transaction references, in instruction index order
+  fn account_keys(transaction: &TransactionResponse) -> Vec<&str> {
+    let loaded = transaction
       .meta
       .as_ref()
-      .ok_or(SolanaError::InvalidAmount)?;
+      .and_then(|meta| meta.loaded_addresses.as_ref());
+    // Lookup table accounts are indexed after the static keys, writable ones first
+    transaction
+      .transaction
+      .message
+      .account_keys
+      .iter()
+      .chain(loaded.into_iter().flat_map(|loaded| &loaded.writable))
+      .chain(loaded.into_iter().flat_map(|loaded| &loaded.readonly))
+      .map(String::as_str)
+      .collect()
+  }
 
-    // Get balance changes
-    let pre_balances = meta
-      .pre_balances
-      .as_ref()
-      .ok_or(SolanaError::InvalidAmount)?;
-    let post_balances = meta
-      .post_balances
-      .as_ref()
-      .ok_or(SolanaError::InvalidAmount)?;
+  /// Transfers made by the transaction's top-level instructions
+  fn transfers(&self, transaction: &TransactionResponse) -> Result<Vec<Transfer>, SolanaError> {
+    let message = &transaction.transaction.message;
+    let keys = Self::account_keys(transaction);
+    let key = |index: usize| {
+      keys.get(index).copied().ok_or_else(|| {
+        SolanaError::PaymentMismatch(format!("instruction references unknown account {}", index))
+      })
+    };
 
-    if pre_balances.len() != post_balances.len() {
-      return Err(SolanaError::InvalidAmount);
-    }
+    let mut transfers = Vec::new();
+    for instruction in &message.instructions {
+      let program_id = key(instruction.program_id_index)?;
+      if program_id != SYSTEM_PROGRAM_ID && program_id != self.payment_program_id {
+        continue;
+      }
 
-    // Find the largest positive balance change (money received)
-    let mut max_increase = 0u64;
-    for (pre, post) in pre_balances.iter().zip(post_balances.iter()) {
-      if post > pre {
-        let increase = post - pre;
-        if increase > max_increase {
-          max_increase = increase;
+      let data = bs58::decode(&instruction.data)
+        .into_vec()
+        .map_err(|_| SolanaError::PaymentMismatch("undecodable instruction data".to_string()))?;
+      let accounts = instruction
+        .accounts
+        .iter()
+        .map(|index| key(*index))
+        .collect::<Result<Vec<_>, _>>()?;
+
+      if program_id == SYSTEM_PROGRAM_ID {
+        if let (Some(lamports), [from, to, ..]) =
+          (Self::decode_system_transfer(&data), &accounts[..])
+        {
+          transfers.push(Transfer {
+            from: from.to_string(),
+            to: to.to_string(),
+            lamports,
+          });
         }
+      } else if let (Some(lamports), [_, shopper, expert, platform, ..]) =
+        (Self::decode_consultation_payment(&data), &accounts[..])
+      {
+        let (expert_share, platform_share) = Self::split(lamports);
+        transfers.push(Transfer {
+          from: shopper.to_string(),
+          to: expert.to_string(),
+          lamports: expert_share,
+        });
+        transfers.push(Transfer {
+          from: shopper.to_string(),
+          to: platform.to_string(),
+          lamports: platform_share,
+        });
       }
     }
 
-    if max_increase == 0 {
-      return Err(SolanaError::InvalidAmount);
-    }
+    Ok(transfers)
+  }
 
-    Ok(max_increase)
+  /// Lamports of a `SystemInstruction::Transfer`: a u32 tag and a u64 amount, little endian
+  fn decode_system_transfer(data: &[u8]) -> Option<u64> {
+    let (tag, lamports) = data.split_first_chunk::<4>()?;
+    if u32::from_le_bytes(*tag) != SYSTEM_TRANSFER {
+      return None;
+    }
+    Some(u64::from_le_bytes(lamports.try_into().ok()?))
   }
 
-  fn verify_recipient(
-    &self,
-    transaction: &TransactionResponse,
-    expected_recipient: &str,
-  ) -> Result<bool, SolanaError> {
-    // Check if the expected recipient is in the account keys
-    let account_keys = &transaction.transaction.message.account_keys;
-    Ok(account_keys.contains(&expected_recipient.to_string()))
+  /// Amount of a `process_consultation_payment` call: Anchor's 8-byte discriminator and a u64
+  fn decode_consultation_payment(data: &[u8]) -> Option<u64> {
+    let (discriminator, amount) = data.split_first_chunk::<8>()?;
+    let expected = Sha256::digest(b"global:process_consultation_payment");
+    if discriminator[..] != expected[..8] {
+      return None;
+    }
+    Some(u64::from_le_bytes(amount.try_into().ok()?))
   }
 
   async fn send_rpc_request<T>(&self, request: RpcRequest) -> Result<RpcResponse<T>, SolanaError>
@@ -335,4 +482,99 @@ mod tests {
     assert_eq!(service.lamports_to_sol(500_000_000), Decimal::new(5, 1));
     assert_eq!(service.sol_to_lamports(Decimal::from(1)), 1_000_000_000);
   }
+
+  fn expected() -> ExpectedPayment {
+    ExpectedPayment {
+      payer: "Shopper1111111111111111111111111111111111111".to_string(),
+      expert: "Expert11111111111111111111111111111111111111".to_string(),
+      treasury: "Treasury111111111111111111111111111111111111".to_string(),
+      lamports: 1_000_000_001,
+      reference: "Reference11111111111111111111111111111111111".to_string(),
+    }
+  }
+
+  fn transfer(from: &str, to: &str, lamports: u64) -> Transfer {
+    Transfer {
+      from: from.to_string(),
+      to: to.to_string(),
+      lamports,
+    }
+  }
+
+  #[test]
+  fn test_split_matches_the_program() {
+    assert_eq!(
+      SolanaService::split(1_000_000_001),
+      (800_000_000, 200_000_001)
+    );
+  }
+
+  #[test]
+  fn test_check_payment_requires_exact_shares_from_the_shopper() {
+    let expected = expected();
+    let paid = |expert_lamports, treasury_lamports| {
+      vec![
+        transfer(&expected.payer, &expected.expert, expert_lamports),
+        transfer(&expected.payer, &expected.treasury, treasury_lamports),
+      ]
+    };
+
+    assert!(SolanaService::check_payment(&paid(800_000_000, 200_000_001), &expected).is_ok());
+    for transfers in [
+      paid(800_000_001, 200_000_000),
+      paid(1_000_000_001, 0),
+      paid(800_000_000, 200_000_000),
+      vec![
+        transfer(&expected.treasury, &expected.expert, 800_000_000),
+        transfer(&expected.payer, &expected.treasury, 200_000_001),
+      ],
+    ] {
+      assert!(matches!(
+        SolanaService::check_payment(&transfers, &expected),
+        Err(SolanaError::PaymentMismatch(_))
+      ));
+    }
+  }
+
+  #[test]
+  fn test_transfers_are_decoded_from_instructions() {
+    let service = SolanaService::new("https://api.devnet.solana.com");
+    let expected = expected();
+
+    let mut system_transfer = SYSTEM_TRANSFER.to_le_bytes().to_vec();
+    system_transfer.extend(800_000_000u64.to_le_bytes());
+    let mut program_call = Sha256::digest(b"global:process_consultation_payment")[..8].to_vec();
+    program_call.extend(1_000_000_001u64.to_le_bytes());
+    let memo = bs58::encode(b"ShopSage consultation payment").into_string();
+
+    let transaction: TransactionResponse = serde_json::from_value(serde_json::json!({
+      "meta": {
+        "err": null,
+        "loadedAddresses": { "writable": [expected.treasury], "readonly": [expected.reference] }
+      },
+      "transaction": { "message": {
+        "accountKeys": [
+          expected.payer, expected.expert, SYSTEM_PROGRAM_ID,
+          SHOPSAGE_PAYMENT_PROGRAM_ID, "PaymentAccount11111111111111111111111111111",
+          "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"
+        ],
+        "instructions": [
+          { "programIdIndex": 2, "accounts": [0, 1, 7], "data": bs58::encode(&system_transfer).into_string() },
+          { "programIdIndex": 3, "accounts": [4, 0, 1, 6, 2], "data": bs58::encode(&program_call).into_string() },
+          { "programIdIndex": 5, "accounts": [], "data": memo }
+        ]
+      }}
+    }))
+    .unwrap();
+
+    assert!(SolanaService::account_keys(&transaction).contains(&expected.reference.as_str()));
+    assert_eq!(
+      service.transfers(&transaction).unwrap(),
+      vec![
+        transfer(&expected.payer, &expected.expert, 800_000_000),
+        transfer(&expected.payer, &expected.expert, 800_000_000),
+        transfer(&expected.payer, &expected.treasury, 200_000_001),
+      ]
+    );
+  }
 }
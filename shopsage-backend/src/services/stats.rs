@@ -0,0 +1,423 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use rust_decimal::Decimal;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{
+  expert_profiles, expert_stats, payments,
+  prelude::*,
+  sea_orm_active_enums::{PaymentStatus, SessionStatus},
+  sessions, shopper_profiles,
+};
+use crate::services::payments::PaymentService;
+use crate::services::ratings::RatingAggregate;
+use crate::services::session_events::AuditContext;
+use crate::services::solana::EXPERT_SHARE_PERCENT;
+
+/// A shopper's sessions, payments and reviews, summed in SQL
+#[derive(Debug, Clone, Copy, Default, PartialEq, FromQueryResult)]
+pub struct ShopperAggregate {
+  pub completed: i64,
+  /// Lamports paid in confirmed payments
+  pub spent_lamports: i64,
+  pub rating_count: i64,
+  pub rating_sum: i64,
+}
+
+/// An expert's sessions and payments, summed in SQL
+#[derive(Debug, Clone, Copy, Default, PartialEq, FromQueryResult)]
+pub struct ExpertAggregate {
+  pub completed: i64,
+  pub disputed: i64,
+  /// Length of the completed sessions
+  pub completed_seconds: i64,
+  /// Sessions the expert changed the status of
+  pub responded: i64,
+  /// Time from booking to the expert's first status change, over the responded sessions
+  pub response_seconds: i64,
+  /// The expert's share of confirmed payments
+  pub earned_lamports: i64,
+}
+
+/// `$1` is the shopper, then the completed session and confirmed payment statuses
+const SHOPPER_AGGREGATE_SQL: &str = "\
+  SELECT \
+    (SELECT COUNT(*) FROM sessions WHERE shopper_id = $1 AND status = $2) AS completed, \
+    (SELECT COALESCE(SUM(lamports), 0)::bigint FROM payments \
+      WHERE payer_id = $1 AND status = $3) AS spent_lamports, \
+    (SELECT COUNT(*) FROM reviews WHERE shopper_id = $1 AND hidden_at IS NULL) AS rating_count, \
+    (SELECT COALESCE(SUM(rating), 0)::bigint FROM reviews \
+      WHERE shopper_id = $1 AND hidden_at IS NULL) AS rating_sum";
+
+/// `$1` is the expert, then the completed and disputed session statuses, the confirmed payment
+/// status and the expert's share in percent. A finished session counts its actual length,
+/// falling back to the booked duration.
+const EXPERT_AGGREGATE_SQL: &str = "\
+  SELECT \
+    COUNT(*) FILTER (WHERE s.status = $2) AS completed, \
+    COUNT(*) FILTER (WHERE s.status = $3) AS disputed, \
+    COALESCE(SUM(CASE \
+      WHEN s.end_time > s.start_time THEN EXTRACT(EPOCH FROM s.end_time - s.start_time) \
+      ELSE COALESCE(s.duration_minutes, 0) * 60 \
+    END) FILTER (WHERE s.status = $2), 0)::bigint AS completed_seconds, \
+    COUNT(r.responded_at) AS responded, \
+    COALESCE(SUM(GREATEST(EXTRACT(EPOCH FROM r.responded_at - s.created_at), 0)), 0)::bigint \
+      AS response_seconds, \
+    (SELECT COALESCE(SUM(div(lamports::numeric * $5, 100)), 0)::bigint FROM payments \
+      WHERE payee_id = $1 AND status = $4) AS earned_lamports \
+  FROM sessions s \
+  LEFT JOIN LATERAL ( \
+    SELECT MIN(e.created_at) AS responded_at FROM session_events e \
+    WHERE e.session_id = s.id AND e.actor_id = s.expert_id AND e.old_status IS NOT NULL \
+  ) r ON true \
+  WHERE s.expert_id = $1";
+
+/// Session-derived counters for a shopper profile
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShopperTotals {
+  pub total_sessions: i32,
+  /// SOL paid in confirmed payments
+  pub total_spent: Decimal,
+  /// Mean of the stars the shopper gave in their visible reviews
+  pub average_rating: Decimal,
+}
+
+/// Session-derived counters for an expert's profile and stats rows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpertTotals {
+  pub total_consultations: i32,
+  /// The expert's share, in SOL, of confirmed payments
+  pub total_earnings: Decimal,
+  pub total_hours: Decimal,
+  /// Average minutes between a booking and the expert's first status change on it
+  pub response_time: Decimal,
+  /// Percentage of delivered sessions that completed without a dispute
+  pub satisfaction: Decimal,
+}
+
+/// Keeps `shopper_profiles` and `expert_stats` in line with the sessions they summarize.
+///
+/// Counters are always rebuilt from the sessions, payments and reviews of the affected shopper
+/// and expert rather than incremented, so a refresh is idempotent and `recompute_all` produces
+/// the same numbers. Money only counts once a payment is confirmed: the shopper spent the full
+/// amount and the expert earned their share of it, rounded down per payment like
+/// `SolanaService::split`. Refreshes lock the shopper's profile row before the expert's, so
+/// callers touching both must refresh the shopper first.
+pub struct StatsService;
+
+impl StatsService {
+  /// Whether a status change, or the transaction confirming it, can move any counter
+  pub fn affects_stats(from: SessionStatus, to: SessionStatus, audit: &AuditContext) -> bool {
+    use SessionStatus::*;
+
+    audit.tx_signature.is_some()
+      || [from, to]
+        .iter()
+        .any(|status| matches!(status, Completed | Disputed))
+  }
+
+  pub fn shopper_totals(aggregate: &ShopperAggregate) -> ShopperTotals {
+    let ratings = RatingAggregate {
+      count: aggregate.rating_count as i32,
+      sum: aggregate.rating_sum as i32,
+    };
+
+    ShopperTotals {
+      total_sessions: aggregate.completed as i32,
+      total_spent: PaymentService::to_sol(aggregate.spent_lamports),
+      average_rating: ratings.average(),
+    }
+  }
+
+  pub fn expert_totals(aggregate: &ExpertAggregate) -> ExpertTotals {
+    let mut totals = ExpertTotals {
+      total_consultations: aggregate.completed as i32,
+      total_earnings: PaymentService::to_sol(aggregate.earned_lamports),
+      total_hours: (Decimal::from(aggregate.completed_seconds) / Decimal::from(3600)).round_dp(2),
+      ..Default::default()
+    };
+
+    if aggregate.responded > 0 {
+      totals.response_time = (Decimal::from(aggregate.response_seconds)
+        / Decimal::from(60 * aggregate.responded))
+      .round_dp(2);
+    }
+
+    let delivered = aggregate.completed + aggregate.disputed;
+    if delivered > 0 {
+      totals.satisfaction =
+        (Decimal::from(aggregate.completed * 100) / Decimal::from(delivered)).round_dp(2);
+    }
+
+    totals
+  }
+
+  /// Rebuild the counters of the session's shopper and expert; pass the session's transaction
+  pub async fn refresh_for_session<C: ConnectionTrait>(
+    db: &C,
+    session: &sessions::Model,
+  ) -> Result<(), DbErr> {
+    Self::refresh_shopper(db, session.shopper_id).await?;
+    Self::refresh_expert(db, session.expert_id).await
+  }
+
+  /// Rebuild the counters of the payment's payer and payee; pass the payment's transaction
+  pub async fn refresh_for_payment<C: ConnectionTrait>(
+    db: &C,
+    payment: &payments::Model,
+  ) -> Result<(), DbErr> {
+    Self::refresh_shopper(db, payment.payer_id).await?;
+    Self::refresh_expert(db, payment.payee_id).await
+  }
+
+  /// Rebuild a shopper's counters, e.g. after a review of theirs was added or hidden.
+  ///
+  /// Call inside the transaction that made the change: the shopper's profile row is locked
+  /// before summing, so concurrent refreshes run one after the other and the last one sees every
+  /// committed change.
+  pub async fn refresh_shopper<C: ConnectionTrait>(db: &C, shopper_id: Uuid) -> Result<(), DbErr> {
+    ShopperProfiles::find()
+      .select_only()
+      .column(shopper_profiles::Column::Id)
+      .filter(shopper_profiles::Column::UserId.eq(shopper_id))
+      .lock_exclusive()
+      .into_tuple::<Uuid>()
+      .one(db)
+      .await?;
+
+    let aggregate = ShopperAggregate::find_by_statement(Statement::from_sql_and_values(
+      db.get_database_backend(),
+      SHOPPER_AGGREGATE_SQL,
+      [
+        shopper_id.into(),
+        SessionStatus::Completed.into(),
+        PaymentStatus::Confirmed.into(),
+      ],
+    ))
+    .one(db)
+    .await?
+    .unwrap_or_default();
+
+    Self::save_shopper(db, shopper_id, &Self::shopper_totals(&aggregate)).await
+  }
+
+  /// Rebuild an expert's counters, locking their profile row first like `refresh_shopper`
+  async fn refresh_expert<C: ConnectionTrait>(db: &C, expert_id: Uuid) -> Result<(), DbErr> {
+    ExpertProfiles::find()
+      .select_only()
+      .column(expert_profiles::Column::Id)
+      .filter(expert_profiles::Column::UserId.eq(expert_id))
+      .lock_exclusive()
+      .into_tuple::<Uuid>()
+      .one(db)
+      .await?;
+
+    let aggregate = ExpertAggregate::find_by_statement(Statement::from_sql_and_values(
+      db.get_database_backend(),
+      EXPERT_AGGREGATE_SQL,
+      [
+        expert_id.into(),
+        SessionStatus::Completed.into(),
+        SessionStatus::Disputed.into(),
+        PaymentStatus::Confirmed.into(),
+        (EXPERT_SHARE_PERCENT as i64).into(),
+      ],
+    ))
+    .one(db)
+    .await?
+    .unwrap_or_default();
+
+    Self::save_expert(db, expert_id, &Self::expert_totals(&aggregate)).await
+  }
+
+  /// Rebuild every shopper's and expert's counters; returns how many of each were written
+  pub async fn recompute_all<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+  ) -> Result<(usize, usize), DbErr> {
+    let txn = db.begin().await?;
+
+    // Profiles without sessions are reset to zero as well
+    let shopper_ids: Vec<Uuid> = ShopperProfiles::find()
+      .select_only()
+      .column(shopper_profiles::Column::UserId)
+      .into_tuple()
+      .all(&txn)
+      .await?;
+    for user_id in &shopper_ids {
+      Self::refresh_shopper(&txn, *user_id).await?;
+    }
+
+    let expert_ids: Vec<Uuid> = ExpertProfiles::find()
+      .select_only()
+      .column(expert_profiles::Column::UserId)
+      .into_tuple()
+      .all(&txn)
+      .await?;
+    for user_id in &expert_ids {
+      Self::refresh_expert(&txn, *user_id).await?;
+    }
+
+    txn.commit().await?;
+
+    Ok((shopper_ids.len(), expert_ids.len()))
+  }
+
+  async fn save_shopper<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    totals: &ShopperTotals,
+  ) -> Result<(), DbErr> {
+    ShopperProfiles::update_many()
+      .col_expr(
+        shopper_profiles::Column::TotalSessions,
+        Expr::value(totals.total_sessions),
+      )
+      .col_expr(
+        shopper_profiles::Column::TotalSpent,
+        Expr::value(totals.total_spent),
+      )
+      .col_expr(
+        shopper_profiles::Column::AverageRating,
+        Expr::value(totals.average_rating),
+      )
+      .col_expr(
+        shopper_profiles::Column::UpdatedAt,
+        Expr::value(Self::now()),
+      )
+      .filter(shopper_profiles::Column::UserId.eq(user_id))
+      .exec(db)
+      .await?;
+
+    Ok(())
+  }
+
+  async fn save_expert<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    totals: &ExpertTotals,
+  ) -> Result<(), DbErr> {
+    let now = Self::now();
+
+    ExpertProfiles::update_many()
+      .col_expr(
+        expert_profiles::Column::TotalConsultations,
+        Expr::value(totals.total_consultations),
+      )
+      .col_expr(expert_profiles::Column::UpdatedAt, Expr::value(now))
+      .filter(expert_profiles::Column::UserId.eq(user_id))
+      .exec(db)
+      .await?;
+
+    let updated = ExpertStats::update_many()
+      .col_expr(
+        expert_stats::Column::TotalEarnings,
+        Expr::value(totals.total_earnings),
+      )
+      .col_expr(
+        expert_stats::Column::TotalHours,
+        Expr::value(totals.total_hours),
+      )
+      .col_expr(
+        expert_stats::Column::ResponseTime,
+        Expr::value(totals.response_time),
+      )
+      .col_expr(
+        expert_stats::Column::Satisfaction,
+        Expr::value(totals.satisfaction),
+      )
+      .col_expr(expert_stats::Column::UpdatedAt, Expr::value(now))
+      .filter(expert_stats::Column::UserId.eq(user_id))
+      .exec(db)
+      .await?;
+
+    // Only seeded experts start out with a stats row
+    if updated.rows_affected == 0 {
+      expert_stats::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        total_earnings: Set(totals.total_earnings),
+        total_hours: Set(totals.total_hours),
+        response_time: Set(totals.response_time),
+        satisfaction: Set(totals.satisfaction),
+        created_at: Set(now),
+        updated_at: Set(now),
+      }
+      .insert(db)
+      .await?;
+    }
+
+    Ok(())
+  }
+
+  fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_only_completed_sessions_count() {
+    let aggregate = ExpertAggregate {
+      completed: 2,
+      disputed: 1,
+      completed_seconds: 2 * 3600,
+      ..Default::default()
+    };
+
+    let totals = StatsService::expert_totals(&aggregate);
+    assert_eq!(totals.total_consultations, 2);
+    assert_eq!(totals.total_hours, Decimal::from(2));
+    assert_eq!(totals.satisfaction, Decimal::new(6667, 2));
+    assert_eq!(totals.response_time, Decimal::ZERO);
+  }
+
+  #[test]
+  fn test_response_time_averages_responded_sessions() {
+    let aggregate = ExpertAggregate {
+      responded: 2,
+      response_seconds: 15 * 60 + 45 * 60,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      StatsService::expert_totals(&aggregate).response_time,
+      Decimal::from(30)
+    );
+  }
+
+  #[test]
+  fn test_money_comes_from_confirmed_payments() {
+    let aggregate = ShopperAggregate {
+      spent_lamports: 1_500_000_000,
+      rating_count: 3,
+      rating_sum: 13,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      StatsService::shopper_totals(&aggregate),
+      ShopperTotals {
+        total_sessions: 0,
+        total_spent: Decimal::new(15, 1),
+        average_rating: Decimal::new(433, 2),
+      }
+    );
+
+    let totals = StatsService::expert_totals(&ExpertAggregate {
+      earned_lamports: 1_200_000_000,
+      ..Default::default()
+    });
+    assert_eq!(totals.total_earnings, Decimal::new(12, 1));
+  }
+
+  #[test]
+  fn test_aggregates_filter_in_sql() {
+    assert!(EXPERT_AGGREGATE_SQL.contains("e.actor_id = s.expert_id"));
+    assert!(EXPERT_AGGREGATE_SQL.contains("div(lamports::numeric * $5, 100)"));
+    assert!(SHOPPER_AGGREGATE_SQL.contains("hidden_at IS NULL"));
+  }
+}
@@ -0,0 +1,562 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDateTime, Utc};
+use sea_orm::*;
+use uuid::Uuid;
+
+use crate::entities::{
+  expert_availability, expert_time_off,
+  prelude::*,
+  sea_orm_active_enums::{NotificationKind, SessionStatus, TimeOffRecurrence},
+  sessions,
+};
+use crate::services::availability::{AvailabilityService, Window};
+use crate::services::notifications::NotificationService;
+use crate::services::session_events::AuditContext;
+use crate::services::session_lifecycle::{SessionLifecycle, SessionLifecycleError};
+
+/// Bookings in these states are cancelled when new time off covers them
+pub const CANCELLABLE_STATUSES: [SessionStatus; 2] =
+  [SessionStatus::Scheduled, SessionStatus::Pending];
+
+#[derive(thiserror::Error, Debug)]
+pub enum TimeOffError {
+  #[error("Database error: {0}")]
+  DbError(#[from] DbErr),
+  #[error("Time off entry not found")]
+  NotFound,
+  #[error("Unknown timezone '{0}', expected an IANA name such as Europe/London")]
+  InvalidTimezone(String),
+  #[error("Time off must end after it starts")]
+  EndsBeforeStart,
+  #[error("Recurring time off must be shorter than the period it repeats over")]
+  LongerThanPeriod,
+  #[error("repeatUntil only applies to recurring time off")]
+  RepeatUntilWithoutRecurrence,
+  #[error("repeatUntil must not be before the time off starts")]
+  RepeatUntilBeforeStart,
+}
+
+/// A time off entry as submitted by the expert
+#[derive(Debug, Clone)]
+pub struct TimeOffInput {
+  pub starts_at: DateTime<Utc>,
+  pub ends_at: DateTime<Utc>,
+  pub timezone: String,
+  pub recurrence: TimeOffRecurrence,
+  pub repeat_until: Option<DateTime<Utc>>,
+  pub reason: Option<String>,
+}
+
+/// A saved entry and the bookings it cancelled
+#[derive(Debug, Clone)]
+pub struct TimeOffChange {
+  pub time_off: expert_time_off::Model,
+  pub cancelled_sessions: Vec<Uuid>,
+}
+
+/// Expert time off: holidays, single afternoons and recurring exceptions to the weekly schedule.
+///
+/// `starts_at`/`ends_at` are the first occurrence. Recurring entries repeat at the same
+/// wall-clock time in the entry's timezone, so a weekly Friday 14:00-18:00 block stays at 14:00
+/// local across DST changes. A yearly entry starting on 29 February only occurs in leap years.
+pub struct TimeOffService;
+
+impl TimeOffService {
+  pub fn validate(input: &TimeOffInput) -> Result<(), TimeOffError> {
+    AvailabilityService::parse_timezone(&input.timezone)
+      .ok_or_else(|| TimeOffError::InvalidTimezone(input.timezone.clone()))?;
+
+    if input.ends_at <= input.starts_at {
+      return Err(TimeOffError::EndsBeforeStart);
+    }
+
+    let period = match input.recurrence {
+      TimeOffRecurrence::Once => None,
+      TimeOffRecurrence::Weekly => Some(Duration::weeks(1)),
+      TimeOffRecurrence::Yearly => Some(Duration::days(365)),
+    };
+
+    match (period, input.repeat_until) {
+      (None, Some(_)) => return Err(TimeOffError::RepeatUntilWithoutRecurrence),
+      (Some(period), _) if input.ends_at - input.starts_at >= period => {
+        return Err(TimeOffError::LongerThanPeriod)
+      }
+      (Some(_), Some(until)) if until < input.starts_at => {
+        return Err(TimeOffError::RepeatUntilBeforeStart)
+      }
+      _ => {}
+    }
+
+    Ok(())
+  }
+
+  /// Every occurrence of `entries` overlapping `[from, to)`, sorted and merged
+  pub fn windows_between(
+    entries: &[expert_time_off::Model],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+  ) -> Vec<Window> {
+    let mut windows = Vec::new();
+
+    for entry in entries {
+      let start = entry.starts_at.with_timezone(&Utc);
+      let end = entry.ends_at.with_timezone(&Utc);
+
+      if entry.recurrence == TimeOffRecurrence::Once {
+        let window = Window { start, end };
+        if window.overlaps(from, to) {
+          windows.push(window);
+        }
+        continue;
+      }
+
+      let Some(tz) = AvailabilityService::parse_timezone(&entry.timezone) else {
+        tracing::warn!(
+          time_off_id = %entry.id,
+          timezone = %entry.timezone,
+          "Skipping time off with an unparseable timezone"
+        );
+        continue;
+      };
+
+      let local_start = start.with_timezone(&tz).naive_local();
+      let length = end.with_timezone(&tz).naive_local() - local_start;
+      let repeat_until = entry.repeat_until.map(|until| until.with_timezone(&Utc));
+
+      // Occurrence numbers that can reach [from, to), with one period of slack for DST
+      let (first, last) = match entry.recurrence {
+        TimeOffRecurrence::Weekly => (
+          ((from - end).num_days() / 7 - 1).max(0),
+          (to - start).num_days() / 7 + 1,
+        ),
+        _ => (
+          i64::from(from.with_timezone(&tz).year() - local_start.year() - 1).max(0),
+          i64::from(to.with_timezone(&tz).year() - local_start.year() + 1),
+        ),
+      };
+
+      for n in first..=last {
+        let Some(occurrence) = Self::nth_occurrence(entry.recurrence, local_start, n) else {
+          continue;
+        };
+
+        let window = Window {
+          start: AvailabilityService::resolve_local(&tz, occurrence),
+          end: AvailabilityService::resolve_local(&tz, occurrence + length),
+        };
+
+        if repeat_until.is_some_and(|until| window.start > until) {
+          break;
+        }
+        if window.start < window.end && window.overlaps(from, to) {
+          windows.push(window);
+        }
+      }
+    }
+
+    AvailabilityService::merge(windows)
+  }
+
+  fn nth_occurrence(
+    recurrence: TimeOffRecurrence,
+    local_start: NaiveDateTime,
+    n: i64,
+  ) -> Option<NaiveDateTime> {
+    match recurrence {
+      TimeOffRecurrence::Once => (n == 0).then_some(local_start),
+      TimeOffRecurrence::Weekly => Some(local_start + Duration::weeks(n)),
+      TimeOffRecurrence::Yearly => {
+        let year = local_start.year().checked_add(i32::try_from(n).ok()?)?;
+        local_start.with_year(year)
+      }
+    }
+  }
+
+  pub async fn list_for_expert<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+  ) -> Result<Vec<expert_time_off::Model>, DbErr> {
+    ExpertTimeOff::find()
+      .filter(expert_time_off::Column::UserId.eq(user_id))
+      .order_by_asc(expert_time_off::Column::StartsAt)
+      .all(db)
+      .await
+  }
+
+  /// Timezone of the expert's weekly schedule, used when an entry doesn't name one
+  pub async fn default_timezone<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+  ) -> Result<String, DbErr> {
+    let rule = ExpertAvailability::find()
+      .filter(expert_availability::Column::UserId.eq(user_id))
+      .one(db)
+      .await?;
+
+    Ok(
+      rule
+        .map(|rule| rule.timezone)
+        .filter(|timezone| AvailabilityService::parse_timezone(timezone).is_some())
+        .unwrap_or_else(|| "UTC".to_string()),
+    )
+  }
+
+  pub async fn create<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    user_id: Uuid,
+    input: TimeOffInput,
+  ) -> Result<TimeOffChange, TimeOffError> {
+    Self::validate(&input)?;
+
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let txn = db.begin().await?;
+
+    let time_off = expert_time_off::ActiveModel {
+      id: Set(Uuid::new_v4()),
+      user_id: Set(user_id),
+      starts_at: Set(input.starts_at.fixed_offset()),
+      ends_at: Set(input.ends_at.fixed_offset()),
+      timezone: Set(input.timezone),
+      recurrence: Set(input.recurrence),
+      repeat_until: Set(input.repeat_until.map(|until| until.fixed_offset())),
+      reason: Set(input.reason),
+      created_at: Set(now),
+      updated_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    let cancelled = Self::cancel_covered_sessions(&txn, &time_off).await?;
+    txn.commit().await?;
+
+    Ok(TimeOffChange {
+      time_off,
+      cancelled_sessions: Self::notify_cancelled(db, cancelled).await,
+    })
+  }
+
+  /// Replace an entry; bookings cancelled by its old times stay cancelled
+  pub async fn update<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    user_id: Uuid,
+    time_off_id: Uuid,
+    input: TimeOffInput,
+  ) -> Result<TimeOffChange, TimeOffError> {
+    Self::validate(&input)?;
+
+    let txn = db.begin().await?;
+    let existing = Self::find_owned(&txn, user_id, time_off_id).await?;
+
+    let mut active_model: expert_time_off::ActiveModel = existing.into();
+    active_model.starts_at = Set(input.starts_at.fixed_offset());
+    active_model.ends_at = Set(input.ends_at.fixed_offset());
+    active_model.timezone = Set(input.timezone);
+    active_model.recurrence = Set(input.recurrence);
+    active_model.repeat_until = Set(input.repeat_until.map(|until| until.fixed_offset()));
+    active_model.reason = Set(input.reason);
+    active_model.updated_at = Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
+
+    let time_off = active_model.update(&txn).await?;
+    let cancelled = Self::cancel_covered_sessions(&txn, &time_off).await?;
+    txn.commit().await?;
+
+    Ok(TimeOffChange {
+      time_off,
+      cancelled_sessions: Self::notify_cancelled(db, cancelled).await,
+    })
+  }
+
+  pub async fn delete<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    time_off_id: Uuid,
+  ) -> Result<(), TimeOffError> {
+    let existing = Self::find_owned(db, user_id, time_off_id).await?;
+    existing.delete(db).await?;
+
+    Ok(())
+  }
+
+  async fn find_owned<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    time_off_id: Uuid,
+  ) -> Result<expert_time_off::Model, TimeOffError> {
+    ExpertTimeOff::find_by_id(time_off_id)
+      .filter(expert_time_off::Column::UserId.eq(user_id))
+      .one(db)
+      .await?
+      .ok_or(TimeOffError::NotFound)
+  }
+
+  /// Cancel the expert's upcoming unstarted bookings inside `time_off`, in the transaction that
+  /// saves the entry. Returns the cancelled sessions.
+  ///
+  /// Runs after the entry is written, so `BookingService::check_slot` turns away new bookings for
+  /// the same time once it commits.
+  async fn cancel_covered_sessions(
+    txn: &DatabaseTransaction,
+    time_off: &expert_time_off::Model,
+  ) -> Result<Vec<sessions::Model>, TimeOffError> {
+    let not_before = Utc::now().max(time_off.starts_at.with_timezone(&Utc));
+
+    let upcoming = Sessions::find()
+      .filter(sessions::Column::ExpertId.eq(time_off.user_id))
+      .filter(sessions::Column::Status.is_in(CANCELLABLE_STATUSES))
+      .filter(sessions::Column::BookedEnd.gt(not_before))
+      .order_by_asc(sessions::Column::BookedStart)
+      .all(txn)
+      .await?;
+
+    let audit = AuditContext {
+      actor_id: Some(time_off.user_id),
+      reason: Some("expert time off".to_string()),
+      ..Default::default()
+    };
+
+    let mut cancelled = Vec::new();
+    for session in upcoming {
+      let (Some(start), Some(end)) = (session.booked_start, session.booked_end) else {
+        continue;
+      };
+      let (start, end) = (start.with_timezone(&Utc), end.with_timezone(&Utc));
+
+      if Self::windows_between(std::slice::from_ref(time_off), start, end).is_empty() {
+        continue;
+      }
+
+      match SessionLifecycle::transition(txn, &session, SessionStatus::Cancelled, &audit).await {
+        Ok(_) => {}
+        // Started or cancelled by someone else meanwhile; leave it be
+        Err(SessionLifecycleError::Conflict | SessionLifecycleError::IllegalTransition { .. }) => {
+          continue
+        }
+        Err(SessionLifecycleError::DbError(err)) => return Err(err.into()),
+      }
+
+      cancelled.push(session);
+    }
+
+    Ok(cancelled)
+  }
+
+  /// Tell the shoppers of committed cancellations; returns the cancelled session ids.
+  ///
+  /// The cancellations already stand, so a notice that can't be written is logged, not returned.
+  async fn notify_cancelled<C: ConnectionTrait>(
+    db: &C,
+    cancelled: Vec<sessions::Model>,
+  ) -> Vec<Uuid> {
+    for session in &cancelled {
+      let Some(start) = session.booked_start else {
+        continue;
+      };
+
+      if let Err(err) = NotificationService::notify(
+        db,
+        session.shopper_id,
+        NotificationKind::SessionCancelled,
+        format!(
+          "Your session on {} was cancelled because the expert is unavailable at that time",
+          start.with_timezone(&Utc).format("%Y-%m-%d %H:%M UTC")
+        ),
+        Some(session.id),
+      )
+      .await
+      {
+        tracing::error!(
+          error = %err,
+          session_id = %session.id,
+          "Failed to notify shopper of cancellation"
+        );
+      }
+    }
+
+    cancelled.into_iter().map(|session| session.id).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn utc(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+      .unwrap()
+      .with_timezone(&Utc)
+  }
+
+  fn entry(
+    starts_at: &str,
+    ends_at: &str,
+    timezone: &str,
+    recurrence: TimeOffRecurrence,
+    repeat_until: Option<&str>,
+  ) -> expert_time_off::Model {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    expert_time_off::Model {
+      id: Uuid::new_v4(),
+      user_id: Uuid::new_v4(),
+      starts_at: DateTime::parse_from_rfc3339(starts_at).unwrap(),
+      ends_at: DateTime::parse_from_rfc3339(ends_at).unwrap(),
+      timezone: timezone.to_string(),
+      recurrence,
+      repeat_until: repeat_until.map(|until| DateTime::parse_from_rfc3339(until).unwrap()),
+      reason: None,
+      created_at: now,
+      updated_at: now,
+    }
+  }
+
+  fn input(starts_at: &str, ends_at: &str, recurrence: TimeOffRecurrence) -> TimeOffInput {
+    TimeOffInput {
+      starts_at: utc(starts_at),
+      ends_at: utc(ends_at),
+      timezone: "Europe/London".to_string(),
+      recurrence,
+      repeat_until: None,
+      reason: None,
+    }
+  }
+
+  #[test]
+  fn test_one_off_time_off() {
+    let entries = vec![entry(
+      "2025-08-15T13:00:00Z",
+      "2025-08-15T17:00:00Z",
+      "UTC",
+      TimeOffRecurrence::Once,
+      None,
+    )];
+
+    let windows = TimeOffService::windows_between(
+      &entries,
+      utc("2025-08-15T00:00:00Z"),
+      utc("2025-08-16T00:00:00Z"),
+    );
+    assert_eq!(
+      windows,
+      vec![Window {
+        start: utc("2025-08-15T13:00:00Z"),
+        end: utc("2025-08-15T17:00:00Z"),
+      }]
+    );
+
+    assert!(TimeOffService::windows_between(
+      &entries,
+      utc("2025-08-22T00:00:00Z"),
+      utc("2025-08-23T00:00:00Z"),
+    )
+    .is_empty());
+  }
+
+  #[test]
+  fn test_weekly_time_off_keeps_local_time_across_dst() {
+    // Friday afternoons 14:00-18:00 London, first on 2025-10-17 (BST, UTC+1)
+    let entries = vec![entry(
+      "2025-10-17T14:00:00+01:00",
+      "2025-10-17T18:00:00+01:00",
+      "Europe/London",
+      TimeOffRecurrence::Weekly,
+      Some("2025-11-01T00:00:00Z"),
+    )];
+
+    let windows = TimeOffService::windows_between(
+      &entries,
+      utc("2025-10-01T00:00:00Z"),
+      utc("2025-12-01T00:00:00Z"),
+    );
+
+    // Clocks go back on 2025-10-26, after which 14:00 London is 14:00 UTC
+    assert_eq!(
+      windows,
+      vec![
+        Window {
+          start: utc("2025-10-17T13:00:00Z"),
+          end: utc("2025-10-17T17:00:00Z"),
+        },
+        Window {
+          start: utc("2025-10-24T13:00:00Z"),
+          end: utc("2025-10-24T17:00:00Z"),
+        },
+        Window {
+          start: utc("2025-10-31T14:00:00Z"),
+          end: utc("2025-10-31T18:00:00Z"),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn test_yearly_time_off() {
+    // Christmas Day in Tokyo, every year from 2024
+    let entries = vec![entry(
+      "2024-12-25T00:00:00+09:00",
+      "2024-12-26T00:00:00+09:00",
+      "Asia/Tokyo",
+      TimeOffRecurrence::Yearly,
+      None,
+    )];
+
+    let windows = TimeOffService::windows_between(
+      &entries,
+      utc("2027-12-01T00:00:00Z"),
+      utc("2028-01-01T00:00:00Z"),
+    );
+    assert_eq!(
+      windows,
+      vec![Window {
+        start: utc("2027-12-24T15:00:00Z"),
+        end: utc("2027-12-25T15:00:00Z"),
+      }]
+    );
+  }
+
+  #[test]
+  fn test_validation() {
+    assert!(TimeOffService::validate(&input(
+      "2025-08-15T13:00:00Z",
+      "2025-08-15T17:00:00Z",
+      TimeOffRecurrence::Weekly,
+    ))
+    .is_ok());
+
+    assert!(matches!(
+      TimeOffService::validate(&input(
+        "2025-08-15T17:00:00Z",
+        "2025-08-15T13:00:00Z",
+        TimeOffRecurrence::Once,
+      )),
+      Err(TimeOffError::EndsBeforeStart)
+    ));
+    assert!(matches!(
+      TimeOffService::validate(&input(
+        "2025-08-01T00:00:00Z",
+        "2025-08-10T00:00:00Z",
+        TimeOffRecurrence::Weekly,
+      )),
+      Err(TimeOffError::LongerThanPeriod)
+    ));
+
+    let mut once_until = input(
+      "2025-08-15T13:00:00Z",
+      "2025-08-15T17:00:00Z",
+      TimeOffRecurrence::Once,
+    );
+    once_until.repeat_until = Some(utc("2025-09-01T00:00:00Z"));
+    assert!(matches!(
+      TimeOffService::validate(&once_until),
+      Err(TimeOffError::RepeatUntilWithoutRecurrence)
+    ));
+
+    let mut bad_timezone = input(
+      "2025-08-15T13:00:00Z",
+      "2025-08-15T17:00:00Z",
+      TimeOffRecurrence::Once,
+    );
+    bad_timezone.timezone = "Mars/Olympus".to_string();
+    assert!(matches!(
+      TimeOffService::validate(&bad_timezone),
+      Err(TimeOffError::InvalidTimezone(_))
+    ));
+  }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use jsonwebtoken::{
+  decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::Config;
+
+/// Access token claims shared by the auth handlers and the auth middleware
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+  /// User id
+  pub sub: Uuid,
+  /// Auth session the token was issued for, checked against revocation on every request
+  pub sid: Uuid,
+  pub wallet_address: String,
+  pub iss: String,
+  pub aud: String,
+  pub iat: usize,
+  pub exp: usize,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TokenError {
+  #[error("JWT error: {0}")]
+  JwtError(#[from] jsonwebtoken::errors::Error),
+  #[error("Unsupported JWT algorithm: {0}")]
+  UnsupportedAlgorithm(String),
+  #[error("Token is missing a key id")]
+  MissingKeyId,
+  #[error("Unknown key id: {0}")]
+  UnknownKeyId(String),
+}
+
+/// Issues and verifies access tokens.
+///
+/// Tokens are signed with the current key and carry its id in the `kid` header. Verification
+/// accepts the current key and every retired key listed in `JWT_PREVIOUS_KEYS`, so secrets can
+/// be rotated without logging everybody out.
+#[derive(Clone)]
+pub struct TokenService {
+  algorithm: Algorithm,
+  signing_key_id: String,
+  encoding_key: EncodingKey,
+  decoding_keys: HashMap<String, DecodingKey>,
+  ttl_secs: i64,
+  issuer: String,
+  audience: String,
+}
+
+impl TokenService {
+  pub fn from_config(config: &Config) -> Result<Self, TokenError> {
+    let algorithm = Algorithm::from_str(&config.jwt_algorithm)
+      .map_err(|_| TokenError::UnsupportedAlgorithm(config.jwt_algorithm.clone()))?;
+
+    // Secrets are shared keys, so only the HMAC family makes sense here
+    if !matches!(
+      algorithm,
+      Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+      return Err(TokenError::UnsupportedAlgorithm(
+        config.jwt_algorithm.clone(),
+      ));
+    }
+
+    let mut decoding_keys = HashMap::new();
+    for (kid, secret) in &config.jwt_previous_keys {
+      decoding_keys.insert(kid.clone(), DecodingKey::from_secret(secret.as_bytes()));
+    }
+    decoding_keys.insert(
+      config.jwt_key_id.clone(),
+      DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+    );
+
+    Ok(Self {
+      algorithm,
+      signing_key_id: config.jwt_key_id.clone(),
+      encoding_key: EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+      decoding_keys,
+      ttl_secs: config.jwt_ttl_secs,
+      issuer: config.jwt_issuer.clone(),
+      audience: config.jwt_audience.clone(),
+    })
+  }
+
+  pub fn issue(
+    &self,
+    user_id: Uuid,
+    session_id: Uuid,
+    wallet_address: &str,
+  ) -> Result<String, TokenError> {
+    let now = chrono::Utc::now();
+    let expiration = now
+      .checked_add_signed(chrono::Duration::seconds(self.ttl_secs))
+      .expect("valid timestamp");
+
+    let claims = Claims {
+      sub: user_id,
+      sid: session_id,
+      wallet_address: wallet_address.to_string(),
+      iss: self.issuer.clone(),
+      aud: self.audience.clone(),
+      iat: now.timestamp() as usize,
+      exp: expiration.timestamp() as usize,
+    };
+
+    let mut header = Header::new(self.algorithm);
+    header.kid = Some(self.signing_key_id.clone());
+
+    Ok(encode(&header, &claims, &self.encoding_key)?)
+  }
+
+  pub fn verify(&self, token: &str) -> Result<Claims, TokenError> {
+    let header = decode_header(token)?;
+    let kid = header.kid.ok_or(TokenError::MissingKeyId)?;
+    let decoding_key = self
+      .decoding_keys
+      .get(&kid)
+      .ok_or_else(|| TokenError::UnknownKeyId(kid.clone()))?;
+
+    let mut validation = Validation::new(self.algorithm);
+    validation.set_issuer(&[&self.issuer]);
+    validation.set_audience(&[&self.audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    Ok(decode::<Claims>(token, decoding_key, &validation)?.claims)
+  }
+
+  pub fn ttl_secs(&self) -> i64 {
+    self.ttl_secs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn test_config() -> Config {
+    let mut config = Config::for_tests();
+    config.jwt_secret = "current-secret".to_string();
+    config.jwt_key_id = "2025-08".to_string();
+    config.jwt_previous_keys = vec![("2025-07".to_string(), "old-secret".to_string())];
+    config
+  }
+
+  #[test]
+  fn test_issue_and_verify_round_trip() {
+    let service = TokenService::from_config(&test_config()).unwrap();
+    let user_id = Uuid::new_v4();
+
+    let token = service.issue(user_id, Uuid::new_v4(), "wallet").unwrap();
+    assert_eq!(
+      decode_header(&token).unwrap().kid.as_deref(),
+      Some("2025-08")
+    );
+
+    let claims = service.verify(&token).unwrap();
+    assert_eq!(claims.sub, user_id);
+    assert_eq!(claims.wallet_address, "wallet");
+  }
+
+  #[test]
+  fn test_rotated_key_still_verifies() {
+    let mut old_config = test_config();
+    old_config.jwt_secret = "old-secret".to_string();
+    old_config.jwt_key_id = "2025-07".to_string();
+    old_config.jwt_previous_keys = vec![];
+    let token = TokenService::from_config(&old_config)
+      .unwrap()
+      .issue(Uuid::new_v4(), Uuid::new_v4(), "wallet")
+      .unwrap();
+
+    let service = TokenService::from_config(&test_config()).unwrap();
+    assert!(service.verify(&token).is_ok());
+
+    let mut retired_config = test_config();
+    retired_config.jwt_previous_keys = vec![];
+    let service = TokenService::from_config(&retired_config).unwrap();
+    assert!(matches!(
+      service.verify(&token),
+      Err(TokenError::UnknownKeyId(_))
+    ));
+  }
+
+  #[test]
+  fn test_rejects_wrong_audience() {
+    let token = TokenService::from_config(&test_config())
+      .unwrap()
+      .issue(Uuid::new_v4(), Uuid::new_v4(), "wallet")
+      .unwrap();
+
+    let mut other_config = test_config();
+    other_config.jwt_audience = "someone-else".to_string();
+    let service = TokenService::from_config(&other_config).unwrap();
+    assert!(service.verify(&token).is_err());
+  }
+}
@@ -6,10 +6,19 @@ use std::collections::HashMap;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::database::DatabaseResult;
+use crate::database::{DatabaseError, DatabaseResult};
 use crate::entities::{
-  expert_availability, expert_profiles, expert_stats, prelude::*, shopper_profiles, users,
+  expert_availability, expert_profiles, expert_stats,
+  prelude::*,
+  sea_orm_active_enums::{ContentType, PricingModel},
+  shopper_profiles, users,
 };
+use crate::services::availability::AvailabilityService;
+use crate::services::categories::CategoryService;
+use crate::services::moderation::ModerationService;
+use crate::services::pricing::{PricingService, PricingUpdate, SUPPORTED_DURATIONS};
+use crate::services::ratings::{RatingAggregate, RatingPrior};
+use crate::services::saved_experts::SavedExpertService;
 
 // Frontend-compatible types (matching React Redux interface)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +43,8 @@ pub struct UserCompleteProfile {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShopperProfile {
+  pub id: Uuid,
+  pub user_id: Uuid,
   pub preferences: ShopperPreferences,
   #[serde(rename = "consultationHistory")]
   pub consultation_history: ConsultationHistory,
@@ -82,16 +93,32 @@ pub struct ExpertProfile {
   pub is_verified: bool,
   #[serde(rename = "isOnline")]
   pub is_online: bool,
+  #[serde(rename = "pricingModel")]
+  pub pricing_model: PricingModel,
+  #[serde(rename = "allowedDurations")]
+  pub allowed_durations: Vec<i32>,
   pub availability: Availability,
   #[serde(rename = "profileImageUrl")]
   pub profile_image_url: Option<String>,
   pub stats: ExpertStats,
 }
 
+/// Weekly schedule; `schedule` keeps one window per day for older clients, `windows` has them all
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Availability {
   pub schedule: HashMap<String, ScheduleSlot>,
   pub timezone: String,
+  #[serde(default)]
+  pub windows: Vec<AvailabilityWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityWindow {
+  #[serde(rename = "dayOfWeek")]
+  pub day_of_week: String,
+  pub start: String,
+  pub end: String,
+  pub available: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,11 +139,40 @@ pub struct ExpertStats {
   pub satisfaction: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum UserRole {
   Shopper,
   Expert,
+  Admin,
+}
+
+impl UserRole {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      UserRole::Shopper => "shopper",
+      UserRole::Expert => "expert",
+      UserRole::Admin => "admin",
+    }
+  }
+}
+
+/// Roles held by a user, as resolved from their profiles and the `users.is_admin` flag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roles {
+  pub shopper: bool,
+  pub expert: bool,
+  pub admin: bool,
+}
+
+impl Roles {
+  pub fn has(&self, role: UserRole) -> bool {
+    match role {
+      UserRole::Shopper => self.shopper,
+      UserRole::Expert => self.expert,
+      UserRole::Admin => self.admin,
+    }
+  }
 }
 
 // Request types
@@ -164,23 +220,23 @@ impl UserService {
   ) -> DatabaseResult<Option<UserProfile>> {
     let user_model = Users::find_by_id(id).one(db).await?;
 
-    if let Some(user_model) = user_model {
-      let user = Self::build_user_from_model(&user_model, db).await?;
-      Ok(Some(user.user))
-    } else {
-      Ok(None)
-    }
+    Ok(user_model.as_ref().map(Self::profile_from_model))
   }
 
+  /// `prior` scores the placeholder expert profile until it has reviews
   pub async fn create(
     db: &DatabaseConnection,
     request: CreateUserRequest,
+    prior: &RatingPrior,
   ) -> DatabaseResult<UserCompleteProfile> {
     let user_active_model = users::ActiveModel {
       id: Set(Uuid::new_v4()),
       wallet_address: Set(request.wallet_address),
       name: Set(request.name),
       email: Set(request.email.unwrap_or_default()),
+      is_admin: Set(false),
+      banned_at: Set(None),
+      ban_reason: Set(None),
       created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
     };
@@ -194,8 +250,6 @@ impl UserService {
       categories: Set(json!([])),
       price_range_min: Set(rust_decimal::Decimal::from(0)),
       price_range_max: Set(rust_decimal::Decimal::from(5)),
-      preferred_experts: Set(json!([])),
-      saved_experts: Set(json!([])),
       interests: Set(json!([])),
       total_sessions: Set(0),
       total_spent: Set(rust_decimal::Decimal::from(0)),
@@ -210,14 +264,21 @@ impl UserService {
       user_id: Set(user_model.id),
       specialization: Set("Unverified consultant".to_string()),
       bio: Set("Unverified consultant".to_string()),
+      bio_hidden_at: Set(None),
+      bio_held: Set(false),
       session_rate: Set(rust_decimal::Decimal::from(0)),
       rating: Set(rust_decimal::Decimal::from(0)),
+      rating_count: Set(0),
+      rating_sum: Set(0),
+      rating_score: Set(RatingAggregate::default().score(prior)),
       total_consultations: Set(0),
       is_verified: Set(false),
       is_online: Set(false),
       profile_image_url: Set(
         "https://images.unsplash.com/photo-1472099645785-5658abf4ff4e?w=300".to_string(),
       ),
+      pricing_model: Set(PricingModel::Flat),
+      allowed_durations: Set(json!(SUPPORTED_DURATIONS)),
       created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
     };
@@ -238,20 +299,23 @@ impl UserService {
     Self::build_user_from_model(&user_model, db).await
   }
 
-  async fn build_user_from_model(
-    user_model: &users::Model,
-    db: &DatabaseConnection,
-  ) -> DatabaseResult<UserCompleteProfile> {
-    let profile = UserProfile {
+  pub fn profile_from_model(user_model: &users::Model) -> UserProfile {
+    UserProfile {
       id: user_model.id.to_string(),
       wallet_address: user_model.wallet_address.clone(),
       name: user_model.name.clone(),
       email: Some(user_model.email.clone()),
       created_at: user_model.created_at.to_rfc3339(),
       updated_at: user_model.updated_at.to_rfc3339(),
-    };
+    }
+  }
+
+  async fn build_user_from_model(
+    user_model: &users::Model,
+    db: &DatabaseConnection,
+  ) -> DatabaseResult<UserCompleteProfile> {
+    let profile = Self::profile_from_model(user_model);
 
-    
     let shopper_profile = Self::get_shopper_profile(db, user_model.id).await?;
     let expert_profile = Self::get_expert_profile(db, user_model.id).await?;
 
@@ -262,7 +326,7 @@ impl UserService {
     })
   }
 
-  async fn get_shopper_profile(
+  pub async fn get_shopper_profile(
     db: &DatabaseConnection,
     user_id: Uuid,
   ) -> DatabaseResult<Option<ShopperProfile>> {
@@ -271,28 +335,54 @@ impl UserService {
       .one(db)
       .await?;
 
-    if let Some(data) = shopper_data {
-      Ok(Some(ShopperProfile {
-        preferences: ShopperPreferences {
-          categories: serde_json::from_value(data.categories.clone()).unwrap_or_default(),
-          price_range: PriceRange {
-            min: data.price_range_min.to_string().parse().unwrap_or(0.0),
-            max: data.price_range_max.to_string().parse().unwrap_or(1000.0),
-          },
-          preferred_experts: serde_json::from_value(data.preferred_experts.clone())
-            .unwrap_or_default(),
-        },
-        consultation_history: ConsultationHistory {
-          total_sessions: data.total_sessions,
-          total_spent: data.total_spent.to_string().parse().unwrap_or(0.0),
-          average_rating: data.average_rating.to_string().parse().unwrap_or(0.0),
+    match shopper_data {
+      Some(data) => Ok(Some(Self::shopper_with_saved_experts(db, data).await?)),
+      None => Ok(None),
+    }
+  }
+
+  async fn shopper_with_saved_experts(
+    db: &DatabaseConnection,
+    data: shopper_profiles::Model,
+  ) -> DatabaseResult<ShopperProfile> {
+    let (saved, preferred) = SavedExpertService::ids_for_profile(db, data.id).await?;
+    Ok(Self::shopper_from_model(data, saved, preferred))
+  }
+
+  fn shopper_from_model(
+    data: shopper_profiles::Model,
+    saved_experts: Vec<Uuid>,
+    preferred_experts: Vec<Uuid>,
+  ) -> ShopperProfile {
+    let ids = |ids: Vec<Uuid>| ids.into_iter().map(|id| id.to_string()).collect();
+
+    ShopperProfile {
+      id: data.id,
+      user_id: data.user_id,
+      preferences: ShopperPreferences {
+        categories: serde_json::from_value(data.categories).unwrap_or_default(),
+        price_range: PriceRange {
+          min: data.price_range_min.to_string().parse().unwrap_or(0.0),
+          max: data.price_range_max.to_string().parse().unwrap_or(1000.0),
         },
-        saved_experts: serde_json::from_value(data.saved_experts.clone()).unwrap_or_default(),
-        interests: serde_json::from_value(data.interests.clone()).unwrap_or_default(),
-      }))
-    } else {
-      Ok(None)
+        preferred_experts: ids(preferred_experts),
+      },
+      consultation_history: ConsultationHistory {
+        total_sessions: data.total_sessions,
+        total_spent: data.total_spent.to_string().parse().unwrap_or(0.0),
+        average_rating: data.average_rating.to_string().parse().unwrap_or(0.0),
+      },
+      saved_experts: ids(saved_experts),
+      interests: serde_json::from_value(data.interests).unwrap_or_default(),
+    }
+  }
+
+  /// Prices must be non-negative and the range must not be inverted
+  pub fn validate_price_range(min: f64, max: f64) -> DatabaseResult<()> {
+    if !min.is_finite() || !max.is_finite() || min < 0.0 || min > max {
+      return Err(DatabaseError::InvalidPriceRange);
     }
+    Ok(())
   }
 
   pub async fn get_expert_profile(
@@ -331,17 +421,7 @@ impl UserService {
         }
       };
 
-      let mut schedule = HashMap::new();
-      for avail in availability_data {
-        schedule.insert(
-          avail.day_of_week,
-          ScheduleSlot {
-            start: avail.start_time.to_string(),
-            end: avail.end_time.to_string(),
-            available: avail.available,
-          },
-        );
-      }
+      let allowed_durations = PricingService::allowed_durations(&data);
 
       Ok(Some(ExpertProfile {
         id: data.id,
@@ -353,10 +433,9 @@ impl UserService {
         total_consultations: data.total_consultations,
         is_verified: data.is_verified,
         is_online: data.is_online,
-        availability: Availability {
-          schedule,
-          timezone: "UTC".to_string(),
-        },
+        pricing_model: data.pricing_model,
+        allowed_durations,
+        availability: Self::availability_from_rules(availability_data),
         profile_image_url: Some(data.profile_image_url),
         stats,
       }))
@@ -365,6 +444,46 @@ impl UserService {
     }
   }
 
+  /// Shape stored `expert_availability` rows for the API; the timezone is shared by every row
+  pub fn availability_from_rules(mut rules: Vec<expert_availability::Model>) -> Availability {
+    rules.sort_by_key(|rule| {
+      (
+        AvailabilityService::parse_weekday(&rule.day_of_week).map(|day| day.num_days_from_monday()),
+        !rule.available,
+        rule.start_time,
+      )
+    });
+
+    let timezone = rules
+      .first()
+      .map(|rule| rule.timezone.clone())
+      .unwrap_or_else(|| "UTC".to_string());
+
+    let mut schedule = HashMap::new();
+    let mut windows = Vec::with_capacity(rules.len());
+    for rule in rules {
+      schedule
+        .entry(rule.day_of_week.clone())
+        .or_insert_with(|| ScheduleSlot {
+          start: rule.start_time.to_string(),
+          end: rule.end_time.to_string(),
+          available: rule.available,
+        });
+      windows.push(AvailabilityWindow {
+        day_of_week: rule.day_of_week,
+        start: rule.start_time.to_string(),
+        end: rule.end_time.to_string(),
+        available: rule.available,
+      });
+    }
+
+    Availability {
+      schedule,
+      timezone,
+      windows,
+    }
+  }
+
   // Profile management methods
   pub async fn has_shopper_profile(db: &DatabaseConnection, user_id: Uuid) -> DatabaseResult<bool> {
     let shopper_profile = ShopperProfiles::find()
@@ -382,13 +501,13 @@ impl UserService {
     Ok(expert_profile.is_some())
   }
 
-  pub async fn get_user_roles(
-    db: &DatabaseConnection,
-    user_id: Uuid,
-  ) -> DatabaseResult<(bool, bool)> {
-    let can_shop = Self::has_shopper_profile(db, user_id).await?;
-    let can_expert = Self::has_expert_profile(db, user_id).await?;
-    Ok((can_shop, can_expert))
+  /// Roles of a user whose row is already loaded
+  pub async fn roles_of(db: &DatabaseConnection, user: &users::Model) -> DatabaseResult<Roles> {
+    Ok(Roles {
+      shopper: Self::has_shopper_profile(db, user.id).await?,
+      expert: Self::has_expert_profile(db, user.id).await?,
+      admin: user.is_admin,
+    })
   }
 
   pub async fn create_shopper_profile_only(
@@ -398,15 +517,19 @@ impl UserService {
     price_range_min: f64,
     price_range_max: f64,
     interests: Vec<String>,
-  ) -> DatabaseResult<()> {
+  ) -> DatabaseResult<ShopperProfile> {
+    Self::validate_price_range(price_range_min, price_range_max)?;
+
+    if Self::has_shopper_profile(db, user_id).await? {
+      return Err(DatabaseError::ShopperProfileExists);
+    }
+
     let shopper_profile = shopper_profiles::ActiveModel {
       id: Set(Uuid::new_v4()),
       user_id: Set(user_id),
       categories: Set(json!(categories)),
       price_range_min: Set(rust_decimal::Decimal::try_from(price_range_min).unwrap_or_default()),
       price_range_max: Set(rust_decimal::Decimal::try_from(price_range_max).unwrap_or_default()),
-      preferred_experts: Set(json!([])),
-      saved_experts: Set(json!([])),
       interests: Set(json!(interests)),
       total_sessions: Set(0),
       total_spent: Set(rust_decimal::Decimal::from(0)),
@@ -414,33 +537,76 @@ impl UserService {
       created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
     };
-    shopper_profile.insert(db).await?;
-    Ok(())
+
+    // A concurrent request can still win the race; uq_shopper_profiles_user_id catches it
+    let model = shopper_profile
+      .insert(db)
+      .await
+      .map_err(|err| match err.sql_err() {
+        Some(SqlErr::UniqueConstraintViolation(_)) => DatabaseError::ShopperProfileExists,
+        _ => err.into(),
+      })?;
+
+    Ok(Self::shopper_from_model(model, vec![], vec![]))
   }
 
+  /// Create the expert profile and stats. `held_bio` is why the content filter holds the bio, if
+  /// it does; the bio is then saved hidden in the same transaction, as are `pricing` and the
+  /// already resolved `category_ids`.
+  #[allow(clippy::too_many_arguments)]
   pub async fn create_expert_profile_only(
     db: &DatabaseConnection,
+    prior: &RatingPrior,
     user_id: Uuid,
     specialization: String,
     bio: String,
+    held_bio: Option<&str>,
     session_rate: f64,
     profile_image_url: Option<String>,
+    pricing: PricingUpdate,
+    category_ids: Option<&[Uuid]>,
   ) -> DatabaseResult<()> {
+    let txn = db.begin().await?;
+
     let expert_profile = expert_profiles::ActiveModel {
       id: Set(Uuid::new_v4()),
       user_id: Set(user_id),
       specialization: Set(specialization),
       bio: Set(bio),
+      bio_hidden_at: Set(None),
+      bio_held: Set(false),
       session_rate: Set(rust_decimal::Decimal::try_from(session_rate).unwrap_or_default()),
       rating: Set(rust_decimal::Decimal::from(0)),
+      rating_count: Set(0),
+      rating_sum: Set(0),
+      rating_score: Set(RatingAggregate::default().score(prior)),
       total_consultations: Set(0),
       is_verified: Set(false),
       is_online: Set(false),
       profile_image_url: Set(profile_image_url.unwrap_or_default()),
+      pricing_model: Set(pricing.pricing_model.unwrap_or(PricingModel::Flat)),
+      allowed_durations: Set(json!(pricing
+        .allowed_durations
+        .unwrap_or_else(|| SUPPORTED_DURATIONS.to_vec()))),
       created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
     };
-    expert_profile.insert(db).await?;
+    let expert_profile = expert_profile.insert(&txn).await?;
+
+    if let Some(category_ids) = category_ids {
+      CategoryService::set_expert_categories(&txn, expert_profile.id, category_ids).await?;
+    }
+
+    if let Some(reason) = held_bio {
+      ModerationService::hold(
+        &txn,
+        prior,
+        ContentType::ExpertBio,
+        expert_profile.id,
+        reason,
+      )
+      .await?;
+    }
 
     // Also create expert stats
     let expert_stats = expert_stats::ActiveModel {
@@ -453,31 +619,48 @@ impl UserService {
       created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
       updated_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
     };
-    expert_stats.insert(db).await?;
+    expert_stats.insert(&txn).await?;
 
+    txn.commit().await?;
     Ok(())
   }
 
+  /// Update the expert profile. `held_bio` is why the content filter holds the new bio, if it
+  /// does; a rewritten bio is screened in the same transaction as the save, see
+  /// `ModerationService::rescreen_bio`. `pricing` and the already resolved `category_ids` are
+  /// saved in that transaction as well.
+  #[allow(clippy::too_many_arguments)]
   pub async fn update_expert_profile(
     db: &DatabaseConnection,
+    prior: &RatingPrior,
     user_id: Uuid,
     specialization: Option<String>,
     bio: Option<String>,
+    held_bio: Option<&str>,
     session_rate: Option<f64>,
     profile_image_url: Option<String>,
     is_online: Option<bool>,
+    pricing: PricingUpdate,
+    category_ids: Option<&[Uuid]>,
   ) -> DatabaseResult<()> {
+    let txn = db.begin().await?;
+
     let expert_profile = expert_profiles::Entity::find()
       .filter(expert_profiles::Column::UserId.eq(user_id))
-      .one(db)
+      .lock_exclusive()
+      .one(&txn)
       .await?;
 
     if let Some(profile) = expert_profile {
+      let previous = profile.clone();
       let mut active_model: expert_profiles::ActiveModel = profile.into();
 
       if let Some(spec) = specialization {
         active_model.specialization = Set(spec);
       }
+      let bio_changed = bio
+        .as_ref()
+        .is_some_and(|bio_text| *bio_text != previous.bio);
       if let Some(bio_text) = bio {
         active_model.bio = Set(bio_text);
       }
@@ -490,11 +673,25 @@ impl UserService {
       if let Some(online) = is_online {
         active_model.is_online = Set(online);
       }
+      if let Some(model) = pricing.pricing_model {
+        active_model.pricing_model = Set(model);
+      }
+      if let Some(durations) = pricing.allowed_durations {
+        active_model.allowed_durations = Set(json!(durations));
+      }
 
       active_model.updated_at = Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
-      active_model.update(db).await?;
+      active_model.update(&txn).await?;
+
+      if bio_changed {
+        ModerationService::rescreen_bio(&txn, prior, &previous, held_bio).await?;
+      }
+      if let Some(category_ids) = category_ids {
+        CategoryService::set_expert_categories(&txn, previous.id, category_ids).await?;
+      }
     }
 
+    txn.commit().await?;
     Ok(())
   }
 
@@ -519,14 +716,7 @@ impl UserService {
       active_model.updated_at = Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
       let updated_user = active_model.update(db).await?;
 
-      Ok(UserProfile {
-        id: updated_user.id.to_string(),
-        wallet_address: updated_user.wallet_address,
-        name: updated_user.name,
-        email: Some(updated_user.email),
-        created_at: updated_user.created_at.to_rfc3339(),
-        updated_at: updated_user.updated_at.to_rfc3339(),
-      })
+      Ok(Self::profile_from_model(&updated_user))
     } else {
       Err(crate::database::DatabaseError::UserNotFound)
     }
@@ -539,32 +729,61 @@ impl UserService {
     price_range_min: Option<f64>,
     price_range_max: Option<f64>,
     interests: Option<Vec<String>>,
-  ) -> DatabaseResult<()> {
-    let shopper_profile = shopper_profiles::Entity::find()
+  ) -> DatabaseResult<ShopperProfile> {
+    let profile = shopper_profiles::Entity::find()
       .filter(shopper_profiles::Column::UserId.eq(user_id))
       .one(db)
-      .await?;
+      .await?
+      .ok_or(DatabaseError::ShopperProfileNotFound)?;
 
-    if let Some(profile) = shopper_profile {
-      let mut active_model: shopper_profiles::ActiveModel = profile.into();
+    // Validate the range as it will be stored, so a lone min or max can't invert it
+    let stored = |value: rust_decimal::Decimal| value.to_string().parse().unwrap_or(0.0);
+    let min = price_range_min.unwrap_or_else(|| stored(profile.price_range_min));
+    let max = price_range_max.unwrap_or_else(|| stored(profile.price_range_max));
+    Self::validate_price_range(min, max)?;
 
-      if let Some(cats) = categories {
-        active_model.categories = Set(serde_json::json!(cats));
-      }
-      if let Some(min) = price_range_min {
-        active_model.price_range_min = Set(rust_decimal::Decimal::try_from(min).unwrap_or_default());
-      }
-      if let Some(max) = price_range_max {
-        active_model.price_range_max = Set(rust_decimal::Decimal::try_from(max).unwrap_or_default());
-      }
-      if let Some(int) = interests {
-        active_model.interests = Set(serde_json::json!(int));
-      }
+    let mut active_model: shopper_profiles::ActiveModel = profile.into();
 
-      active_model.updated_at = Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
-      active_model.update(db).await?;
+    if let Some(cats) = categories {
+      active_model.categories = Set(serde_json::json!(cats));
+    }
+    if let Some(min) = price_range_min {
+      active_model.price_range_min = Set(rust_decimal::Decimal::try_from(min).unwrap_or_default());
+    }
+    if let Some(max) = price_range_max {
+      active_model.price_range_max = Set(rust_decimal::Decimal::try_from(max).unwrap_or_default());
+    }
+    if let Some(int) = interests {
+      active_model.interests = Set(serde_json::json!(int));
     }
 
-    Ok(())
+    active_model.updated_at = Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()));
+    let updated = active_model.update(db).await?;
+
+    Self::shopper_with_saved_experts(db, updated).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate_price_range() {
+    assert!(UserService::validate_price_range(0.0, 0.0).is_ok());
+    assert!(UserService::validate_price_range(10.0, 500.0).is_ok());
+
+    assert!(matches!(
+      UserService::validate_price_range(500.0, 10.0),
+      Err(DatabaseError::InvalidPriceRange)
+    ));
+    assert!(matches!(
+      UserService::validate_price_range(-5.0, 10.0),
+      Err(DatabaseError::InvalidPriceRange)
+    ));
+    assert!(matches!(
+      UserService::validate_price_range(0.0, f64::NAN),
+      Err(DatabaseError::InvalidPriceRange)
+    ));
   }
 }